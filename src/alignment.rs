@@ -0,0 +1,261 @@
+use crate::{metrics::euclidean_dist, SwarmPos};
+
+/// Which `experiment_len` ticks of a longer recording are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Alignment {
+    /// keep the first ticks, drop the end
+    Start,
+    /// keep the last ticks, drop the beginning
+    End,
+    /// drop frames symmetrically from both ends, one more from the front if the difference is odd
+    #[default]
+    Center,
+    /// start at the last still tick before the first tick where the swarm's max shift exceeds `threshold`
+    MotionOnset { threshold: f64 },
+    /// start at an explicit tick
+    Offset { start: usize },
+}
+
+/// The ticks `start..start + len` of the recording that were kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlignWindow {
+    pub start: usize,
+    pub len: usize,
+    /// length of the recording before cropping
+    pub total: usize,
+}
+
+impl std::fmt::Display for AlignWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}..{}] of {}",
+            self.start,
+            self.start + self.len,
+            self.total
+        )
+    }
+}
+
+impl Alignment {
+    pub fn name(&self) -> String {
+        match self {
+            Alignment::Start => "start".to_string(),
+            Alignment::End => "end".to_string(),
+            Alignment::Center => "center".to_string(),
+            Alignment::MotionOnset { threshold } => format!("onset:{threshold}"),
+            Alignment::Offset { start } => format!("offset:{start}"),
+        }
+    }
+
+    /// parses the format returned by `name`, e.g. `center`, `onset:0.005` or `offset:40`
+    pub fn parse(name: &str) -> Option<Self> {
+        let (kind, arg) = match name.trim().split_once(":") {
+            Some((kind, arg)) => (kind, Some(arg.trim())),
+            None => (name.trim(), None),
+        };
+
+        return match (kind, arg) {
+            ("start", None) => Some(Alignment::Start),
+            ("end", None) => Some(Alignment::End),
+            ("center", None) => Some(Alignment::Center),
+            ("onset", Some(arg)) => Some(Alignment::MotionOnset {
+                threshold: arg.parse().ok()?,
+            }),
+            ("offset", Some(arg)) => Some(Alignment::Offset {
+                start: arg.parse().ok()?,
+            }),
+            _ => None,
+        };
+    }
+
+    /// chooses the window of `experiment_len` ticks
+    pub fn window(
+        &self,
+        bot_pos: &[SwarmPos],
+        experiment_len: usize,
+    ) -> Result<AlignWindow, String> {
+        let total = bot_pos.len();
+        if total < experiment_len {
+            return Err(format!(
+                "recording has {total} ticks but {experiment_len} are needed"
+            ));
+        }
+        let delta = total - experiment_len;
+
+        let start = match self {
+            Alignment::Start => 0,
+            Alignment::End => delta,
+            Alignment::Center => delta / 2 + delta % 2,
+            Alignment::MotionOnset { threshold } => {
+                let onset = motion_onset(bot_pos, *threshold).unwrap_or(0);
+                usize::min(onset, delta)
+            }
+            Alignment::Offset { start } => {
+                if *start > delta {
+                    return Err(format!(
+                        "offset {start} leaves less than {experiment_len} of {total} ticks"
+                    ));
+                }
+                *start
+            }
+        };
+
+        return Ok(AlignWindow {
+            start,
+            len: experiment_len,
+            total,
+        });
+    }
+
+    pub fn align(
+        &self,
+        bot_pos: &[SwarmPos],
+        experiment_len: usize,
+    ) -> Result<(Vec<SwarmPos>, AlignWindow), String> {
+        let window = self.window(bot_pos, experiment_len)?;
+        let aligned = bot_pos[window.start..window.start + window.len].to_vec();
+        return Ok((aligned, window));
+    }
+
+    /// Crops a simulation like `align`. Simulations of exactly `experiment_len` ticks are kept
+    /// as they are and an offset beyond the extra ticks of a longer one is clamped, so the
+    /// offset meant for the real recordings only fails on a simulation that ended early.
+    pub fn align_sim(
+        &self,
+        sim_pos: &[SwarmPos],
+        experiment_len: usize,
+    ) -> Result<Vec<SwarmPos>, String> {
+        if sim_pos.len() < experiment_len {
+            return Err(format!(
+                "simulation has {} ticks but {experiment_len} are needed",
+                sim_pos.len()
+            ));
+        }
+        let delta = sim_pos.len() - experiment_len;
+        if delta == 0 {
+            return Ok(sim_pos.to_vec());
+        }
+        let alignment = match *self {
+            Alignment::Offset { start } => Alignment::Offset {
+                start: start.min(delta),
+            },
+            alignment => alignment,
+        };
+        let (sim_pos, _) = alignment.align(sim_pos, experiment_len)?;
+        return Ok(sim_pos);
+    }
+}
+
+/// the tick before the first tick where any robot moved more than `threshold` since the previous tick
+pub fn motion_onset(bot_pos: &[SwarmPos], threshold: f64) -> Option<usize> {
+    for t in 1..bot_pos.len() {
+        let max_shift = bot_pos[t]
+            .iter()
+            .zip(bot_pos[t - 1].iter())
+            .map(|(cur, pre)| euclidean_dist(cur, pre))
+            .fold(0.0, f64::max);
+        if max_shift > threshold {
+            return Some(t - 1);
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SWARM_SIZE;
+
+    /// every robot at (t, 0) in tick t
+    fn ticks(len: usize) -> Vec<SwarmPos> {
+        return (0..len).map(|t| [(t as f64, 0.0); SWARM_SIZE]).collect();
+    }
+
+    #[test]
+    fn offset_fails_for_recordings_too_short() {
+        assert!(Alignment::Offset { start: 5 }
+            .window(&ticks(12), 10)
+            .is_err());
+        assert_eq!(
+            Alignment::Offset { start: 2 }.window(&ticks(12), 10),
+            Ok(AlignWindow {
+                start: 2,
+                len: 10,
+                total: 12
+            })
+        );
+    }
+
+    #[test]
+    fn start_and_end_keep_one_end() {
+        let pos = ticks(13);
+        assert_eq!(Alignment::Start.align(&pos, 10).unwrap().0, pos[0..10]);
+        assert_eq!(Alignment::End.align(&pos, 10).unwrap().0, pos[3..13]);
+    }
+
+    #[test]
+    fn center_drops_the_odd_tick_at_the_front() {
+        // get_real_bot_data crops the real run like this since the first version
+        let pos = ticks(13);
+        let (aligned, window) = Alignment::Center.align(&pos, 10).unwrap();
+        assert_eq!(window.start, 3 / 2 + 3 % 2);
+        assert_eq!(aligned, pos[2..12]);
+
+        let pos = ticks(14);
+        assert_eq!(Alignment::Center.align(&pos, 10).unwrap().0, pos[2..12]);
+    }
+
+    #[test]
+    fn motion_onset_starts_at_the_last_still_tick() {
+        // the swarm stands still for 5 ticks and then moves 1 per tick
+        let pos = (0..16usize)
+            .map(|t| [(t.saturating_sub(4) as f64, 0.0); SWARM_SIZE])
+            .collect::<Vec<SwarmPos>>();
+        assert_eq!(motion_onset(&pos, 0.5), Some(4));
+        assert_eq!(motion_onset(&pos, 1.0), None);
+
+        let onset = Alignment::MotionOnset { threshold: 0.5 };
+        assert_eq!(onset.align(&pos, 10).unwrap().0, pos[4..14]);
+        // the onset is clamped to the extra ticks
+        assert_eq!(onset.window(&pos, 14).unwrap().start, 2);
+        // a swarm that never moves starts at the first tick
+        let never = Alignment::MotionOnset { threshold: 1.0 };
+        assert_eq!(never.window(&pos, 10).unwrap().start, 0);
+        assert_eq!(motion_onset(&[[(0.0, 0.0); SWARM_SIZE]; 5], 0.0), None);
+    }
+
+    #[test]
+    fn simulation_that_ended_early_fails() {
+        for alignment in [
+            Alignment::Start,
+            Alignment::Center,
+            Alignment::Offset { start: 0 },
+        ] {
+            assert!(alignment.align_sim(&ticks(9), 10).is_err());
+        }
+    }
+
+    #[test]
+    fn simulation_of_the_expected_length_is_kept() {
+        let pos = ticks(10);
+        assert_eq!(
+            Alignment::Offset { start: 5 }.align_sim(&pos, 10),
+            Ok(pos.clone())
+        );
+        assert_eq!(Alignment::End.align_sim(&pos, 10), Ok(pos.clone()));
+    }
+
+    #[test]
+    fn offset_is_clamped_for_longer_simulations() {
+        let pos = ticks(12);
+        assert_eq!(
+            Alignment::Offset { start: 5 }.align_sim(&pos, 10).unwrap(),
+            pos[2..12]
+        );
+        assert_eq!(
+            Alignment::Offset { start: 1 }.align_sim(&pos, 10).unwrap(),
+            pos[1..11]
+        );
+    }
+}
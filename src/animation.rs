@@ -0,0 +1,334 @@
+use crate::{
+    metrics::{center_of_mass, euclidean_dist},
+    report::{escape, COLORS},
+    SwarmPos,
+};
+
+/// Rectangle the robots move in, in the coordinates of the trajectories.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arena {
+    pub min: (f64, f64),
+    pub max: (f64, f64),
+}
+
+impl Arena {
+    /// parses `<width>x<height>`, centered on the origin like the ARGoS arenas
+    pub fn parse(name: &str) -> Option<Self> {
+        let (width, height) = name.trim().split_once("x")?;
+        let width = width.trim().parse::<f64>().ok()?;
+        let height = height.trim().parse::<f64>().ok()?;
+        if !(width > 0.0 && height > 0.0) {
+            return None;
+        }
+        return Some(Self {
+            min: (-width / 2.0, -height / 2.0),
+            max: (width / 2.0, height / 2.0),
+        });
+    }
+
+    /// smallest rectangle around every finite position
+    pub fn around(trajectories: &[&[SwarmPos]]) -> Self {
+        let points = trajectories
+            .iter()
+            .flat_map(|x| x.iter().flatten())
+            .filter(|(x, y)| x.is_finite() && y.is_finite());
+        let (min, max) = points.fold(
+            (
+                (f64::INFINITY, f64::INFINITY),
+                (f64::NEG_INFINITY, f64::NEG_INFINITY),
+            ),
+            |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+        );
+        if !(min.0 <= max.0 && min.1 <= max.1) {
+            return Self {
+                min: (-1.0, -1.0),
+                max: (1.0, 1.0),
+            };
+        }
+        return Self { min, max };
+    }
+}
+
+/// What is drawn on top of the robots and how fast the animation plays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationOptions {
+    /// ticks of the path drawn behind every robot, 0 disables the trails
+    pub trail: usize,
+    pub center_of_mass: bool,
+    /// circle around the center of mass through the robot farthest from it
+    pub max_radius: bool,
+    /// line from every robot to its nearest neighbour
+    pub neighbor_links: bool,
+    /// `None` fits the arena around all trajectories
+    pub arena: Option<Arena>,
+    /// only every `stride`-th tick is a key frame
+    pub stride: usize,
+    /// ticks played per second
+    pub speed: f64,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            trail: 20,
+            center_of_mass: true,
+            max_radius: true,
+            neighbor_links: false,
+            arena: None,
+            stride: 2,
+            speed: 10.0,
+        }
+    }
+}
+
+/// animated SVG with one panel per named trajectory, side by side on the same scale, the
+/// animation loops over the ticks of the longest trajectory
+pub fn animate_svg(panels: &[(&str, &[SwarmPos])], options: AnimationOptions) -> String {
+    let stride = options.stride.max(1);
+    let arena = options
+        .arena
+        .unwrap_or_else(|| Arena::around(&panels.iter().map(|x| x.1).collect::<Vec<_>>()));
+    let len = panels.iter().map(|x| x.1.len()).max().unwrap_or(0);
+    let ticks = (0..len).step_by(stride).collect::<Vec<usize>>();
+    let dur = len as f64 / options.speed;
+
+    let (panel, margin, top) = (400.0, 10.0, 24.0);
+    let width = panels.len() as f64 * (panel + margin) + margin;
+    let height = panel + top + margin + 10.0;
+    let extent = f64::max(arena.max.0 - arena.min.0, arena.max.1 - arena.min.1);
+    let scale = panel / extent;
+    // the arena is centered in its square panel
+    let pad = (
+        (panel - (arena.max.0 - arena.min.0) * scale) / 2.0,
+        (panel - (arena.max.1 - arena.min.1) * scale) / 2.0,
+    );
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n<rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\n"
+    );
+    for (k, (name, pos)) in panels.iter().enumerate() {
+        if pos.is_empty() {
+            continue;
+        }
+        let left = margin + k as f64 * (panel + margin);
+        let to_svg = |(x, y): (f64, f64)| {
+            (
+                left + pad.0 + (x - arena.min.0) * scale,
+                top + panel - pad.1 - (y - arena.min.1) * scale,
+            )
+        };
+        // a trajectory shorter than the longest one stays at its last frame
+        let frame = |t: usize| &pos[usize::min(t, pos.len() - 1)];
+
+        svg.push_str(&format!(
+            "<text x=\"{:.1}\" y=\"16\" font-family=\"sans-serif\" font-size=\"13\" text-anchor=\"middle\">{}</text>\n",
+            left + panel / 2.0,
+            escape(name)
+        ));
+        let (x0, y0) = to_svg((arena.min.0, arena.max.1));
+        svg.push_str(&format!(
+            "<rect x=\"{x0:.1}\" y=\"{y0:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"none\" stroke=\"#888\"/>\n",
+            (arena.max.0 - arena.min.0) * scale,
+            (arena.max.1 - arena.min.1) * scale
+        ));
+
+        if options.trail > 0 {
+            for robot in 0..pos[0].len() {
+                let values = ticks
+                    .iter()
+                    .map(|&t| {
+                        (0..options.trail)
+                            .map(|i| to_svg(frame(t.saturating_sub(options.trail - 1 - i))[robot]))
+                            .map(|(x, y)| format!("{x:.1},{y:.1}"))
+                            .collect::<Vec<String>>()
+                            .join(" ")
+                    })
+                    .collect::<Vec<String>>();
+                svg.push_str(&format!(
+                    "<polyline fill=\"none\" stroke=\"{}\" stroke-opacity=\"0.5\" points=\"{}\">{}</polyline>\n",
+                    COLORS[robot % COLORS.len()],
+                    values[0],
+                    animate("points", &values, dur)
+                ));
+            }
+        }
+
+        if options.neighbor_links {
+            for robot in 0..pos[0].len() {
+                let (mut x1, mut y1, mut x2, mut y2) = (vec![], vec![], vec![], vec![]);
+                for &t in &ticks {
+                    let swarm_pos = frame(t);
+                    let nearest = (0..swarm_pos.len())
+                        .filter(|&j| j != robot)
+                        .min_by(|&a, &b| {
+                            let da = euclidean_dist(&swarm_pos[robot], &swarm_pos[a]);
+                            let db = euclidean_dist(&swarm_pos[robot], &swarm_pos[b]);
+                            da.total_cmp(&db)
+                        })
+                        .unwrap_or(robot);
+                    let (from, to) = (to_svg(swarm_pos[robot]), to_svg(swarm_pos[nearest]));
+                    x1.push(format!("{:.1}", from.0));
+                    y1.push(format!("{:.1}", from.1));
+                    x2.push(format!("{:.1}", to.0));
+                    y2.push(format!("{:.1}", to.1));
+                }
+                svg.push_str(&format!(
+                    "<line stroke=\"#999\" stroke-width=\"0.8\">{}{}{}{}</line>\n",
+                    animate("x1", &x1, dur),
+                    animate("y1", &y1, dur),
+                    animate("x2", &x2, dur),
+                    animate("y2", &y2, dur)
+                ));
+            }
+        }
+
+        if options.center_of_mass || options.max_radius {
+            let (mut cx, mut cy, mut r) = (vec![], vec![], vec![]);
+            for &t in &ticks {
+                let com = center_of_mass(frame(t));
+                let radius = frame(t)
+                    .iter()
+                    .map(|p| euclidean_dist(p, &com))
+                    .fold(0.0, f64::max);
+                let (x, y) = to_svg(com);
+                cx.push(format!("{x:.1}"));
+                cy.push(format!("{y:.1}"));
+                r.push(format!("{:.1}", radius * scale));
+            }
+            if options.max_radius {
+                svg.push_str(&format!(
+                    "<circle fill=\"none\" stroke=\"#d62728\" stroke-dasharray=\"4 3\">{}{}{}</circle>\n",
+                    animate("cx", &cx, dur),
+                    animate("cy", &cy, dur),
+                    animate("r", &r, dur)
+                ));
+            }
+            if options.center_of_mass {
+                svg.push_str(&format!(
+                    "<circle r=\"4\" fill=\"#000\">{}{}</circle>\n",
+                    animate("cx", &cx, dur),
+                    animate("cy", &cy, dur)
+                ));
+            }
+        }
+
+        for robot in 0..pos[0].len() {
+            let (mut cx, mut cy) = (vec![], vec![]);
+            for &t in &ticks {
+                let (x, y) = to_svg(frame(t)[robot]);
+                cx.push(format!("{x:.1}"));
+                cy.push(format!("{y:.1}"));
+            }
+            svg.push_str(&format!(
+                "<circle r=\"5\" fill=\"{}\" stroke=\"#000\" stroke-width=\"0.5\">{}{}</circle>\n",
+                COLORS[robot % COLORS.len()],
+                animate("cx", &cx, dur),
+                animate("cy", &cy, dur)
+            ));
+        }
+    }
+
+    // progress of the loop over the ticks
+    svg.push_str(&format!(
+        "<rect x=\"{margin}\" y=\"{:.1}\" height=\"4\" fill=\"#888\"><animate attributeName=\"width\" values=\"0;{:.1}\" dur=\"{dur:.2}s\" repeatCount=\"indefinite\"/></rect>\n",
+        height - 8.0,
+        width - 2.0 * margin
+    ));
+    svg.push_str("</svg>\n");
+    return svg;
+}
+
+fn animate(attribute: &str, values: &[String], dur: f64) -> String {
+    return format!(
+        "<animate attributeName=\"{attribute}\" values=\"{}\" dur=\"{dur:.2}s\" repeatCount=\"indefinite\"/>",
+        values.join(";")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SWARM_SIZE;
+
+    fn line(len: usize) -> Vec<SwarmPos> {
+        return (0..len)
+            .map(|t| {
+                let mut frame = [(0.0, 0.0); SWARM_SIZE];
+                for (i, pos) in frame.iter_mut().enumerate() {
+                    *pos = (t as f64, i as f64 / 10.0);
+                }
+                frame
+            })
+            .collect();
+    }
+
+    #[test]
+    fn arena_names() {
+        assert_eq!(
+            Arena::parse("4x2"),
+            Some(Arena {
+                min: (-2.0, -1.0),
+                max: (2.0, 1.0)
+            })
+        );
+        assert_eq!(Arena::parse("4"), None);
+        assert_eq!(Arena::parse("0x2"), None);
+        assert_eq!(Arena::parse("ax2"), None);
+    }
+
+    #[test]
+    fn arena_around_finite_positions() {
+        let mut pos = line(3);
+        pos[1][0] = (f64::NAN, 100.0);
+        let arena = Arena::around(&[&pos, &line(5)]);
+        assert_eq!(arena.min, (0.0, 0.0));
+        assert_eq!(arena.max, (4.0, 1.4));
+
+        let default = Arena::around(&[]);
+        assert_eq!(default.min, (-1.0, -1.0));
+        assert_eq!(default.max, (1.0, 1.0));
+    }
+
+    #[test]
+    fn every_robot_moves_through_the_key_frames() {
+        let long = line(10);
+        let short = line(3);
+        let options = AnimationOptions {
+            trail: 0,
+            center_of_mass: false,
+            max_radius: false,
+            arena: Some(Arena {
+                min: (0.0, 0.0),
+                max: (9.0, 9.0),
+            }),
+            ..Default::default()
+        };
+        let svg = animate_svg(&[("long", &long), ("a & b", &short)], options);
+
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert!(svg.contains(">a &amp; b</text>"));
+        assert_eq!(svg.matches("<circle").count(), 2 * SWARM_SIZE);
+        assert_eq!(svg.matches("<polyline").count(), 0);
+        // ticks 0, 2, 4, 6 and 8 at 10 ticks per second over 10 ticks, 400 / 9 px per unit
+        assert!(svg.contains(
+            "<animate attributeName=\"cx\" values=\"10.0;98.9;187.8;276.7;365.6\" dur=\"1.00s\""
+        ));
+        // the short trajectory stays at its last frame
+        assert!(svg.contains(
+            "<animate attributeName=\"cx\" values=\"420.0;508.9;508.9;508.9;508.9\" dur=\"1.00s\""
+        ));
+    }
+
+    #[test]
+    fn overlays_are_drawn_when_enabled() {
+        let pos = line(4);
+        let options = AnimationOptions {
+            neighbor_links: true,
+            ..Default::default()
+        };
+        let svg = animate_svg(&[("sim", &pos)], options);
+        assert_eq!(svg.matches("<polyline").count(), SWARM_SIZE);
+        assert_eq!(svg.matches("<line").count(), SWARM_SIZE);
+        assert_eq!(svg.matches("<circle").count(), SWARM_SIZE + 2);
+    }
+}
@@ -0,0 +1,270 @@
+use crate::fsm::{Behaviour, Condition, Flags};
+use rand::Rng;
+
+/// maximum number of children of the root AutoMoDe-Maple samples
+pub const MAX_ROOT_CHILDREN: usize = 4;
+/// children of a node are numbered with a single digit in the flags
+pub const MAX_CHILDREN: usize = 10;
+
+/// Node of a behaviour tree, `--n<path>` in the AutoMoDe-Maple flags where the path lists the
+/// child indices from the root, e.g. `--n01` for the second child of the first child.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// ticks the children in order until one succeeds
+    Selector(Vec<Node>),
+    /// ticks the children in order until one fails
+    Sequence(Vec<Node>),
+    /// like `Sequence` but resumes at the child that was running in the last tick
+    SequenceStar(Vec<Node>),
+    /// succeeds if the condition holds, `--c<path>` with its parameters
+    Condition(Condition),
+    /// runs the behaviour, `--a<path>` with its parameter
+    Action(Behaviour),
+}
+
+/// Behaviour tree as configured with `--bt-config`, the root is a control node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BehaviourTree {
+    pub root: Node,
+}
+
+impl Node {
+    /// node type of the flags: 0 selector, 1 sequence, 3 sequence*, 5 action and 6 condition
+    pub fn type_id(&self) -> u32 {
+        match self {
+            Node::Selector(_) => 0,
+            Node::Sequence(_) => 1,
+            Node::SequenceStar(_) => 3,
+            Node::Action(_) => 5,
+            Node::Condition(_) => 6,
+        }
+    }
+
+    /// the children of a control node, empty for leaves
+    pub fn children(&self) -> &[Node] {
+        match self {
+            Node::Selector(children) | Node::Sequence(children) | Node::SequenceStar(children) => {
+                children
+            }
+            Node::Condition(_) | Node::Action(_) => &[],
+        }
+    }
+
+    fn children_mut(&mut self) -> Option<&mut Vec<Node>> {
+        match self {
+            Node::Selector(children) | Node::Sequence(children) | Node::SequenceStar(children) => {
+                Some(children)
+            }
+            Node::Condition(_) | Node::Action(_) => None,
+        }
+    }
+
+    /// the selector AutoMoDe-Maple puts below the root: an action guarded by a condition
+    pub fn random_guarded_action(rng: &mut impl Rng) -> Self {
+        return Node::Selector(vec![
+            Node::Condition(Condition::random(rng)),
+            Node::Action(Behaviour::random(rng)),
+        ]);
+    }
+
+    fn parse(flags: &Flags, path: &str) -> Result<Self, String> {
+        let (type_key, children_key) = if path.is_empty() {
+            ("nroot".to_string(), "nchildroot".to_string())
+        } else {
+            (format!("n{path}"), format!("nchild{path}"))
+        };
+
+        let parse_children = || -> Result<Vec<Node>, String> {
+            let num_children = flags.get::<usize>(&children_key)?;
+            if num_children == 0 || num_children > MAX_CHILDREN {
+                return Err(format!(
+                    "invalid number of children {num_children} for --{children_key}"
+                ));
+            }
+            return (0..num_children)
+                .map(|i| Node::parse(flags, &format!("{path}{i}")))
+                .collect();
+        };
+
+        return match flags.get::<u32>(&type_key)? {
+            0 => Ok(Node::Selector(parse_children()?)),
+            1 => Ok(Node::Sequence(parse_children()?)),
+            3 => Ok(Node::SequenceStar(parse_children()?)),
+            5 if !path.is_empty() => Ok(Node::Action(Behaviour::from_flags(
+                flags,
+                &format!("a{path}"),
+                path,
+            )?)),
+            6 if !path.is_empty() => Ok(Node::Condition(Condition::from_flags(
+                flags,
+                &format!("c{path}"),
+                path,
+            )?)),
+            5 | 6 => Err("the root must be a selector or a sequence".to_string()),
+            id => Err(format!("unknown node type {id} for --{type_key}")),
+        };
+    }
+
+    fn push_args(&self, args: &mut Vec<String>, path: &str) {
+        let (type_key, children_key) = if path.is_empty() {
+            ("nroot".to_string(), "nchildroot".to_string())
+        } else {
+            (format!("n{path}"), format!("nchild{path}"))
+        };
+        args.push(format!("--{type_key}"));
+        args.push(format!("{}", self.type_id()));
+
+        match self {
+            Node::Selector(children) | Node::Sequence(children) | Node::SequenceStar(children) => {
+                args.push(format!("--{children_key}"));
+                args.push(format!("{}", children.len()));
+                for (i, child) in children.iter().enumerate() {
+                    child.push_args(args, &format!("{path}{i}"));
+                }
+            }
+            Node::Condition(condition) => condition.push_args(args, &format!("c{path}"), path),
+            Node::Action(behaviour) => behaviour.push_args(args, &format!("a{path}"), path),
+        }
+    }
+
+    /// the leaves below and including this node, depth first
+    fn leaves_mut(&mut self) -> Vec<&mut Node> {
+        return match self {
+            Node::Selector(children) | Node::Sequence(children) | Node::SequenceStar(children) => {
+                children.iter_mut().flat_map(|x| x.leaves_mut()).collect()
+            }
+            Node::Condition(_) | Node::Action(_) => vec![self],
+        };
+    }
+}
+
+impl BehaviourTree {
+    /// random tree shaped like the ones AutoMoDe-Maple samples, a sequence* of 1 to
+    /// `MAX_ROOT_CHILDREN` selectors with a condition and an action
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let num_children = rng.gen_range(1..=MAX_ROOT_CHILDREN);
+        let children = (0..num_children)
+            .map(|_| Node::random_guarded_action(rng))
+            .collect();
+        return Self {
+            root: Node::SequenceStar(children),
+        };
+    }
+
+    /// copy with one random change: the behaviour of an action or a condition, or a child of
+    /// the root added, removed or moved, always a valid tree
+    pub fn mutate(&self, rng: &mut impl Rng) -> Self {
+        let mut tree = self.clone();
+        let num_children = tree.root.children().len();
+
+        // operators that do not apply are redrawn, changing a leaf always applies to a tree
+        // with leaves
+        loop {
+            match rng.gen_range(0..4) {
+                0 => {
+                    let mut leaves = tree.root.leaves_mut();
+                    if leaves.is_empty() {
+                        continue;
+                    }
+                    let i = rng.gen_range(0..leaves.len());
+                    match leaves.swap_remove(i) {
+                        Node::Condition(condition) => *condition = condition.mutate(rng),
+                        Node::Action(behaviour) => *behaviour = behaviour.mutate(rng),
+                        _ => unreachable!(),
+                    }
+                }
+                1 if num_children < MAX_ROOT_CHILDREN => {
+                    let node = Node::random_guarded_action(rng);
+                    let children = tree.root.children_mut().unwrap();
+                    children.insert(rng.gen_range(0..=num_children), node);
+                }
+                2 if num_children > 1 => {
+                    let children = tree.root.children_mut().unwrap();
+                    children.remove(rng.gen_range(0..num_children));
+                }
+                3 if num_children > 1 => {
+                    let children = tree.root.children_mut().unwrap();
+                    let from = rng.gen_range(0..num_children);
+                    let node = children.remove(from);
+                    let mut to = rng.gen_range(0..num_children - 1);
+                    if to >= from {
+                        to += 1;
+                    }
+                    children.insert(to, node);
+                }
+                _ => continue,
+            }
+            return tree;
+        }
+    }
+
+    /// parses the arguments passed after `--bt-config`
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let flags = Flags::new(args)?;
+        return Ok(Self {
+            root: Node::parse(&flags, "")?,
+        });
+    }
+
+    /// the arguments to pass after `--bt-config`
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        self.root.push_args(&mut args, "");
+        return args;
+    }
+
+    pub fn to_cmd(&self) -> String {
+        return self.to_args().join(" ");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsm::split_cmd;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const TREE: &str = "--nroot 3 --nchildroot 2 --n0 0 --nchild0 2 --n00 6 --c00 3 --p00 5 --w00 8.5 --n01 5 --a01 0 --rwm01 50 --n1 0 --nchild1 2 --n10 6 --c10 5 --p10 0.25 --n11 5 --a11 4 --att11 2.5";
+
+    #[test]
+    fn parse_to_args_round_trip() {
+        let tree = BehaviourTree::parse(&split_cmd(TREE)).unwrap();
+        assert_eq!(
+            tree.root,
+            Node::SequenceStar(vec![
+                Node::Selector(vec![
+                    Node::Condition(Condition::NeighborsCount { p: 5.0, w: 8.5 }),
+                    Node::Action(Behaviour::Exploration { rwm: 50 }),
+                ]),
+                Node::Selector(vec![
+                    Node::Condition(Condition::FixedProbability { p: 0.25 }),
+                    Node::Action(Behaviour::Attraction { att: 2.5 }),
+                ]),
+            ])
+        );
+        assert_eq!(tree.to_cmd(), TREE);
+    }
+
+    #[test]
+    fn random_and_mutated_trees_round_trip() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..50 {
+            let mut tree = BehaviourTree::random(&mut rng);
+            for _ in 0..20 {
+                assert!((1..=MAX_ROOT_CHILDREN).contains(&tree.root.children().len()));
+                assert_eq!(BehaviourTree::parse(&tree.to_args()).unwrap(), tree);
+                tree = tree.mutate(&mut rng);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_trees() {
+        let err = BehaviourTree::parse(&split_cmd("--nroot 5 --a 0")).unwrap_err();
+        assert!(err.contains("root"), "{err}");
+        assert!(BehaviourTree::parse(&split_cmd("--nroot 0 --nchildroot 0")).is_err());
+        assert!(BehaviourTree::parse(&split_cmd("--nroot 0 --nchildroot 11")).is_err());
+        assert!(BehaviourTree::parse(&split_cmd("--nroot 2 --nchildroot 1")).is_err());
+        assert!(BehaviourTree::parse(&split_cmd("--nroot 0 --nchildroot 1 --n0 6")).is_err());
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{
+    controller::Controller,
+    fsm::{Condition, Fsm, State, Transition},
+    utilities::fnv1a,
+};
+
+/// A machine with the parts removed that cannot change what the robots do.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simplification {
+    pub fsm: Fsm,
+    /// transitions that never fire or only after one that always fires
+    pub dead_transitions: usize,
+    /// states not reachable from the initial state
+    pub unreachable_states: usize,
+    /// states merged into an equivalent one
+    pub merged_states: usize,
+}
+
+impl Simplification {
+    pub fn summary(&self) -> String {
+        return format!(
+            "removed {} dead transitions and {} unreachable states, merged {} states",
+            self.dead_transitions, self.unreachable_states, self.merged_states
+        );
+    }
+}
+
+impl Fsm {
+    /// Removes dead transitions and unreachable states and merges equivalent states until
+    /// nothing changes. AutoMoDe fires the first transition of a state whose condition holds,
+    /// so the order of the transitions is kept and states are only merged if none of them has
+    /// a transition to another of them.
+    pub fn simplify(&self) -> Simplification {
+        let mut result = Simplification {
+            fsm: self.clone(),
+            dead_transitions: 0,
+            unreachable_states: 0,
+            merged_states: 0,
+        };
+        if self.states.is_empty() {
+            return result;
+        }
+
+        loop {
+            let dead = remove_dead_transitions(&mut result.fsm);
+            let unreachable = remove_unreachable_states(&mut result.fsm);
+            let merged = merge_equivalent_states(&mut result.fsm);
+            result.dead_transitions += dead;
+            result.unreachable_states += unreachable;
+            result.merged_states += merged;
+            if dead + unreachable + merged == 0 {
+                return result;
+            }
+        }
+    }
+
+    /// the simplified machine with its states numbered in breadth first order from the initial
+    /// state, following the transitions in order, so equivalent machines are equal
+    pub fn canonical(&self) -> Fsm {
+        let fsm = self.simplify().fsm;
+        if fsm.states.is_empty() {
+            return fsm;
+        }
+
+        let mut order = vec![0];
+        let mut i = 0;
+        while i < order.len() {
+            for transition in &fsm.states[order[i]].transitions {
+                if !order.contains(&transition.to) {
+                    order.push(transition.to);
+                }
+            }
+            i += 1;
+        }
+        return renumber(&fsm, &order);
+    }
+
+    /// `to_cmd` of the canonical machine
+    pub fn canonical_cmd(&self) -> String {
+        return self.canonical().to_cmd();
+    }
+
+    /// FNV-1a of `canonical_cmd` as 16 hex digits, stable across builds and platforms
+    pub fn canonical_hash(&self) -> String {
+        return format!("{:016x}", fnv1a(self.canonical_cmd().as_bytes()));
+    }
+}
+
+impl Controller {
+    /// the canonical machine of an FSM, the tree as parsed for a behaviour tree
+    pub fn canonical_cmd(&self) -> String {
+        return match self {
+            Controller::Fsm(fsm) => fsm.canonical_cmd(),
+            Controller::BehaviourTree(tree) => tree.to_cmd(),
+        };
+    }
+
+    pub fn canonical_hash(&self) -> String {
+        return format!("{:016x}", fnv1a(self.canonical_cmd().as_bytes()));
+    }
+}
+
+/// whether two controller strings configure structurally the same controller
+pub fn equivalent(a: &[String], b: &[String]) -> Result<bool, String> {
+    let a = Controller::parse(a)?;
+    let b = Controller::parse(b)?;
+    return Ok(a.kind() == b.kind() && a.canonical_cmd() == b.canonical_cmd());
+}
+
+/// `canonical_hash` of a controller string, `None` if it does not parse
+pub fn canonical_hash(controller_cmd: &[String]) -> Option<String> {
+    return Controller::parse(controller_cmd)
+        .ok()
+        .map(|x| x.canonical_hash());
+}
+
+/// The floor and fixed probability conditions fire with their probability, the neighbour
+/// conditions with a sigmoid of the neighbour count and never with certainty.
+fn never_fires(condition: &Condition) -> bool {
+    return match condition {
+        Condition::BlackFloor { p }
+        | Condition::GrayFloor { p }
+        | Condition::WhiteFloor { p }
+        | Condition::FixedProbability { p } => *p <= 0.0,
+        Condition::NeighborsCount { .. } | Condition::InvertedNeighborsCount { .. } => false,
+    };
+}
+
+fn always_fires(condition: &Condition) -> bool {
+    return matches!(condition, Condition::FixedProbability { p } if *p >= 1.0);
+}
+
+fn remove_dead_transitions(fsm: &mut Fsm) -> usize {
+    let mut removed = 0;
+    for state in fsm.states.iter_mut() {
+        let len = state.transitions.len();
+        state.transitions.retain(|x| !never_fires(&x.condition));
+        // the transitions after one that always fires are never checked
+        if let Some(i) = state
+            .transitions
+            .iter()
+            .position(|x| always_fires(&x.condition))
+        {
+            state.transitions.truncate(i + 1);
+        }
+        removed += len - state.transitions.len();
+    }
+    return removed;
+}
+
+fn remove_unreachable_states(fsm: &mut Fsm) -> usize {
+    let mut reachable = vec![false; fsm.states.len()];
+    let mut stack = vec![0];
+    while let Some(k) = stack.pop() {
+        if reachable[k] {
+            continue;
+        }
+        reachable[k] = true;
+        stack.extend(fsm.states[k].transitions.iter().map(|x| x.to));
+    }
+
+    let order = (0..fsm.states.len())
+        .filter(|&k| reachable[k])
+        .collect::<Vec<usize>>();
+    let removed = fsm.states.len() - order.len();
+    if removed > 0 {
+        *fsm = renumber(fsm, &order);
+    }
+    return removed;
+}
+
+/// Partition refinement: states start in one class and are split until the states of a class
+/// have the same behaviour and the same conditions, in the same order, leading to the same
+/// classes.
+fn merge_equivalent_states(fsm: &mut Fsm) -> usize {
+    let mut class = vec![0; fsm.states.len()];
+    let mut num_classes = 1;
+    loop {
+        let signatures = fsm
+            .states
+            .iter()
+            .map(|state| {
+                let transitions = state
+                    .transitions
+                    .iter()
+                    .map(|x| (x.condition, class[x.to]))
+                    .collect::<Vec<_>>();
+                (state.behaviour, transitions)
+            })
+            .collect::<Vec<_>>();
+
+        let mut representatives: Vec<usize> = Vec::new();
+        let mut refined = Vec::with_capacity(fsm.states.len());
+        for (k, signature) in signatures.iter().enumerate() {
+            // a state joins the class of an earlier state of the same class and signature
+            match representatives
+                .iter()
+                .position(|&r| class[r] == class[k] && signatures[r] == *signature)
+            {
+                Some(c) => refined.push(c),
+                None => {
+                    refined.push(representatives.len());
+                    representatives.push(k);
+                }
+            }
+        }
+
+        let stable = representatives.len() == num_classes;
+        num_classes = representatives.len();
+        class = refined;
+        if !stable {
+            continue;
+        }
+
+        // A transition inside a class would become a self-loop, which AutoMoDe cannot express,
+        // and dropping it would change when the later transitions of the state fire. The
+        // states of such a class are kept apart, the other classes shrink to their first state.
+        let mergeable = (0..num_classes)
+            .map(|c| {
+                !fsm.states.iter().enumerate().any(|(k, state)| {
+                    class[k] == c && state.transitions.iter().any(|x| class[x.to] == c)
+                })
+            })
+            .collect::<Vec<bool>>();
+        let kept = (0..fsm.states.len())
+            .filter(|&k| !mergeable[class[k]] || representatives[class[k]] == k)
+            .collect::<Vec<usize>>();
+        let merged = fsm.states.len() - kept.len();
+        if merged == 0 {
+            return 0;
+        }
+
+        // the state that stands for state k, the initial state stays the first
+        let index = |k: usize| {
+            let k = if mergeable[class[k]] {
+                representatives[class[k]]
+            } else {
+                k
+            };
+            kept.iter().position(|&x| x == k).unwrap()
+        };
+        fsm.states = kept
+            .iter()
+            .map(|&k| State {
+                behaviour: fsm.states[k].behaviour,
+                transitions: fsm.states[k]
+                    .transitions
+                    .iter()
+                    .map(|x| Transition {
+                        to: index(x.to),
+                        condition: x.condition,
+                    })
+                    .collect(),
+            })
+            .collect();
+        return merged;
+    }
+}
+
+/// the states of `order` in that order, transitions to other states are dropped
+fn renumber(fsm: &Fsm, order: &[usize]) -> Fsm {
+    let states = order
+        .iter()
+        .map(|&k| State {
+            behaviour: fsm.states[k].behaviour,
+            transitions: fsm.states[k]
+                .transitions
+                .iter()
+                .filter_map(|x| {
+                    let to = order.iter().position(|&k| k == x.to)?;
+                    Some(Transition {
+                        to,
+                        condition: x.condition,
+                    })
+                })
+                .collect(),
+        })
+        .collect();
+    return Fsm { states };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsm::split_cmd;
+
+    fn fsm(cmd: &str) -> Fsm {
+        return Fsm::parse(&split_cmd(cmd)).unwrap();
+    }
+
+    /// exploration that stops on white floor and restarts at random
+    const EXPLORE_STOP: &str = "--nstates 2 --s0 0 --rwm0 50 --n0 1 --n0x0 0 --c0x0 2 --p0x0 0.5 --s1 1 --n1 1 --n1x0 0 --c1x0 5 --p1x0 0.2";
+
+    #[test]
+    fn unreachable_states_are_removed() {
+        // state 1 (phototaxis) has no incoming transition
+        let with_unreachable = fsm("--nstates 3 --s0 0 --rwm0 50 --n0 1 --n0x0 1 --c0x0 2 --p0x0 0.5 --s1 2 --n1 1 --n1x0 0 --c1x0 5 --p1x0 1 --s2 1 --n2 1 --n2x0 0 --c2x0 5 --p2x0 0.2");
+        let simplification = with_unreachable.simplify();
+        assert_eq!(simplification.unreachable_states, 1);
+        assert_eq!(simplification.fsm, fsm(EXPLORE_STOP));
+    }
+
+    #[test]
+    fn transitions_that_never_fire_are_removed() {
+        let with_dead = fsm("--nstates 2 --s0 0 --rwm0 50 --n0 2 --n0x0 0 --c0x0 0 --p0x0 0 --n0x1 0 --c0x1 2 --p0x1 0.5 --s1 1 --n1 1 --n1x0 0 --c1x0 5 --p1x0 0.2");
+        let simplification = with_dead.simplify();
+        assert_eq!(simplification.dead_transitions, 1);
+        assert_eq!(simplification.fsm, fsm(EXPLORE_STOP));
+    }
+
+    #[test]
+    fn transitions_after_one_that_always_fires_are_removed() {
+        let shadowed = fsm("--nstates 2 --s0 0 --rwm0 50 --n0 2 --n0x0 0 --c0x0 5 --p0x0 1 --n0x1 0 --c0x1 2 --p0x1 0.5 --s1 1 --n1 1 --n1x0 0 --c1x0 5 --p1x0 0.2");
+        let simplification = shadowed.simplify();
+        assert_eq!(simplification.dead_transitions, 1);
+        assert_eq!(simplification.fsm.states[0].transitions.len(), 1);
+    }
+
+    #[test]
+    fn canonical_form_ignores_the_numbering() {
+        // the same machine, its stop and phototaxis states swapped
+        let a = fsm("--nstates 3 --s0 0 --rwm0 50 --n0 2 --n0x0 0 --c0x0 2 --p0x0 0.5 --n0x1 1 --c0x1 0 --p0x1 0.3 --s1 1 --n1 1 --n1x0 0 --c1x0 5 --p1x0 0.2 --s2 2 --n2 1 --n2x0 0 --c2x0 5 --p2x0 0.1");
+        let b = fsm("--nstates 3 --s0 0 --rwm0 50 --n0 2 --n0x0 1 --c0x0 2 --p0x0 0.5 --n0x1 0 --c0x1 0 --p0x1 0.3 --s1 2 --n1 1 --n1x0 0 --c1x0 5 --p1x0 0.1 --s2 1 --n2 1 --n2x0 0 --c2x0 5 --p2x0 0.2");
+        assert_ne!(a, b);
+        assert_eq!(a.canonical(), b.canonical());
+        assert_eq!(a.canonical_hash(), b.canonical_hash());
+        assert_eq!(a.canonical().canonical(), a.canonical());
+    }
+
+    #[test]
+    fn duplicated_states_are_merged() {
+        // explore -> stop -> explore -> stop -> back to the first
+        let doubled = fsm("--nstates 4 --s0 0 --rwm0 50 --n0 1 --n0x0 0 --c0x0 2 --p0x0 0.5 --s1 1 --n1 1 --n1x0 1 --c1x0 5 --p1x0 0.2 --s2 0 --rwm2 50 --n2 1 --n2x0 2 --c2x0 2 --p2x0 0.5 --s3 1 --n3 1 --n3x0 0 --c3x0 5 --p3x0 0.2");
+        let simplification = doubled.simplify();
+        assert_eq!(simplification.merged_states, 2);
+        assert_eq!(simplification.fsm, fsm(EXPLORE_STOP));
+    }
+
+    #[test]
+    fn states_with_transitions_between_them_are_not_merged() {
+        // the transition between the two explorations delays the one to stop, dropping it
+        // would make stopping more likely
+        let delayed = fsm("--nstates 3 --s0 0 --rwm0 50 --n0 2 --n0x0 0 --c0x0 5 --p0x0 0.3 --n0x1 1 --c0x1 2 --p0x1 0.5 --s1 0 --rwm1 50 --n1 2 --n1x0 0 --c1x0 5 --p1x0 0.3 --n1x1 1 --c1x1 2 --p1x1 0.5 --s2 1 --n2 1 --n2x0 0 --c2x0 5 --p2x0 0.2");
+        let simplification = delayed.simplify();
+        assert_eq!(simplification.merged_states, 0);
+        assert_ne!(delayed.canonical_hash(), fsm(EXPLORE_STOP).canonical_hash());
+    }
+
+    #[test]
+    fn equivalent_controller_strings() {
+        let a = split_cmd(EXPLORE_STOP);
+        let b = split_cmd("--nstates 2 --s0 0 --rwm0 50 --n0 2 --n0x0 0 --c0x0 5 --p0x0 0 --n0x1 0 --c0x1 2 --p0x1 0.50 --s1 1 --n1 1 --n1x0 0 --c1x0 5 --p1x0 0.2");
+        let c = split_cmd("--nstates 2 --s0 0 --rwm0 51 --n0 1 --n0x0 0 --c0x0 2 --p0x0 0.5 --s1 1 --n1 1 --n1x0 0 --c1x0 5 --p1x0 0.2");
+        assert_eq!(equivalent(&a, &b), Ok(true));
+        assert_eq!(equivalent(&a, &c), Ok(false));
+        assert!(equivalent(&a, &split_cmd("--nstates")).is_err());
+    }
+}
@@ -0,0 +1,665 @@
+use crate::{animation::Arena, metrics::euclidean_dist, trajectory::Trajectory, SWARM_SIZE};
+
+/// steps used when none are given, clipping needs an arena and is left out
+pub const DEFAULT_STEPS: &str = "frozen,swaps:0.1,outliers:0.5,median:5";
+
+/// outliers longer than this many frames are taken as a real relocation and kept
+const MAX_OUTLIER_GAP: usize = 10;
+
+/// One repair applied to a tracked trajectory.
+///
+/// Steps change positions only. Where `Frozen` and `Outliers` replace a position the reported
+/// heading and velocity belong to the same glitch and are cleared, the smoothing steps and
+/// clipping keep them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CleaningStep {
+    /// frames where no robot moved at all are a stalled tracker, they are interpolated
+    Frozen,
+    /// positions reached faster than `max_speed` m/s from the last good position are
+    /// interpolated between the surrounding good positions, the first frames count as good once
+    /// the robot moves plausibly from them
+    Outliers { max_speed: f64 },
+    /// two robots closer than `max_dist` whose positions fit better crossed over are swapped
+    /// back from that frame on
+    Swaps { max_dist: f64 },
+    /// centered running median over an odd number of frames
+    Median { window: usize },
+    /// constant velocity Kalman filter with a Rauch-Tung-Striebel smoother, `accel` is the
+    /// standard deviation of the acceleration and `noise` the one of the tracking
+    Kalman { accel: f64, noise: f64 },
+    /// positions outside the arena are moved onto its border
+    Clip(Arena),
+}
+
+/// What a step changed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    Moved {
+        tick: usize,
+        robot: usize,
+        from: (f64, f64),
+        to: (f64, f64),
+    },
+    /// the identities of the robots are exchanged from `tick` on
+    Swapped { tick: usize, robots: (usize, usize) },
+}
+
+/// Changes of every step of a pipeline, in the order the steps ran.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CleaningLog {
+    pub steps: Vec<(CleaningStep, Vec<Change>)>,
+}
+
+/// Steps applied one after another to real recordings before they are used as the reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cleaning {
+    pub steps: Vec<CleaningStep>,
+}
+
+impl CleaningStep {
+    pub fn name(&self) -> String {
+        match self {
+            CleaningStep::Frozen => "frozen".to_string(),
+            CleaningStep::Outliers { max_speed } => format!("outliers:{max_speed}"),
+            CleaningStep::Swaps { max_dist } => format!("swaps:{max_dist}"),
+            CleaningStep::Median { window } => format!("median:{window}"),
+            CleaningStep::Kalman { accel, noise } => format!("kalman:{accel}/{noise}"),
+            CleaningStep::Clip(arena) => format!(
+                "clip:{}x{}",
+                arena.max.0 - arena.min.0,
+                arena.max.1 - arena.min.1
+            ),
+        }
+    }
+
+    /// parses the format returned by `name`, a bare `clip` uses `arena`
+    pub fn parse(name: &str, arena: Option<Arena>) -> Option<Self> {
+        let name = name.trim();
+        let (kind, args) = name.split_once(":").unwrap_or((name, ""));
+        let positive = |x: &str| x.trim().parse::<f64>().ok().filter(|x| *x > 0.0);
+        return match kind {
+            "frozen" if args.is_empty() => Some(CleaningStep::Frozen),
+            "outliers" => Some(CleaningStep::Outliers {
+                max_speed: positive(args)?,
+            }),
+            "swaps" => Some(CleaningStep::Swaps {
+                max_dist: positive(args)?,
+            }),
+            "median" => {
+                let window = args.trim().parse::<usize>().ok()?;
+                (window % 2 == 1).then_some(CleaningStep::Median { window })
+            }
+            "kalman" => {
+                let (accel, noise) = args.split_once("/")?;
+                Some(CleaningStep::Kalman {
+                    accel: positive(accel)?,
+                    noise: positive(noise)?,
+                })
+            }
+            "clip" if args.is_empty() => arena.map(CleaningStep::Clip),
+            "clip" => Arena::parse(args).map(CleaningStep::Clip),
+            _ => None,
+        };
+    }
+
+    pub fn apply(&self, trajectory: &mut Trajectory) -> Vec<Change> {
+        let before = trajectory.frames.clone();
+        match *self {
+            CleaningStep::Frozen => frozen(trajectory),
+            CleaningStep::Outliers { max_speed } => outliers(trajectory, max_speed),
+            CleaningStep::Swaps { max_dist } => return swaps(trajectory, max_dist),
+            CleaningStep::Median { window } => median(trajectory, window),
+            CleaningStep::Kalman { accel, noise } => {
+                for robot in 0..SWARM_SIZE {
+                    kalman(trajectory, robot, accel, noise);
+                }
+            }
+            CleaningStep::Clip(arena) => {
+                for frame in trajectory.frames.iter_mut() {
+                    for (x, y) in frame.iter_mut() {
+                        *x = x.clamp(arena.min.0, arena.max.0);
+                        *y = y.clamp(arena.min.1, arena.max.1);
+                    }
+                }
+            }
+        }
+
+        let mut changes = Vec::new();
+        for (tick, (old, new)) in before.iter().zip(&trajectory.frames).enumerate() {
+            for robot in 0..SWARM_SIZE {
+                if old[robot] != new[robot] {
+                    changes.push(Change::Moved {
+                        tick,
+                        robot,
+                        from: old[robot],
+                        to: new[robot],
+                    });
+                }
+            }
+        }
+
+        if matches!(self, CleaningStep::Frozen | CleaningStep::Outliers { .. }) {
+            for change in &changes {
+                if let Change::Moved { tick, robot, .. } = *change {
+                    if let Some(states) = trajectory.states.get_mut(tick) {
+                        states[robot].heading = None;
+                        states[robot].velocity = None;
+                    }
+                }
+            }
+        }
+        return changes;
+    }
+}
+
+impl Cleaning {
+    /// steps separated by `,`, e.g. `swaps:0.1,outliers:0.5,median:5,clip:2x2`
+    pub fn parse(spec: &str, arena: Option<Arena>) -> Result<Self, String> {
+        let steps = spec
+            .split(",")
+            .filter(|x| !x.trim().is_empty())
+            .map(|x| {
+                CleaningStep::parse(x, arena).ok_or(format!("invalid cleaning step {}", x.trim()))
+            })
+            .collect::<Result<Vec<CleaningStep>, String>>()?;
+        return Ok(Self { steps });
+    }
+
+    pub fn name(&self) -> String {
+        return self
+            .steps
+            .iter()
+            .map(|x| x.name())
+            .collect::<Vec<String>>()
+            .join(",");
+    }
+
+    pub fn clean(&self, trajectory: &Trajectory) -> (Trajectory, CleaningLog) {
+        let mut trajectory = trajectory.clone();
+        let mut log = CleaningLog::default();
+        for step in &self.steps {
+            let changes = step.apply(&mut trajectory);
+            log.steps.push((*step, changes));
+        }
+        return (trajectory, log);
+    }
+}
+
+impl CleaningLog {
+    /// one line per step with the number of moved positions, the largest move and the swaps
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        for (step, changes) in &self.steps {
+            let mut moved = 0;
+            let mut max_shift: f64 = 0.0;
+            let mut swaps = 0;
+            for change in changes {
+                match change {
+                    Change::Moved { from, to, .. } => {
+                        moved += 1;
+                        max_shift = max_shift.max(euclidean_dist(from, to));
+                    }
+                    Change::Swapped { .. } => swaps += 1,
+                }
+            }
+            out.push_str(&format!(
+                "{:<20}\t{moved:>6} moved\t{max_shift:>8.4} max shift\t{swaps:>4} swaps\n",
+                step.name()
+            ));
+        }
+        return out;
+    }
+
+    /// csv lines `run,step,tick,robot,other,from_x,from_y,to_x,to_y` without a header, swaps
+    /// leave the positions empty
+    pub fn to_csv(&self, run: &str) -> String {
+        let mut out = String::new();
+        for (step, changes) in &self.steps {
+            let step = step.name();
+            for change in changes {
+                out.push_str(&match change {
+                    Change::Moved {
+                        tick,
+                        robot,
+                        from,
+                        to,
+                    } => format!(
+                        "{run},{step},{tick},{robot},,{},{},{},{}\n",
+                        from.0, from.1, to.0, to.1
+                    ),
+                    Change::Swapped { tick, robots } => {
+                        format!("{run},{step},{tick},{},{},,,,\n", robots.0, robots.1)
+                    }
+                });
+            }
+        }
+        return out;
+    }
+}
+
+/// position of `robot` at `t` on the line between the frames `a` and `b`
+fn interpolate(trajectory: &Trajectory, robot: usize, a: usize, b: usize, t: usize) -> (f64, f64) {
+    let (pa, pb) = (trajectory.frames[a][robot], trajectory.frames[b][robot]);
+    let span = trajectory.times[b] - trajectory.times[a];
+    let w = if span > 0.0 {
+        (trajectory.times[t] - trajectory.times[a]) / span
+    } else {
+        0.0
+    };
+    return (pa.0 + w * (pb.0 - pa.0), pa.1 + w * (pb.1 - pa.1));
+}
+
+/// replaces the positions of `robot` at the frames that are not `good` by interpolating between
+/// the good ones around them, gaps at the end hold the last good position
+fn repair(trajectory: &mut Trajectory, robot: usize, good: &[bool]) {
+    let mut last_good = None;
+    let mut t = 0;
+    while t < good.len() {
+        if good[t] {
+            last_good = Some(t);
+            t += 1;
+            continue;
+        }
+        let next_good = (t..good.len()).find(|&i| good[i]);
+        let end = next_good.unwrap_or(good.len());
+        for i in t..end {
+            trajectory.frames[i][robot] = match (last_good, next_good) {
+                (Some(a), Some(b)) => interpolate(trajectory, robot, a, b, i),
+                (Some(a), None) => trajectory.frames[a][robot],
+                (None, Some(b)) => trajectory.frames[b][robot],
+                (None, None) => trajectory.frames[i][robot],
+            };
+        }
+        t = end;
+    }
+}
+
+fn frozen(trajectory: &mut Trajectory) {
+    let mut good = vec![true];
+    good.extend(trajectory.frames.windows(2).map(|x| x[0] != x[1]));
+    for robot in 0..SWARM_SIZE {
+        repair(trajectory, robot, &good);
+    }
+}
+
+fn outliers(trajectory: &mut Trajectory, max_speed: f64) {
+    let len = trajectory.len();
+    for robot in 0..SWARM_SIZE {
+        let plausible = |a: usize, b: usize| {
+            let dt = trajectory.times[b] - trajectory.times[a];
+            euclidean_dist(&trajectory.frames[a][robot], &trajectory.frames[b][robot])
+                <= max_speed * dt
+        };
+        // the track starts at the first frame the robot moves plausibly from, so a glitch in
+        // the first frames is repaired as well
+        let start = (0..len.saturating_sub(1))
+            .take(MAX_OUTLIER_GAP + 1)
+            .find(|&t| plausible(t, t + 1))
+            .unwrap_or(0);
+
+        let mut good = vec![true; len];
+        good[..start].fill(false);
+        let mut last = start;
+        for (t, good) in good.iter_mut().enumerate().skip(start + 1) {
+            if !plausible(last, t) && t - last <= MAX_OUTLIER_GAP {
+                *good = false;
+            } else {
+                last = t;
+            }
+        }
+        repair(trajectory, robot, &good);
+    }
+}
+
+fn swaps(trajectory: &mut Trajectory, max_dist: f64) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for t in 1..trajectory.len() {
+        let prev = trajectory.frames[t - 1];
+        for i in 0..SWARM_SIZE {
+            for j in i + 1..SWARM_SIZE {
+                if euclidean_dist(&prev[i], &prev[j]) >= max_dist {
+                    continue;
+                }
+                // read anew, an earlier swap of this tick may have moved robot i or j
+                let cur = trajectory.frames[t];
+                let straight =
+                    euclidean_dist(&prev[i], &cur[i]) + euclidean_dist(&prev[j], &cur[j]);
+                let crossed = euclidean_dist(&prev[i], &cur[j]) + euclidean_dist(&prev[j], &cur[i]);
+                // crossing has to fit clearly better, robots that barely move fit both ways
+                if 2.0 * crossed < straight {
+                    for frame in trajectory.frames[t..].iter_mut() {
+                        frame.swap(i, j);
+                    }
+                    for state in trajectory.states.iter_mut().skip(t) {
+                        state.swap(i, j);
+                    }
+                    changes.push(Change::Swapped {
+                        tick: t,
+                        robots: (i, j),
+                    });
+                }
+            }
+        }
+    }
+    return changes;
+}
+
+fn median(trajectory: &mut Trajectory, window: usize) {
+    let half = window / 2;
+    let frames = trajectory.frames.clone();
+    for (t, frame) in trajectory.frames.iter_mut().enumerate() {
+        // the window shrinks symmetrically at the ends
+        let half = half.min(t).min(frames.len() - 1 - t);
+        let around = &frames[t - half..=t + half];
+        for (robot, pos) in frame.iter_mut().enumerate() {
+            let mut xs = around.iter().map(|x| x[robot].0).collect::<Vec<f64>>();
+            let mut ys = around.iter().map(|x| x[robot].1).collect::<Vec<f64>>();
+            xs.sort_by(f64::total_cmp);
+            ys.sort_by(f64::total_cmp);
+            *pos = (xs[half], ys[half]);
+        }
+    }
+}
+
+/// smooths both coordinates of `robot` independently
+fn kalman(trajectory: &mut Trajectory, robot: usize, accel: f64, noise: f64) {
+    let len = trajectory.len();
+    if len < 2 {
+        return;
+    }
+    let q = accel * accel;
+    let r = noise * noise;
+
+    for axis in 0..2 {
+        let z = |t: usize| {
+            let pos = trajectory.frames[t][robot];
+            if axis == 0 {
+                pos.0
+            } else {
+                pos.1
+            }
+        };
+
+        // state is position and velocity
+        let mut x = vec![[0.0; 2]; len];
+        let mut p = vec![[[0.0; 2]; 2]; len];
+        let mut x_pred = vec![[0.0; 2]; len];
+        let mut p_pred = vec![[[0.0; 2]; 2]; len];
+        x[0] = [z(0), 0.0];
+        p[0] = [[r, 0.0], [0.0, 1.0]];
+        for t in 1..len {
+            let dt = trajectory.times[t] - trajectory.times[t - 1];
+            let f = [[1.0, dt], [0.0, 1.0]];
+            x_pred[t] = [x[t - 1][0] + dt * x[t - 1][1], x[t - 1][1]];
+            let mut pp = mul(mul(f, p[t - 1]), transpose(f));
+            pp[0][0] += q * dt.powi(4) / 4.0;
+            pp[0][1] += q * dt.powi(3) / 2.0;
+            pp[1][0] += q * dt.powi(3) / 2.0;
+            pp[1][1] += q * dt.powi(2);
+            p_pred[t] = pp;
+
+            let s = pp[0][0] + r;
+            let k = [pp[0][0] / s, pp[1][0] / s];
+            let innovation = z(t) - x_pred[t][0];
+            x[t] = [
+                x_pred[t][0] + k[0] * innovation,
+                x_pred[t][1] + k[1] * innovation,
+            ];
+            p[t] = [
+                [pp[0][0] - k[0] * pp[0][0], pp[0][1] - k[0] * pp[0][1]],
+                [pp[1][0] - k[1] * pp[0][0], pp[1][1] - k[1] * pp[0][1]],
+            ];
+        }
+
+        // Rauch-Tung-Striebel pass backwards through the filtered states
+        let mut smoothed = x.clone();
+        for t in (0..len - 1).rev() {
+            let dt = trajectory.times[t + 1] - trajectory.times[t];
+            let f = [[1.0, dt], [0.0, 1.0]];
+            let Some(inv) = inverse(p_pred[t + 1]) else {
+                continue;
+            };
+            let c = mul(mul(p[t], transpose(f)), inv);
+            let d = [
+                smoothed[t + 1][0] - x_pred[t + 1][0],
+                smoothed[t + 1][1] - x_pred[t + 1][1],
+            ];
+            smoothed[t] = [
+                x[t][0] + c[0][0] * d[0] + c[0][1] * d[1],
+                x[t][1] + c[1][0] * d[0] + c[1][1] * d[1],
+            ];
+        }
+
+        for (frame, state) in trajectory.frames.iter_mut().zip(&smoothed) {
+            if axis == 0 {
+                frame[robot].0 = state[0];
+            } else {
+                frame[robot].1 = state[0];
+            }
+        }
+    }
+}
+
+fn mul(a: [[f64; 2]; 2], b: [[f64; 2]; 2]) -> [[f64; 2]; 2] {
+    return [
+        [
+            a[0][0] * b[0][0] + a[0][1] * b[1][0],
+            a[0][0] * b[0][1] + a[0][1] * b[1][1],
+        ],
+        [
+            a[1][0] * b[0][0] + a[1][1] * b[1][0],
+            a[1][0] * b[0][1] + a[1][1] * b[1][1],
+        ],
+    ];
+}
+
+fn transpose(a: [[f64; 2]; 2]) -> [[f64; 2]; 2] {
+    return [[a[0][0], a[1][0]], [a[0][1], a[1][1]]];
+}
+
+fn inverse(a: [[f64; 2]; 2]) -> Option<[[f64; 2]; 2]> {
+    let det = a[0][0] * a[1][1] - a[0][1] * a[1][0];
+    if det.abs() < 1e-300 {
+        return None;
+    }
+    return Some([
+        [a[1][1] / det, -a[0][1] / det],
+        [-a[1][0] / det, a[0][0] / det],
+    ]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{trajectory::RobotState, SwarmPos};
+
+    /// robots 1 m apart, all moving up at 0.1 m/s and recorded at 10 Hz
+    fn walk(len: usize) -> Trajectory {
+        let frames = (0..len)
+            .map(|t| {
+                let mut frame = SwarmPos::default();
+                for (robot, pos) in frame.iter_mut().enumerate() {
+                    *pos = (robot as f64, 0.01 * t as f64);
+                }
+                frame
+            })
+            .collect();
+        return Trajectory::from_tick_rate(frames, 10.0);
+    }
+
+    fn assert_close(got: &Trajectory, expected: &Trajectory) {
+        for (t, (a, b)) in got.frames.iter().zip(&expected.frames).enumerate() {
+            for robot in 0..SWARM_SIZE {
+                let dist = euclidean_dist(&a[robot], &b[robot]);
+                assert!(
+                    dist < 1e-9,
+                    "tick {t} robot {robot}: {:?} != {:?}",
+                    a[robot],
+                    b[robot]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn teleport_is_interpolated() {
+        let clean = walk(20);
+        let mut glitched = clean.clone();
+        glitched.frames[5][3] = (10.0, 10.0);
+        let changes = CleaningStep::Outliers { max_speed: 0.5 }.apply(&mut glitched);
+        assert_close(&glitched, &clean);
+        assert_eq!(changes.len(), 1);
+    }
+
+    #[test]
+    fn repaired_positions_lose_heading_and_velocity() {
+        let mut glitched = walk(20);
+        glitched.states = vec![
+            [RobotState {
+                heading: Some(1.5),
+                velocity: Some((0.0, 0.1)),
+                state: Some(2),
+            }; SWARM_SIZE];
+            20
+        ];
+        glitched.frames[5][3] = (10.0, 10.0);
+        let mut smoothed = glitched.clone();
+
+        CleaningStep::Outliers { max_speed: 0.5 }.apply(&mut glitched);
+        let repaired = glitched.states[5][3];
+        assert_eq!((repaired.heading, repaired.velocity), (None, None));
+        assert_eq!(repaired.state, Some(2));
+        assert_eq!(glitched.states[5][4].heading, Some(1.5));
+        assert_eq!(glitched.states[6][3].velocity, Some((0.0, 0.1)));
+
+        // smoothing moves the position but keeps what the robot reported
+        CleaningStep::Median { window: 3 }.apply(&mut smoothed);
+        assert_ne!(smoothed.frames[5][3], (10.0, 10.0));
+        assert_eq!(smoothed.states[5][3].heading, Some(1.5));
+    }
+
+    #[test]
+    fn teleport_in_the_first_frame_is_repaired() {
+        let clean = walk(20);
+        let mut glitched = clean.clone();
+        glitched.frames[0][3] = (10.0, 10.0);
+        CleaningStep::Outliers { max_speed: 0.5 }.apply(&mut glitched);
+        // nothing before the first good frame to interpolate from, it is held
+        assert_eq!(glitched.frames[0][3], clean.frames[1][3]);
+        assert_close(
+            &Trajectory::from_tick_rate(glitched.frames[1..].to_vec(), 10.0),
+            &Trajectory::from_tick_rate(clean.frames[1..].to_vec(), 10.0),
+        );
+    }
+
+    /// robots 0, 1 and 2 walk side by side 4 cm apart
+    fn close_walk(len: usize) -> Trajectory {
+        let mut trajectory = walk(len);
+        for frame in trajectory.frames.iter_mut() {
+            for (robot, pos) in frame.iter_mut().take(3).enumerate() {
+                pos.0 = 0.04 * robot as f64;
+            }
+        }
+        return trajectory;
+    }
+
+    #[test]
+    fn crossed_pair_is_swapped_back() {
+        let clean = close_walk(20);
+        let mut crossed = clean.clone();
+        for frame in crossed.frames[6..].iter_mut() {
+            frame.swap(0, 1);
+        }
+        let changes = CleaningStep::Swaps { max_dist: 0.1 }.apply(&mut crossed);
+        assert_close(&crossed, &clean);
+        assert_eq!(
+            changes,
+            vec![Change::Swapped {
+                tick: 6,
+                robots: (0, 1)
+            }]
+        );
+    }
+
+    #[test]
+    fn rotated_identities_are_swapped_back() {
+        let clean = close_walk(20);
+        let mut rotated = clean.clone();
+        for frame in rotated.frames[6..].iter_mut() {
+            frame[..3].rotate_left(1);
+        }
+        CleaningStep::Swaps { max_dist: 0.1 }.apply(&mut rotated);
+        assert_close(&rotated, &clean);
+    }
+
+    #[test]
+    fn later_pairs_see_the_earlier_swaps_of_a_tick() {
+        // with the positions from before swapping 0 and 1 back, 1 and 2 look crossed as well
+        let mut frames = walk(2).frames;
+        frames[0][..3].copy_from_slice(&[(0.05, 0.02), (0.05, 0.0), (0.04, 0.01)]);
+        frames[1][..3].copy_from_slice(&[(0.04, 0.03), (0.05, 0.0), (0.05, 0.02)]);
+        let clean = Trajectory::from_tick_rate(frames, 10.0);
+        let mut crossed = clean.clone();
+        crossed.frames[1].swap(0, 1);
+        CleaningStep::Swaps { max_dist: 0.1 }.apply(&mut crossed);
+        assert_close(&crossed, &clean);
+    }
+
+    #[test]
+    fn frozen_frame_is_interpolated() {
+        let clean = walk(20);
+        let mut frozen = clean.clone();
+        frozen.frames[8] = frozen.frames[7];
+        let changes = CleaningStep::Frozen.apply(&mut frozen);
+        assert_close(&frozen, &clean);
+        assert_eq!(changes.len(), SWARM_SIZE);
+    }
+
+    #[test]
+    fn median_removes_a_spike() {
+        let clean = walk(20);
+        let mut spiked = clean.clone();
+        spiked.frames[10][2].0 += 0.5;
+        CleaningStep::Median { window: 5 }.apply(&mut spiked);
+        assert_close(&spiked, &clean);
+    }
+
+    #[test]
+    fn kalman_keeps_a_constant_velocity() {
+        let clean = walk(20);
+        let mut smoothed = clean.clone();
+        CleaningStep::Kalman {
+            accel: 0.1,
+            noise: 0.01,
+        }
+        .apply(&mut smoothed);
+        // the prior of zero velocity pulls the estimate by far less than the tracking noise
+        let max_dist = smoothed
+            .frames
+            .iter()
+            .zip(&clean.frames)
+            .map(|(a, b)| euclidean_dist(&a[4], &b[4]))
+            .fold(0.0, f64::max);
+        assert!(max_dist < 1e-3, "{max_dist}");
+    }
+
+    #[test]
+    fn clip_moves_positions_onto_the_border() {
+        let mut trajectory = walk(3);
+        let arena = Arena {
+            min: (0.0, 0.0),
+            max: (2.0, 2.0),
+        };
+        CleaningStep::Clip(arena).apply(&mut trajectory);
+        assert_eq!(trajectory.frames[2][14], (2.0, 0.02));
+        assert_eq!(trajectory.frames[2][1], (1.0, 0.02));
+    }
+
+    #[test]
+    fn steps_parse_their_names() {
+        let cleaning = Cleaning::parse(DEFAULT_STEPS, None).unwrap();
+        assert_eq!(cleaning.name(), DEFAULT_STEPS);
+        assert!(CleaningStep::parse("median:4", None).is_none());
+        assert!(CleaningStep::parse("clip", None).is_none());
+    }
+}
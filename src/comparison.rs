@@ -0,0 +1,483 @@
+use crate::{
+    distance::quantile, json::Json, metrics::METRIC_NAMES, utilities::Evaluator, SwarmMetric,
+};
+use rand::Rng;
+
+/// How the confidence intervals and the paired tests are computed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComparisonOptions {
+    /// bootstrap resamples of every mean
+    pub resamples: usize,
+    /// coverage of the percentile intervals
+    pub confidence: f64,
+    /// random sign flips of a paired test, all of them are enumerated for up to 16 seeds
+    pub permutations: usize,
+}
+
+impl Default for ComparisonOptions {
+    fn default() -> Self {
+        Self {
+            resamples: 10000,
+            confidence: 0.95,
+            permutations: 10000,
+        }
+    }
+}
+
+/// Mean over the seeds and its bootstrap confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub mean: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+/// Distances of one controller on every shared seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControllerResult {
+    pub name: String,
+    pub controller_cmd: Vec<String>,
+    /// one metric distance per seed
+    pub metrics: Vec<SwarmMetric>,
+    /// one cost per seed
+    pub costs: Vec<f64>,
+    pub metric_intervals: [Interval; 9],
+    pub cost_interval: Interval,
+}
+
+/// Paired permutation test of two controllers on one metric or the cost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairTest {
+    /// indices into `Comparison::controllers`
+    pub a: usize,
+    pub b: usize,
+    /// metric name or `cost`
+    pub quantity: String,
+    /// mean of `a - b` over the seeds
+    pub mean_diff: f64,
+    pub p_value: f64,
+    /// Holm corrected over all tests of the comparison
+    pub p_adjusted: f64,
+}
+
+/// Controllers evaluated on the same seeds, with confidence intervals and pairwise tests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub options: ComparisonOptions,
+    /// seeds every controller finished on, the others are dropped to keep the samples paired
+    pub seeds: Vec<i32>,
+    pub controllers: Vec<ControllerResult>,
+    pub tests: Vec<PairTest>,
+}
+
+impl Comparison {
+    /// simulates every named controller on every seed
+    pub fn run(
+        eval: &Evaluator,
+        controllers: &[(String, Vec<String>)],
+        seeds: &[i32],
+        options: ComparisonOptions,
+        rng: &mut impl Rng,
+    ) -> Result<Self, String> {
+        let evaluations = controllers
+            .iter()
+            .map(|(_, controller_cmd)| eval.eval_seeds(controller_cmd.clone(), seeds.to_vec()))
+            .collect::<Vec<_>>();
+
+        let shared = seeds
+            .iter()
+            .copied()
+            .filter(|seed| {
+                evaluations.iter().all(|data| {
+                    data.iter()
+                        .any(|(s, evaluation)| s == seed && evaluation.is_finite())
+                })
+            })
+            .collect::<Vec<i32>>();
+        if shared.len() < 2 {
+            return Err(format!(
+                "only {} of {} seeds finished for every controller",
+                shared.len(),
+                seeds.len()
+            ));
+        }
+
+        let mut samples = Vec::with_capacity(controllers.len());
+        for ((name, controller_cmd), data) in controllers.iter().zip(&evaluations) {
+            let evaluations = shared
+                .iter()
+                .map(|seed| &data.iter().find(|(s, _)| s == seed).unwrap().1)
+                .collect::<Vec<_>>();
+            samples.push((
+                name.clone(),
+                controller_cmd.clone(),
+                evaluations.iter().map(|x| x.metric).collect(),
+                evaluations.iter().map(|x| eval.cost(x)).collect(),
+            ));
+        }
+        return Ok(Self::new(shared, samples, options, rng));
+    }
+
+    /// intervals and tests of already evaluated controllers, given as name, command, one metric
+    /// distance and one cost per seed
+    #[allow(clippy::type_complexity)]
+    pub fn new(
+        seeds: Vec<i32>,
+        samples: Vec<(String, Vec<String>, Vec<SwarmMetric>, Vec<f64>)>,
+        options: ComparisonOptions,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let controllers = samples
+            .into_iter()
+            .map(|(name, controller_cmd, metrics, costs)| {
+                let mut metric_intervals = [Interval {
+                    mean: 0.0,
+                    low: 0.0,
+                    high: 0.0,
+                }; 9];
+                for (i, interval) in metric_intervals.iter_mut().enumerate() {
+                    let series = metrics.iter().map(|m| m[i]).collect::<Vec<f64>>();
+                    *interval = bootstrap(&series, options, rng);
+                }
+                let cost_interval = bootstrap(&costs, options, rng);
+                ControllerResult {
+                    name,
+                    controller_cmd,
+                    metrics,
+                    costs,
+                    metric_intervals,
+                    cost_interval,
+                }
+            })
+            .collect::<Vec<ControllerResult>>();
+
+        let mut tests = Vec::new();
+        for a in 0..controllers.len() {
+            for b in a + 1..controllers.len() {
+                let (ca, cb) = (&controllers[a], &controllers[b]);
+                for (i, name) in METRIC_NAMES.iter().enumerate() {
+                    let diffs = ca
+                        .metrics
+                        .iter()
+                        .zip(&cb.metrics)
+                        .map(|(x, y)| x[i] - y[i])
+                        .collect::<Vec<f64>>();
+                    tests.push(pair_test(a, b, name, &diffs, options, rng));
+                }
+                let diffs = ca
+                    .costs
+                    .iter()
+                    .zip(&cb.costs)
+                    .map(|(x, y)| x - y)
+                    .collect::<Vec<f64>>();
+                tests.push(pair_test(a, b, "cost", &diffs, options, rng));
+            }
+        }
+
+        let adjusted = holm(&tests.iter().map(|x| x.p_value).collect::<Vec<f64>>());
+        for (test, p) in tests.iter_mut().zip(adjusted) {
+            test.p_adjusted = p;
+        }
+
+        return Self {
+            options,
+            seeds,
+            controllers,
+            tests,
+        };
+    }
+
+    /// one row per metric with the mean and interval of every controller, then every pairwise
+    /// test with the corrected p value
+    pub fn to_table(&self) -> String {
+        let format = |x: &Interval| format!("{:.4} [{:.4}, {:.4}]", x.mean, x.low, x.high);
+
+        let mut out = format!("{:<24}", "metric");
+        for controller in &self.controllers {
+            out.push_str(&format!("\t{:<26}", controller.name));
+        }
+        out.push('\n');
+        for (i, name) in METRIC_NAMES.iter().enumerate() {
+            out.push_str(&format!("{name:<24}"));
+            for controller in &self.controllers {
+                out.push_str(&format!(
+                    "\t{:<26}",
+                    format(&controller.metric_intervals[i])
+                ));
+            }
+            out.push('\n');
+        }
+        out.push_str(&format!("{:<24}", "cost"));
+        for controller in &self.controllers {
+            out.push_str(&format!("\t{:<26}", format(&controller.cost_interval)));
+        }
+        out.push('\n');
+
+        out.push_str(&format!(
+            "\n{:<24}\t{:<24}\t{:>10}\t{:>8}\t{:>8}\n",
+            "pair", "metric", "mean diff", "p", "p holm"
+        ));
+        for test in &self.tests {
+            let pair = format!(
+                "{} - {}",
+                self.controllers[test.a].name, self.controllers[test.b].name
+            );
+            let significant = if test.p_adjusted < 1.0 - self.options.confidence {
+                " *"
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "{pair:<24}\t{:<24}\t{:>10.4}\t{:>8.4}\t{:>8.4}{significant}\n",
+                test.quantity, test.mean_diff, test.p_value, test.p_adjusted
+            ));
+        }
+        return out;
+    }
+
+    pub fn to_json(&self) -> Json {
+        let interval = |x: &Interval| {
+            vec![
+                ("mean".to_string(), Json::from(x.mean)),
+                ("low".to_string(), Json::from(x.low)),
+                ("high".to_string(), Json::from(x.high)),
+            ]
+        };
+
+        let controllers = self
+            .controllers
+            .iter()
+            .map(|controller| {
+                let metrics = METRIC_NAMES
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| {
+                        let series = controller
+                            .metrics
+                            .iter()
+                            .map(|m| m[i])
+                            .collect::<Vec<f64>>();
+                        let mut metric = interval(&controller.metric_intervals[i]);
+                        metric.push(("per_seed".to_string(), Json::from(series.as_slice())));
+                        (name.to_string(), Json::Object(metric))
+                    })
+                    .collect();
+                Json::Object(vec![
+                    ("name".to_string(), Json::from(controller.name.as_str())),
+                    (
+                        "controller".to_string(),
+                        Json::from(controller.controller_cmd.join(" ")),
+                    ),
+                    ("metrics".to_string(), Json::Object(metrics)),
+                    (
+                        "cost".to_string(),
+                        Json::Object(interval(&controller.cost_interval)),
+                    ),
+                    ("costs".to_string(), Json::from(controller.costs.as_slice())),
+                ])
+            })
+            .collect();
+
+        let tests = self
+            .tests
+            .iter()
+            .map(|test| {
+                Json::Object(vec![
+                    (
+                        "a".to_string(),
+                        Json::from(self.controllers[test.a].name.as_str()),
+                    ),
+                    (
+                        "b".to_string(),
+                        Json::from(self.controllers[test.b].name.as_str()),
+                    ),
+                    ("quantity".to_string(), Json::from(test.quantity.as_str())),
+                    ("mean_diff".to_string(), Json::from(test.mean_diff)),
+                    ("p".to_string(), Json::from(test.p_value)),
+                    ("p_holm".to_string(), Json::from(test.p_adjusted)),
+                ])
+            })
+            .collect();
+
+        let seeds = self.seeds.iter().map(|x| *x as f64).collect::<Vec<f64>>();
+        return Json::Object(vec![
+            ("seeds".to_string(), Json::from(seeds.as_slice())),
+            (
+                "resamples".to_string(),
+                Json::from(self.options.resamples as f64),
+            ),
+            (
+                "confidence".to_string(),
+                Json::from(self.options.confidence),
+            ),
+            ("controllers".to_string(), Json::Array(controllers)),
+            ("tests".to_string(), Json::Array(tests)),
+        ]);
+    }
+}
+
+fn pair_test(
+    a: usize,
+    b: usize,
+    quantity: &str,
+    diffs: &[f64],
+    options: ComparisonOptions,
+    rng: &mut impl Rng,
+) -> PairTest {
+    return PairTest {
+        a,
+        b,
+        quantity: quantity.to_string(),
+        mean_diff: diffs.iter().sum::<f64>() / diffs.len() as f64,
+        p_value: sign_flip_test(diffs, options.permutations, rng),
+        p_adjusted: f64::NAN,
+    };
+}
+
+/// mean and percentile interval of `resamples` means of samples drawn with replacement
+pub fn bootstrap(vals: &[f64], options: ComparisonOptions, rng: &mut impl Rng) -> Interval {
+    let mean = vals.iter().sum::<f64>() / vals.len() as f64;
+
+    let mut means = Vec::with_capacity(options.resamples);
+    for _ in 0..options.resamples {
+        let mut sum = 0.0;
+        for _ in 0..vals.len() {
+            sum += vals[rng.gen_range(0..vals.len())];
+        }
+        means.push(sum / vals.len() as f64);
+    }
+    if means.is_empty() {
+        return Interval {
+            mean,
+            low: mean,
+            high: mean,
+        };
+    }
+    means.sort_by(f64::total_cmp);
+
+    let alpha = (1.0 - options.confidence) / 2.0;
+    return Interval {
+        mean,
+        low: quantile(&means, alpha),
+        high: quantile(&means, 1.0 - alpha),
+    };
+}
+
+/// two sided p value of the paired differences under random sign flips, every sign pattern is
+/// enumerated for up to 16 differences, otherwise `permutations` random ones are drawn
+pub fn sign_flip_test(diffs: &[f64], permutations: usize, rng: &mut impl Rng) -> f64 {
+    let observed = diffs.iter().sum::<f64>().abs();
+    // differences of rounding must not count as more extreme
+    let tolerance = 1e-12 * diffs.iter().map(|x| x.abs()).sum::<f64>();
+    let flipped_sum = |signs: &dyn Fn(usize) -> bool| {
+        diffs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| if signs(i) { -x } else { *x })
+            .sum::<f64>()
+            .abs()
+    };
+
+    if diffs.len() <= 16 {
+        let total = 1_usize << diffs.len();
+        let extreme = (0..total)
+            .filter(|mask| flipped_sum(&|i| mask & (1 << i) != 0) >= observed - tolerance)
+            .count();
+        return extreme as f64 / total as f64;
+    }
+
+    let mut extreme = 0;
+    for _ in 0..permutations {
+        let signs = (0..diffs.len())
+            .map(|_| rng.gen_bool(0.5))
+            .collect::<Vec<bool>>();
+        if flipped_sum(&|i| signs[i]) >= observed - tolerance {
+            extreme += 1;
+        }
+    }
+    return (extreme + 1) as f64 / (permutations + 1) as f64;
+}
+
+/// Holm-Bonferroni adjusted p values, in the order of `p_values`
+pub fn holm(p_values: &[f64]) -> Vec<f64> {
+    let mut order = (0..p_values.len()).collect::<Vec<usize>>();
+    order.sort_by(|&a, &b| p_values[a].total_cmp(&p_values[b]));
+
+    let mut result = vec![0.0; p_values.len()];
+    let mut running_max: f64 = 0.0;
+    for (rank, &i) in order.iter().enumerate() {
+        let adjusted = f64::min(1.0, (p_values.len() - rank) as f64 * p_values[i]);
+        running_max = running_max.max(adjusted);
+        result[i] = running_max;
+    }
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn bootstrap_interval_contains_the_mean() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let options = ComparisonOptions {
+            resamples: 2000,
+            ..Default::default()
+        };
+        let vals = [0.3, 0.5, 0.1, 0.9, 0.4, 0.6, 0.2, 0.8];
+        let interval = bootstrap(&vals, options, &mut rng);
+        assert!((interval.mean - 0.475).abs() < 1e-12);
+        assert!(interval.low < interval.mean && interval.mean < interval.high);
+        assert!(interval.low >= 0.1 && interval.high <= 0.9);
+
+        // a constant sample has no spread
+        let interval = bootstrap(&[0.25; 5], options, &mut rng);
+        assert_eq!((interval.low, interval.high), (0.25, 0.25));
+
+        let options = ComparisonOptions {
+            resamples: 0,
+            ..options
+        };
+        let interval = bootstrap(&vals, options, &mut rng);
+        assert_eq!(
+            (interval.low, interval.high),
+            (interval.mean, interval.mean)
+        );
+    }
+
+    #[test]
+    fn sign_flip_test_enumerates_small_samples() {
+        let mut rng = StdRng::seed_from_u64(7);
+        // only flipping all or no signs gives a sum of 6 in absolute value
+        assert_eq!(sign_flip_test(&[1.0, 2.0, 3.0], 0, &mut rng), 2.0 / 8.0);
+        // |±1 ±2 ±4| >= 3 for all but the patterns summing to ±1
+        assert_eq!(sign_flip_test(&[1.0, -2.0, 4.0], 0, &mut rng), 6.0 / 8.0);
+        assert_eq!(sign_flip_test(&[0.5, -0.5], 0, &mut rng), 1.0);
+    }
+
+    #[test]
+    fn sign_flip_test_samples_large_samples() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let p = sign_flip_test(&[1.0; 20], 999, &mut rng);
+        // the observed pattern always counts
+        assert!((0.001..0.01).contains(&p), "{p}");
+        let p = sign_flip_test(
+            &(0..20).map(|i| (-1.0_f64).powi(i)).collect::<Vec<_>>(),
+            999,
+            &mut rng,
+        );
+        assert_eq!(p, 1.0);
+    }
+
+    #[test]
+    fn holm_adjusts_in_input_order() {
+        let adjusted = holm(&[0.01, 0.04, 0.03, 0.005]);
+        let expected = [0.03, 0.06, 0.06, 0.02];
+        for (p, expected) in adjusted.iter().zip(expected) {
+            assert!((p - expected).abs() < 1e-12, "{adjusted:?}");
+        }
+        assert_eq!(holm(&[0.5, 0.6]), [1.0, 1.0]);
+        assert!(holm(&[]).is_empty());
+    }
+}
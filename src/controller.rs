@@ -0,0 +1,136 @@
+use crate::{bt::BehaviourTree, fsm::Fsm};
+use rand::Rng;
+
+/// Control software architecture the simulator is configured with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ControllerKind {
+    Fsm,
+    BehaviourTree,
+}
+
+/// Controller of either architecture, the arguments tell which one it is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Controller {
+    Fsm(Fsm),
+    BehaviourTree(BehaviourTree),
+}
+
+impl ControllerKind {
+    /// `fsm` or `bt`
+    pub fn parse(name: &str) -> Option<Self> {
+        return match name.trim() {
+            "fsm" => Some(ControllerKind::Fsm),
+            "bt" => Some(ControllerKind::BehaviourTree),
+            _ => None,
+        };
+    }
+
+    /// the kind of the controller arguments, behaviour trees are the ones with a `--nroot`
+    pub fn of(controller_cmd: &[String]) -> Self {
+        if controller_cmd.iter().any(|x| x.trim() == "--nroot") {
+            return ControllerKind::BehaviourTree;
+        }
+        return ControllerKind::Fsm;
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ControllerKind::Fsm => "fsm",
+            ControllerKind::BehaviourTree => "bt",
+        }
+    }
+
+    /// the simulator flag the controller arguments follow
+    pub fn config_flag(&self) -> &'static str {
+        match self {
+            ControllerKind::Fsm => "--fsm-config",
+            ControllerKind::BehaviourTree => "--bt-config",
+        }
+    }
+}
+
+impl Controller {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        return match ControllerKind::of(args) {
+            ControllerKind::Fsm => Ok(Controller::Fsm(Fsm::parse(args)?)),
+            ControllerKind::BehaviourTree => {
+                Ok(Controller::BehaviourTree(BehaviourTree::parse(args)?))
+            }
+        };
+    }
+
+    pub fn random(kind: ControllerKind, rng: &mut impl Rng) -> Self {
+        return match kind {
+            ControllerKind::Fsm => Controller::Fsm(Fsm::random(rng)),
+            ControllerKind::BehaviourTree => Controller::BehaviourTree(BehaviourTree::random(rng)),
+        };
+    }
+
+    /// copy with one random change that keeps the architecture
+    pub fn mutate(&self, rng: &mut impl Rng) -> Self {
+        return match self {
+            Controller::Fsm(fsm) => Controller::Fsm(fsm.mutate(rng)),
+            Controller::BehaviourTree(tree) => Controller::BehaviourTree(tree.mutate(rng)),
+        };
+    }
+
+    pub fn kind(&self) -> ControllerKind {
+        match self {
+            Controller::Fsm(_) => ControllerKind::Fsm,
+            Controller::BehaviourTree(_) => ControllerKind::BehaviourTree,
+        }
+    }
+
+    /// the arguments to pass after `kind().config_flag()`
+    pub fn to_args(&self) -> Vec<String> {
+        return match self {
+            Controller::Fsm(fsm) => fsm.to_args(),
+            Controller::BehaviourTree(tree) => tree.to_args(),
+        };
+    }
+
+    pub fn to_cmd(&self) -> String {
+        return self.to_args().join(" ");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsm::split_cmd;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn kind_of_the_arguments() {
+        let fsm = split_cmd("--nstates 1 --s0 1");
+        let tree = split_cmd("--nroot 3 --nchildroot 1 --n0 5 --a0 1");
+        assert_eq!(ControllerKind::of(&fsm), ControllerKind::Fsm);
+        assert_eq!(ControllerKind::of(&tree), ControllerKind::BehaviourTree);
+        assert!(matches!(Controller::parse(&fsm), Ok(Controller::Fsm(_))));
+        assert!(matches!(
+            Controller::parse(&tree),
+            Ok(Controller::BehaviourTree(_))
+        ));
+
+        for kind in [ControllerKind::Fsm, ControllerKind::BehaviourTree] {
+            assert_eq!(ControllerKind::parse(kind.name()), Some(kind));
+        }
+        assert_eq!(ControllerKind::parse("nn"), None);
+    }
+
+    #[test]
+    fn mutations_keep_the_architecture() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for kind in [ControllerKind::Fsm, ControllerKind::BehaviourTree] {
+            let mut controller = Controller::random(kind, &mut rng);
+            for _ in 0..20 {
+                controller = controller.mutate(&mut rng);
+                assert_eq!(controller.kind(), kind);
+                assert_eq!(
+                    Controller::parse(&controller.to_args()).unwrap(),
+                    controller
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{json::Json, metrics::METRIC_NAMES, SwarmMetric};
+
+/// How the per-tick differences are smoothed and when a metric counts as diverged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurveOptions {
+    /// ticks of the centered moving average, 1 disables smoothing
+    pub smoothing: usize,
+    /// normalized difference above which a tick counts as off
+    pub threshold: f64,
+    /// ticks the smoothed difference has to stay above the threshold
+    pub sustain: usize,
+}
+
+impl Default for CurveOptions {
+    fn default() -> Self {
+        Self {
+            smoothing: 21,
+            threshold: 0.1,
+            sustain: 50,
+        }
+    }
+}
+
+/// Per-tick, per-metric normalized differences between a simulation and the real runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceCurves {
+    pub options: CurveOptions,
+    /// absolute difference at every tick, aggregated over the real runs
+    pub per_tick: Vec<SwarmMetric>,
+    pub smoothed: Vec<SwarmMetric>,
+    /// first tick of every metric from which the smoothed difference stays above the
+    /// threshold for `sustain` ticks, `None` if it never does
+    pub divergence: [Option<usize>; 9],
+}
+
+impl DistanceCurves {
+    pub fn new(per_tick: Vec<SwarmMetric>, options: CurveOptions) -> Self {
+        let smoothed = smooth(&per_tick, options.smoothing);
+
+        let mut divergence = [None; 9];
+        for (i, tick) in divergence.iter_mut().enumerate() {
+            let series = smoothed.iter().map(|m| m[i]).collect::<Vec<f64>>();
+            *tick = divergence_time(&series, options.threshold, options.sustain);
+        }
+
+        return Self {
+            options,
+            per_tick,
+            smoothed,
+            divergence,
+        };
+    }
+
+    /// one line per tick: `tick`, then the raw and the smoothed difference of every metric
+    pub fn to_csv(&self) -> String {
+        let mut out = "tick".to_string();
+        for name in METRIC_NAMES {
+            out.push_str(&format!(",{name}"));
+        }
+        for name in METRIC_NAMES {
+            out.push_str(&format!(",{name}_smoothed"));
+        }
+        out.push('\n');
+
+        for (t, (raw, smoothed)) in self.per_tick.iter().zip(&self.smoothed).enumerate() {
+            out.push_str(&format!("{t}"));
+            for val in raw.iter().chain(smoothed.iter()) {
+                out.push_str(&format!(",{val}"));
+            }
+            out.push('\n');
+        }
+        return out;
+    }
+
+    pub fn to_json(&self) -> Json {
+        let series = |data: &[SwarmMetric], i: usize| {
+            Json::from(data.iter().map(|m| m[i]).collect::<Vec<f64>>().as_slice())
+        };
+
+        let metrics = METRIC_NAMES
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let divergence = self.divergence[i]
+                    .map(|t| Json::from(t as f64))
+                    .unwrap_or(Json::Null);
+                let curve = Json::Object(vec![
+                    ("per_tick".to_string(), series(&self.per_tick, i)),
+                    ("smoothed".to_string(), series(&self.smoothed, i)),
+                    ("divergence".to_string(), divergence),
+                ]);
+                (name.to_string(), curve)
+            })
+            .collect();
+
+        return Json::Object(vec![
+            (
+                "smoothing".to_string(),
+                Json::from(self.options.smoothing as f64),
+            ),
+            ("threshold".to_string(), Json::from(self.options.threshold)),
+            (
+                "sustain".to_string(),
+                Json::from(self.options.sustain as f64),
+            ),
+            ("metrics".to_string(), Json::Object(metrics)),
+        ]);
+    }
+}
+
+/// centered moving average, the window shrinks at both ends
+pub fn smooth(data: &[SwarmMetric], window: usize) -> Vec<SwarmMetric> {
+    let half = window / 2;
+    let mut result = Vec::with_capacity(data.len());
+    for t in 0..data.len() {
+        let start = t.saturating_sub(half);
+        let end = usize::min(t + half + 1, data.len());
+
+        let mut mean = SwarmMetric::default();
+        for metric in &data[start..end] {
+            for i in 0..mean.len() {
+                mean[i] += metric[i] / (end - start) as f64;
+            }
+        }
+        result.push(mean);
+    }
+    return result;
+}
+
+/// first index from which `series` stays above `threshold` for at least `sustain` values
+pub fn divergence_time(series: &[f64], threshold: f64, sustain: usize) -> Option<usize> {
+    let mut start = None;
+    for (t, &val) in series.iter().enumerate() {
+        if val <= threshold {
+            start = None;
+            continue;
+        }
+        let first = *start.get_or_insert(t);
+        if t + 1 - first >= sustain {
+            return Some(first);
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_diverges_at_the_step() {
+        let series = [[0.0; 10], [1.0; 10]].concat();
+        assert_eq!(divergence_time(&series, 0.5, 5), Some(10));
+        assert_eq!(divergence_time(&series, 0.5, 10), Some(10));
+        assert_eq!(divergence_time(&series, 0.5, 11), None);
+        assert_eq!(divergence_time(&series, 1.0, 1), None);
+    }
+
+    #[test]
+    fn short_excursions_do_not_diverge() {
+        let series = [0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 1.0, 0.0];
+        assert_eq!(divergence_time(&series, 0.5, 3), Some(4));
+        assert_eq!(divergence_time(&series, 0.5, 4), None);
+        assert_eq!(divergence_time(&[], 0.5, 1), None);
+    }
+
+    #[test]
+    fn smoothing_shrinks_at_the_ends() {
+        let data = [0.0, 3.0, 6.0, 0.0].map(|x| [x; 9]);
+        let smoothed = smooth(&data, 3);
+        assert_eq!(
+            smoothed.iter().map(|m| m[0]).collect::<Vec<f64>>(),
+            [1.5, 3.0, 3.0, 3.0]
+        );
+        assert_eq!(smooth(&data, 1), data);
+    }
+
+    #[test]
+    fn curves_of_a_step() {
+        let per_tick = (0..100)
+            .map(|t| {
+                let mut metric = SwarmMetric::default();
+                metric[2] = if t < 40 { 0.0 } else { 1.0 };
+                metric
+            })
+            .collect::<Vec<SwarmMetric>>();
+        let options = CurveOptions {
+            smoothing: 5,
+            threshold: 0.5,
+            sustain: 20,
+        };
+        let curves = DistanceCurves::new(per_tick, options);
+        // the centered mean of 5 ticks first exceeds 0.5 when 3 of them are past the step
+        assert_eq!(curves.divergence[2], Some(40));
+        assert_eq!(curves.divergence[0], None);
+        assert_eq!(curves.to_csv().lines().count(), 101);
+    }
+}
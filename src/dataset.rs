@@ -0,0 +1,399 @@
+use crate::{
+    formats::read_trajectory,
+    trajectory::{Resampling, Trajectory},
+    SwarmMetric, SwarmPos, SWARM_SIZE,
+};
+use std::path::Path;
+
+/// frames per second assumed for recordings without a time column or a known tick rate
+pub const DEFAULT_TICK_RATE: f64 = 10.0;
+
+/// One real-world recording of a mission.
+///
+/// The metadata is only known for runs listed in a manifest, it is `None` for the builtin run,
+/// the files of a directory without a manifest and empty manifest fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RealRun {
+    pub name: String,
+    pub mission: Option<String>,
+    pub date: Option<String>,
+    /// as stated by the manifest, the trajectory always has `SWARM_SIZE` robots
+    pub robot_count: Option<usize>,
+    /// nominal frames per second of the recording
+    pub tick_rate: Option<f64>,
+    /// timestamps come from the recording if it has a time column, otherwise from `tick_rate` or
+    /// `DEFAULT_TICK_RATE`
+    pub trajectory: Trajectory,
+}
+
+/// All real runs available to the evaluator, possibly of several missions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dataset {
+    pub runs: Vec<RealRun>,
+}
+
+/// How the distances to several real runs of a mission are combined into one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunAggregation {
+    /// element wise mean over all runs
+    #[default]
+    Mean,
+    /// the run with the lowest average distance
+    Best,
+    /// the run with the highest average distance
+    Worst,
+}
+
+impl RunAggregation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunAggregation::Mean => "mean",
+            RunAggregation::Best => "best",
+            RunAggregation::Worst => "worst",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "mean" => Some(RunAggregation::Mean),
+            "best" => Some(RunAggregation::Best),
+            "worst" => Some(RunAggregation::Worst),
+            _ => None,
+        }
+    }
+
+    pub fn aggregate(&self, dists: &[SwarmMetric]) -> SwarmMetric {
+        assert!(!dists.is_empty());
+        if let Some(i) = self.select(dists) {
+            return dists[i];
+        }
+
+        let mut result = SwarmMetric::default();
+        for dist in dists {
+            for i in 0..result.len() {
+                result[i] += dist[i] / dists.len() as f64;
+            }
+        }
+        return result;
+    }
+
+    /// index of the run whose distances `aggregate` takes, `None` for the mean over all runs,
+    /// other values of the same simulation, e.g. its trajectory errors, are taken from this run
+    pub fn select(&self, dists: &[SwarmMetric]) -> Option<usize> {
+        let cost = |m: &SwarmMetric| m.iter().sum::<f64>() / m.len() as f64;
+        let runs = dists.iter().enumerate();
+        return match self {
+            RunAggregation::Mean => None,
+            RunAggregation::Best => runs.min_by(|a, b| cost(a.1).total_cmp(&cost(b.1))),
+            RunAggregation::Worst => runs.max_by(|a, b| cost(a.1).total_cmp(&cost(b.1))),
+        }
+        .map(|x| x.0);
+    }
+}
+
+impl RealRun {
+    /// the trajectory on the grid of the simulator, fails naming the run if it has no frames
+    pub fn resample_to_rate(
+        &self,
+        tick_rate: f64,
+        resampling: Resampling,
+    ) -> Result<Trajectory, String> {
+        return self
+            .trajectory
+            .resample_to_rate(tick_rate, resampling)
+            .map_err(|e| format!("real run {}: {e}", self.name));
+    }
+}
+
+impl Dataset {
+    /// the recording embedded in the binary
+    pub fn builtin() -> Self {
+        return Self {
+            runs: vec![RealRun {
+                name: "all_bot_pos".to_string(),
+                mission: None,
+                date: None,
+                robot_count: None,
+                tick_rate: None,
+                trajectory: parse_timed_bot_pos_csv(
+                    include_str!("all_bot_pos.csv"),
+                    DEFAULT_TICK_RATE,
+                )
+                .unwrap(),
+            }],
+        };
+    }
+
+    /// loads a manifest file, or a directory with a `manifest.csv` or otherwise every trajectory file in it
+    ///
+    /// a manifest has one run per line: `file, mission, date, robot_count, tick_rate`,
+    /// file paths are relative to the manifest, the format of each file is detected and all
+    /// fields but the file may be left empty if they are unknown
+    pub fn load(path: &str) -> Result<Self, String> {
+        let path = Path::new(path);
+        if path.is_file() {
+            return Self::load_manifest(path);
+        }
+
+        let manifest = path.join("manifest.csv");
+        if manifest.is_file() {
+            return Self::load_manifest(&manifest);
+        }
+
+        let mut files = std::fs::read_dir(path)
+            .map_err(|e| format!("{}: {e}", path.display()))?
+            .filter_map(|entry| entry.ok().map(|x| x.path()))
+            .filter(|x| {
+                x.extension()
+                    .is_some_and(|ext| TRAJECTORY_EXTENSIONS.iter().any(|e| ext == *e))
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+
+        let mut runs = Vec::with_capacity(files.len());
+        for file in files {
+            runs.push(RealRun {
+                name: file_name(&file),
+                mission: None,
+                date: None,
+                robot_count: None,
+                tick_rate: None,
+                trajectory: read_bot_pos(&file, DEFAULT_TICK_RATE)?,
+            });
+        }
+
+        if runs.is_empty() {
+            return Err(format!("no real runs found in {}", path.display()));
+        }
+        return Ok(Self { runs });
+    }
+
+    fn load_manifest(path: &Path) -> Result<Self, String> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+
+        let mut runs = Vec::new();
+        for line in content.split("\n") {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split(",").map(|s| s.trim()).collect::<Vec<&str>>();
+            if fields[0] == "file" {
+                // header
+                continue;
+            }
+            if fields.len() != 5 {
+                return Err(format!(
+                    "{}: expected file, mission, date, robot_count, tick_rate but got {line}",
+                    path.display()
+                ));
+            }
+
+            let known = |field: &str| Some(field.to_string()).filter(|x| !x.is_empty());
+            let robot_count = known(fields[3])
+                .map(|x| x.parse::<usize>())
+                .transpose()
+                .map_err(|_| format!("invalid robot count {}", fields[3]))?;
+            if let Some(robot_count) = robot_count.filter(|&x| x != SWARM_SIZE) {
+                return Err(format!(
+                    "{} has {robot_count} robots but the evaluator expects {SWARM_SIZE}",
+                    fields[0]
+                ));
+            }
+
+            let file = dir.join(fields[0]);
+            let tick_rate = known(fields[4])
+                .map(|x| x.parse::<f64>())
+                .transpose()
+                .map_err(|_| format!("invalid tick rate {}", fields[4]))?;
+            runs.push(RealRun {
+                name: file_name(&file),
+                mission: known(fields[1]),
+                date: known(fields[2]),
+                robot_count,
+                tick_rate,
+                trajectory: read_bot_pos(&file, tick_rate.unwrap_or(DEFAULT_TICK_RATE))?,
+            });
+        }
+
+        if runs.is_empty() {
+            return Err(format!("{} lists no runs", path.display()));
+        }
+        return Ok(Self { runs });
+    }
+
+    /// only the runs of one mission
+    pub fn mission(self, mission: &str) -> Result<Self, String> {
+        let runs = self
+            .runs
+            .into_iter()
+            .filter(|x| x.mission.as_deref() == Some(mission))
+            .collect::<Vec<RealRun>>();
+
+        if runs.is_empty() {
+            return Err(format!("no real runs of mission {mission}"));
+        }
+        return Ok(Self { runs });
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    return path
+        .file_stem()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+}
+
+/// files of a directory that are loaded as real runs
+const TRAJECTORY_EXTENSIONS: [&str; 5] = ["csv", "jsonl", "ndjson", "log", "txt"];
+
+fn read_bot_pos(path: &Path, tick_rate: f64) -> Result<Trajectory, String> {
+    return read_trajectory(path, None, tick_rate);
+}
+
+/// parses the `botN_x,botN_y` layout of `all_bot_pos.csv`, the first line is the header
+pub fn parse_bot_pos_csv(content: &str) -> Result<Vec<SwarmPos>, String> {
+    return Ok(parse_timed_bot_pos_csv(content, 1.0)?.frames);
+}
+
+/// like `parse_bot_pos_csv`, but an optional first `time` column holds the timestamp in seconds,
+/// without it the frames are `1 / tick_rate` apart
+pub fn parse_timed_bot_pos_csv(content: &str, tick_rate: f64) -> Result<Trajectory, String> {
+    let mut line_it = content.split("\n");
+
+    let head = line_it
+        .next()
+        .ok_or("empty csv")?
+        .split(",")
+        .map(|s| s.trim())
+        .collect::<Vec<&str>>();
+
+    let timed = matches!(
+        head.first(),
+        Some(&"time") | Some(&"t") | Some(&"timestamp")
+    );
+    let offset = if timed { 1 } else { 0 };
+    if head.len() != 2 * SWARM_SIZE + offset {
+        return Err(format!(
+            "expected {} columns but got {}",
+            2 * SWARM_SIZE + offset,
+            head.len()
+        ));
+    }
+
+    let mut times = Vec::new();
+    let mut bot_pos = Vec::new();
+
+    for line in line_it {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let vals = line
+            .split(",")
+            .map(|s| s.trim().parse().map_err(|_| format!("invalid value {s}")))
+            .collect::<Result<Vec<f64>, String>>()?;
+
+        if vals.len() != 2 * SWARM_SIZE + offset {
+            return Err(format!(
+                "expected {} columns but got {}",
+                2 * SWARM_SIZE + offset,
+                vals.len()
+            ));
+        }
+
+        if timed {
+            times.push(vals[0]);
+        }
+        let mut data = SwarmPos::default();
+        for (i, &val) in vals[offset..].iter().enumerate() {
+            if i % 2 == 0 {
+                data[i / 2].0 = val;
+            } else {
+                data[(i - 1) / 2].1 = val;
+            }
+        }
+        bot_pos.push(data)
+    }
+
+    if timed {
+        return Ok(Trajectory::new(times, bot_pos));
+    }
+    return Ok(Trajectory::from_tick_rate(bot_pos, tick_rate));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_run_fails_with_its_name() {
+        let mut run = Dataset::builtin().runs.remove(0);
+        assert!(run.resample_to_rate(10.0, Resampling::Linear).is_ok());
+
+        run.trajectory = Trajectory::new(Vec::new(), Vec::new());
+        for resampling in [Resampling::None, Resampling::Linear] {
+            let err = run.resample_to_rate(10.0, resampling).unwrap_err();
+            assert_eq!(err, "real run all_bot_pos: trajectory has no frames");
+        }
+    }
+
+    #[test]
+    fn best_and_worst_select_one_run() {
+        let dists = [[0.2; 9], [0.1; 9], [0.6; 9]];
+        assert_eq!(RunAggregation::Mean.select(&dists), None);
+        assert_eq!(RunAggregation::Best.select(&dists), Some(1));
+        assert_eq!(RunAggregation::Worst.select(&dists), Some(2));
+        assert_eq!(RunAggregation::Best.aggregate(&dists), [0.1; 9]);
+        assert_eq!(RunAggregation::Worst.aggregate(&dists), [0.6; 9]);
+        for (mean, expected) in RunAggregation::Mean.aggregate(&dists).iter().zip([0.3; 9]) {
+            assert!((mean - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn manifest_keeps_unknown_metadata_unknown() {
+        let dir = std::env::temp_dir().join(format!("dataset-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut csv = (0..SWARM_SIZE)
+            .map(|i| format!("bot{i}_x,bot{i}_y"))
+            .collect::<Vec<String>>()
+            .join(",");
+        csv.push('\n');
+        for t in 0..3 {
+            csv.push_str(&vec![format!("{t}"); 2 * SWARM_SIZE].join(","));
+            csv.push('\n');
+        }
+        std::fs::write(dir.join("a.csv"), &csv).unwrap();
+        std::fs::write(dir.join("b.csv"), &csv).unwrap();
+        std::fs::write(
+            dir.join("manifest.csv"),
+            "file, mission, date, robot_count, tick_rate\na.csv, aggregation, 2024-05-01, 15, 5\nb.csv, , , , \n",
+        )
+        .unwrap();
+
+        let dataset = Dataset::load(&dir.to_string_lossy()).unwrap();
+        let [a, b] = [&dataset.runs[0], &dataset.runs[1]];
+        assert_eq!(a.mission.as_deref(), Some("aggregation"));
+        assert_eq!(a.robot_count, Some(SWARM_SIZE));
+        assert_eq!(a.tick_rate, Some(5.0));
+        assert_eq!(a.trajectory.times[1], 0.2);
+        assert_eq!((b.mission.clone(), b.date.clone()), (None, None));
+        assert_eq!((b.robot_count, b.tick_rate), (None, None));
+        assert_eq!(b.trajectory.times[1], 1.0 / DEFAULT_TICK_RATE);
+
+        let missions = dataset.mission("aggregation").unwrap();
+        assert_eq!(missions.runs.len(), 1);
+
+        std::fs::remove_file(dir.join("manifest.csv")).unwrap();
+        let dataset = Dataset::load(&dir.to_string_lossy()).unwrap();
+        assert_eq!(dataset.runs.len(), 2);
+        assert!(dataset.runs.iter().all(|x| x.mission.is_none()));
+        assert!(dataset.mission("aggregation").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,438 @@
+use crate::SwarmMetric;
+
+/// How the simulated time series of one metric is compared with the real one.
+///
+/// All measures work on normalized metrics. `TickAligned` is the original measure, it assumes
+/// tick i of the simulation corresponds to tick i of the real run. Two empty series have a
+/// distance of 0, an empty series is infinitely far from a non-empty one.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DistanceMeasure {
+    /// square root of the summed squared differences of tick i against tick i
+    #[default]
+    TickAligned,
+    /// dynamic time warping with squared differences as local cost, ticks may only be
+    /// matched if they are at most `window` ticks apart (Sakoe-Chiba band)
+    Dtw { window: usize },
+    /// shifts the simulated series by up to `max_lag` ticks in both directions and keeps the
+    /// shift with the smallest mean squared difference over the overlap, which is scaled to
+    /// the full length and reported as a root like `TickAligned`
+    LaggedMse { max_lag: usize },
+    /// euclidean distance between the summary statistics (mean, variance and the 10%, 25%,
+    /// 50%, 75% and 90% quantiles) of both series
+    Summary,
+    /// Kolmogorov–Smirnov statistic between the value distributions of both series
+    KolmogorovSmirnov,
+    /// 1-Wasserstein (earth mover's) distance between the value distributions of both series
+    Wasserstein,
+}
+
+impl DistanceMeasure {
+    pub fn name(&self) -> String {
+        match self {
+            DistanceMeasure::TickAligned => "tick".to_string(),
+            DistanceMeasure::Dtw { window } => format!("dtw:{window}"),
+            DistanceMeasure::LaggedMse { max_lag } => format!("lagged_mse:{max_lag}"),
+            DistanceMeasure::Summary => "summary".to_string(),
+            DistanceMeasure::KolmogorovSmirnov => "ks".to_string(),
+            DistanceMeasure::Wasserstein => "wasserstein".to_string(),
+        }
+    }
+
+    /// parses the format returned by `name`, e.g. `tick`, `dtw:50` or `lagged_mse:20`
+    pub fn parse(name: &str) -> Option<Self> {
+        let mut it = name.trim().split(":");
+        let kind = it.next()?;
+        let arg = it.next().map(|x| x.trim().parse::<usize>());
+        if it.next().is_some() {
+            return None;
+        }
+
+        return match (kind, arg) {
+            ("tick", None) => Some(DistanceMeasure::TickAligned),
+            ("dtw", Some(Ok(window))) => Some(DistanceMeasure::Dtw { window }),
+            ("lagged_mse", Some(Ok(max_lag))) => Some(DistanceMeasure::LaggedMse { max_lag }),
+            ("summary", None) => Some(DistanceMeasure::Summary),
+            ("ks", None) => Some(DistanceMeasure::KolmogorovSmirnov),
+            ("wasserstein", None) => Some(DistanceMeasure::Wasserstein),
+            _ => None,
+        };
+    }
+
+    /// parses a comma separated list with either one measure for all metrics or one per metric
+    pub fn parse_all(names: &str) -> Option<[DistanceMeasure; 9]> {
+        let measures = names
+            .split(",")
+            .map(DistanceMeasure::parse)
+            .collect::<Option<Vec<DistanceMeasure>>>()?;
+
+        let mut result = [DistanceMeasure::default(); 9];
+        match measures.len() {
+            1 => result = [measures[0]; 9],
+            9 => result.copy_from_slice(&measures),
+            _ => return None,
+        }
+        return Some(result);
+    }
+
+    /// distance between two time series of the same metric
+    pub fn dist(&self, sim: &[f64], real: &[f64]) -> f64 {
+        return match self {
+            DistanceMeasure::TickAligned => tick_aligned(sim, real),
+            DistanceMeasure::Dtw { window } => dtw(sim, real, *window),
+            DistanceMeasure::LaggedMse { max_lag } => lagged_mse(sim, real, *max_lag),
+            DistanceMeasure::Summary => summary_dist(sim, real),
+            DistanceMeasure::KolmogorovSmirnov => kolmogorov_smirnov(sim, real),
+            DistanceMeasure::Wasserstein => wasserstein(sim, real),
+        };
+    }
+}
+
+/// applies the distance measure of each metric to the normalized simulated and real metrics
+pub fn series_dist(
+    sim_metric_norm: &[SwarmMetric],
+    real_metric_norm: &[SwarmMetric],
+    measures: &[DistanceMeasure; 9],
+) -> SwarmMetric {
+    let mut result = SwarmMetric::default();
+    for i in 0..result.len() {
+        let sim = sim_metric_norm.iter().map(|m| m[i]).collect::<Vec<f64>>();
+        let real = real_metric_norm.iter().map(|m| m[i]).collect::<Vec<f64>>();
+        result[i] = measures[i].dist(&sim, &real);
+    }
+    return result;
+}
+
+pub fn tick_aligned(sim: &[f64], real: &[f64]) -> f64 {
+    assert_eq!(sim.len(), real.len());
+
+    let mut sum = 0.0;
+    for (s, r) in sim.iter().zip(real.iter()) {
+        sum += (s - r).powi(2);
+    }
+    return sum.sqrt();
+}
+
+/// with a window of 0 and equal lengths this is the same as `tick_aligned`
+pub fn dtw(sim: &[f64], real: &[f64], window: usize) -> f64 {
+    let n = sim.len();
+    let m = real.len();
+    if let Some(dist) = empty_dist(sim, real) {
+        return dist;
+    }
+    // the band has to be wide enough to reach the last cell
+    let window = usize::max(window, n.abs_diff(m));
+
+    let mut pre = vec![f64::INFINITY; m + 1];
+    let mut cur = vec![f64::INFINITY; m + 1];
+    pre[0] = 0.0;
+
+    for i in 1..=n {
+        cur.fill(f64::INFINITY);
+        let start = usize::max(1, i.saturating_sub(window));
+        let end = usize::min(m, i + window);
+        for j in start..=end {
+            let cost = (sim[i - 1] - real[j - 1]).powi(2);
+            cur[j] = cost + f64::min(pre[j - 1], f64::min(pre[j], cur[j - 1]));
+        }
+        std::mem::swap(&mut pre, &mut cur);
+    }
+
+    return pre[m].sqrt();
+}
+
+/// with a `max_lag` of 0 this is the same as `tick_aligned`
+pub fn lagged_mse(sim: &[f64], real: &[f64], max_lag: usize) -> f64 {
+    if let Some(dist) = empty_dist(sim, real) {
+        return dist;
+    }
+    let len = usize::min(sim.len(), real.len());
+    let max_lag = usize::min(max_lag, len.saturating_sub(1));
+
+    let mut best = f64::INFINITY;
+    for lag in -(max_lag as isize)..=(max_lag as isize) {
+        // a positive lag means the simulation is late
+        let (sim, real) = if lag >= 0 {
+            (&sim[lag as usize..], real)
+        } else {
+            (sim, &real[(-lag) as usize..])
+        };
+        let overlap = usize::min(sim.len(), real.len());
+
+        let mut sum = 0.0;
+        for i in 0..overlap {
+            sum += (sim[i] - real[i]).powi(2);
+        }
+        let scaled = sum * len as f64 / overlap as f64;
+        if scaled < best {
+            best = scaled;
+        }
+    }
+
+    return best.sqrt();
+}
+
+pub fn summary_dist(sim: &[f64], real: &[f64]) -> f64 {
+    if let Some(dist) = empty_dist(sim, real) {
+        return dist;
+    }
+    let sim = summary(sim);
+    let real = summary(real);
+
+    let mut sum = 0.0;
+    for (s, r) in sim.iter().zip(real.iter()) {
+        sum += (s - r).powi(2);
+    }
+    return sum.sqrt();
+}
+
+/// mean, variance and the 10%, 25%, 50%, 75% and 90% quantiles, all 0 for no values
+pub fn summary(vals: &[f64]) -> [f64; 7] {
+    if vals.is_empty() {
+        return [0.0; 7];
+    }
+    let len = vals.len() as f64;
+    let mean = vals.iter().sum::<f64>() / len;
+    let variance = vals.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / len;
+
+    let sorted = sorted(vals);
+    return [
+        mean,
+        variance,
+        quantile(&sorted, 0.1),
+        quantile(&sorted, 0.25),
+        quantile(&sorted, 0.5),
+        quantile(&sorted, 0.75),
+        quantile(&sorted, 0.9),
+    ];
+}
+
+/// linear interpolated quantile of already sorted values, 0 for no values
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let pos = q * (sorted.len() - 1) as f64;
+    let lower = pos.floor() as usize;
+    let upper = pos.ceil() as usize;
+    return sorted[lower] + (sorted[upper] - sorted[lower]) * (pos - lower as f64);
+}
+
+pub fn kolmogorov_smirnov(sim: &[f64], real: &[f64]) -> f64 {
+    if let Some(dist) = empty_dist(sim, real) {
+        return dist;
+    }
+    let mut max_diff = 0.0;
+    for (cdf_sim, cdf_real, _, _) in EcdfSteps::new(sim, real) {
+        if f64::abs(cdf_sim - cdf_real) > max_diff {
+            max_diff = f64::abs(cdf_sim - cdf_real);
+        }
+    }
+    return max_diff;
+}
+
+/// integral of the absolute difference of both empirical distribution functions
+pub fn wasserstein(sim: &[f64], real: &[f64]) -> f64 {
+    if let Some(dist) = empty_dist(sim, real) {
+        return dist;
+    }
+    let mut area = 0.0;
+    for (cdf_sim, cdf_real, x, next_x) in EcdfSteps::new(sim, real) {
+        area += f64::abs(cdf_sim - cdf_real) * (next_x - x);
+    }
+    return area;
+}
+
+/// 0 if both series are empty, infinite if only one is and None if there is something to compare
+fn empty_dist(sim: &[f64], real: &[f64]) -> Option<f64> {
+    return match (sim.is_empty(), real.is_empty()) {
+        (true, true) => Some(0.0),
+        (false, false) => None,
+        _ => Some(f64::INFINITY),
+    };
+}
+
+fn sorted(vals: &[f64]) -> Vec<f64> {
+    let mut sorted = vals.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    return sorted;
+}
+
+/// walks over the merged values of two samples, yielding both empirical distribution
+/// functions right after each value together with the value and the next value
+struct EcdfSteps {
+    sim: Vec<f64>,
+    real: Vec<f64>,
+    i: usize,
+    j: usize,
+}
+
+impl EcdfSteps {
+    fn new(sim: &[f64], real: &[f64]) -> Self {
+        return Self {
+            sim: sorted(sim),
+            real: sorted(real),
+            i: 0,
+            j: 0,
+        };
+    }
+}
+
+impl Iterator for EcdfSteps {
+    type Item = (f64, f64, f64, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let x = match (self.sim.get(self.i), self.real.get(self.j)) {
+            (Some(&s), Some(&r)) => f64::min(s, r),
+            (Some(&s), None) => s,
+            (None, Some(&r)) => r,
+            (None, None) => return None,
+        };
+
+        while self.i < self.sim.len() && self.sim[self.i] <= x {
+            self.i += 1;
+        }
+        while self.j < self.real.len() && self.real[self.j] <= x {
+            self.j += 1;
+        }
+
+        let next_x = match (self.sim.get(self.i), self.real.get(self.j)) {
+            (Some(&s), Some(&r)) => f64::min(s, r),
+            (Some(&s), None) => s,
+            (None, Some(&r)) => r,
+            (None, None) => x,
+        };
+
+        return Some((
+            self.i as f64 / self.sim.len() as f64,
+            self.j as f64 / self.real.len() as f64,
+            x,
+            next_x,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wave(len: usize, shift: f64) -> Vec<f64> {
+        return (0..len)
+            .map(|i| f64::sin((i as f64 - shift) * 0.3))
+            .collect();
+    }
+
+    #[test]
+    fn names_parse_back() {
+        for measure in [
+            DistanceMeasure::TickAligned,
+            DistanceMeasure::Dtw { window: 50 },
+            DistanceMeasure::LaggedMse { max_lag: 20 },
+            DistanceMeasure::Summary,
+            DistanceMeasure::KolmogorovSmirnov,
+            DistanceMeasure::Wasserstein,
+        ] {
+            assert_eq!(DistanceMeasure::parse(&measure.name()), Some(measure));
+        }
+        assert_eq!(DistanceMeasure::parse("dtw"), None);
+        assert_eq!(DistanceMeasure::parse("tick:3"), None);
+        assert!(DistanceMeasure::parse_all("tick,ks").is_none());
+    }
+
+    #[test]
+    fn zero_window_and_lag_are_tick_aligned() {
+        let sim = wave(40, 0.0);
+        let real = wave(40, 2.5);
+        let expected = tick_aligned(&sim, &real);
+        assert!(expected > 0.1);
+        assert!((dtw(&sim, &real, 0) - expected).abs() < 1e-12);
+        assert!((lagged_mse(&sim, &real, 0) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn dtw_band_limits_the_warping() {
+        // the simulation lags the real run by 3 ticks
+        let sim = wave(60, 3.0);
+        let real = wave(60, 0.0);
+        let aligned = tick_aligned(&sim, &real);
+        let narrow = dtw(&sim, &real, 1);
+        let wide = dtw(&sim, &real, 3);
+        assert!(narrow < aligned);
+        assert!(wide < narrow);
+        // only the first 3 ticks have no counterpart
+        assert!(wide < 0.5 * aligned);
+        assert!(dtw(&sim, &real, 60) <= wide + 1e-12);
+    }
+
+    #[test]
+    fn lagged_mse_finds_the_lag() {
+        let sim = wave(60, 3.0);
+        let real = wave(60, 0.0);
+        assert!(lagged_mse(&sim, &real, 2) > 0.1);
+        assert!(lagged_mse(&sim, &real, 3) < 1e-12);
+    }
+
+    #[test]
+    fn wasserstein_of_a_shift_is_the_shift() {
+        let real = vec![0.1, 0.5, 0.2, 0.9, 0.4];
+        let sim = real.iter().map(|x| x + 0.25).collect::<Vec<f64>>();
+        assert!((wasserstein(&sim, &real) - 0.25).abs() < 1e-12);
+        assert!((wasserstein(&real, &sim) - 0.25).abs() < 1e-12);
+        assert!(wasserstein(&real, &real).abs() < 1e-12);
+
+        // half of the mass moves by 1
+        assert!((wasserstein(&[0.0, 1.0], &[0.0, 0.0]) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn kolmogorov_smirnov_of_disjoint_and_equal_samples() {
+        assert_eq!(kolmogorov_smirnov(&[0.0, 0.1, 0.2], &[0.5, 0.6]), 1.0);
+        assert_eq!(kolmogorov_smirnov(&[0.3, 0.1, 0.2], &[0.1, 0.2, 0.3]), 0.0);
+        assert!((kolmogorov_smirnov(&[0.0, 1.0], &[0.0, 0.0]) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn summary_of_known_values() {
+        let summary = summary(&[4.0, 0.0, 2.0, 1.0, 3.0]);
+        assert_eq!(summary[0], 2.0);
+        assert_eq!(summary[1], 2.0);
+        assert!((summary[2] - 0.4).abs() < 1e-12);
+        assert_eq!(summary[3..6], [1.0, 2.0, 3.0]);
+        assert!((summary[6] - 3.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn empty_series_are_only_close_to_empty_series() {
+        let vals = [0.1, 0.2];
+        for measure in [
+            DistanceMeasure::TickAligned,
+            DistanceMeasure::Dtw { window: 5 },
+            DistanceMeasure::LaggedMse { max_lag: 5 },
+            DistanceMeasure::Summary,
+            DistanceMeasure::KolmogorovSmirnov,
+            DistanceMeasure::Wasserstein,
+        ] {
+            assert_eq!(measure.dist(&[], &[]), 0.0, "{}", measure.name());
+        }
+        for measure in [
+            DistanceMeasure::Dtw { window: 5 },
+            DistanceMeasure::LaggedMse { max_lag: 5 },
+            DistanceMeasure::Summary,
+            DistanceMeasure::KolmogorovSmirnov,
+            DistanceMeasure::Wasserstein,
+        ] {
+            assert_eq!(
+                measure.dist(&vals, &[]),
+                f64::INFINITY,
+                "{}",
+                measure.name()
+            );
+            assert_eq!(
+                measure.dist(&[], &vals),
+                f64::INFINITY,
+                "{}",
+                measure.name()
+            );
+        }
+        assert_eq!(summary(&[]), [0.0; 7]);
+    }
+}
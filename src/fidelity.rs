@@ -0,0 +1,230 @@
+use crate::{distance::quantile, utilities::Evaluator};
+use std::cmp::Ordering;
+
+/// How much of an experiment a low fidelity evaluation simulates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fidelity {
+    /// prefix of the experiment, compared with the same prefix of the real windows
+    pub ticks: usize,
+    pub seeds: usize,
+}
+
+impl Fidelity {
+    pub fn name(&self) -> String {
+        return format!("{}x{}", self.ticks, self.seeds);
+    }
+
+    /// parses the format returned by `name`, e.g. `300x1` for 300 ticks with one seed
+    pub fn parse(name: &str) -> Option<Self> {
+        let (ticks, seeds) = name.trim().split_once("x")?;
+        return Some(Self {
+            ticks: ticks.trim().parse().ok()?,
+            seeds: seeds.trim().parse().ok()?,
+        });
+    }
+}
+
+/// Scores candidates at low fidelity first and only promotes the promising ones to a full evaluation.
+///
+/// A candidate is promoted if its low fidelity cost is within the best `promote_fraction` of all
+/// low fidelity costs seen so far, the first `1 / promote_fraction` candidates are always promoted.
+pub struct MultiFidelity {
+    pub low: Evaluator,
+    pub full: Evaluator,
+    pub promote_fraction: f64,
+    low_costs: Vec<f64>,
+    /// low and full cost of every promoted candidate
+    pairs: Vec<(f64, f64)>,
+}
+
+impl MultiFidelity {
+    pub fn new(eval: &Evaluator, low: Fidelity, promote_fraction: f64) -> Result<Self, String> {
+        if !(promote_fraction > 0.0 && promote_fraction <= 1.0) {
+            return Err(format!(
+                "promote fraction {promote_fraction} is not in (0, 1]"
+            ));
+        }
+        return Ok(Self {
+            low: eval.at_fidelity(low)?,
+            full: eval.clone(),
+            promote_fraction,
+            low_costs: Vec::new(),
+            pairs: Vec::new(),
+        });
+    }
+
+    /// the full cost of promoted candidates, the cost of all others is infinite
+    pub fn eval_controller(&mut self, controller_cmd: Vec<String>) -> f64 {
+        let low_cost = self.low.eval_controller(controller_cmd.clone());
+        self.low_costs.push(low_cost);
+        if !self.promote(low_cost) {
+            return f64::INFINITY;
+        }
+
+        let cost = self.full.eval_controller(controller_cmd);
+        self.pairs.push((low_cost, cost));
+        return cost;
+    }
+
+    fn promote(&self, low_cost: f64) -> bool {
+        if !low_cost.is_finite() {
+            return false;
+        }
+        if (self.low_costs.len() as f64) * self.promote_fraction <= 1.0 {
+            return true;
+        }
+
+        let mut sorted = self
+            .low_costs
+            .iter()
+            .copied()
+            .filter(|x| x.is_finite())
+            .collect::<Vec<f64>>();
+        sorted.sort_by(f64::total_cmp);
+        return low_cost <= quantile(&sorted, self.promote_fraction);
+    }
+
+    /// number of evaluated and promoted candidates
+    pub fn counts(&self) -> (usize, usize) {
+        return (self.low_costs.len(), self.pairs.len());
+    }
+
+    /// Spearman correlation of the low and full fidelity costs of the promoted candidates
+    pub fn rank_correlation(&self) -> Option<f64> {
+        let (low, full): (Vec<f64>, Vec<f64>) = self.pairs.iter().copied().unzip();
+        return spearman(&low, &full);
+    }
+}
+
+/// ranks starting at 1, tied values get the mean of their ranks
+pub fn ranks(vals: &[f64]) -> Vec<f64> {
+    let mut order = (0..vals.len()).collect::<Vec<usize>>();
+    order.sort_by(|&a, &b| vals[a].total_cmp(&vals[b]));
+
+    let mut result = vec![0.0; vals.len()];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i;
+        while j + 1 < order.len() && vals[order[j + 1]] == vals[order[i]] {
+            j += 1;
+        }
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        for &k in &order[i..=j] {
+            result[k] = rank;
+        }
+        i = j + 1;
+    }
+    return result;
+}
+
+/// Pearson correlation of the ranks, `None` for fewer than 2 pairs or constant values
+pub fn spearman(a: &[f64], b: &[f64]) -> Option<f64> {
+    assert_eq!(a.len(), b.len());
+    if a.len() < 2 {
+        return None;
+    }
+
+    let (ra, rb) = (ranks(a), ranks(b));
+    let mean = (a.len() as f64 + 1.0) / 2.0;
+    let mut cov = 0.0;
+    let mut var_a = 0.0;
+    let mut var_b = 0.0;
+    for (x, y) in ra.iter().zip(rb.iter()) {
+        cov += (x - mean) * (y - mean);
+        var_a += (x - mean).powi(2);
+        var_b += (y - mean).powi(2);
+    }
+    if var_a == 0.0 || var_b == 0.0 {
+        return None;
+    }
+    return Some(cov / (var_a * var_b).sqrt());
+}
+
+/// Kendall's tau-b, which accounts for ties
+pub fn kendall_tau(a: &[f64], b: &[f64]) -> Option<f64> {
+    assert_eq!(a.len(), b.len());
+
+    let mut concordant: f64 = 0.0;
+    let mut discordant = 0.0;
+    let mut ties_a = 0.0;
+    let mut ties_b = 0.0;
+    for i in 0..a.len() {
+        for j in i + 1..a.len() {
+            let da = a[i].total_cmp(&a[j]);
+            let db = b[i].total_cmp(&b[j]);
+            match (da, db) {
+                (Ordering::Equal, Ordering::Equal) => {}
+                (Ordering::Equal, _) => ties_a += 1.0,
+                (_, Ordering::Equal) => ties_b += 1.0,
+                _ if da == db => concordant += 1.0,
+                _ => discordant += 1.0,
+            }
+        }
+    }
+
+    let denominator =
+        ((concordant + discordant + ties_a) * (concordant + discordant + ties_b)).sqrt();
+    if denominator == 0.0 {
+        return None;
+    }
+    return Some((concordant - discordant) / denominator);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Option<f64>, b: f64) {
+        let a = a.unwrap();
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn fidelity_name_round_trip() {
+        let fidelity = Fidelity::parse(" 300x1 ").unwrap();
+        assert_eq!(
+            fidelity,
+            Fidelity {
+                ticks: 300,
+                seeds: 1
+            }
+        );
+        assert_eq!(Fidelity::parse(&fidelity.name()), Some(fidelity));
+        assert_eq!(Fidelity::parse("300"), None);
+        assert_eq!(Fidelity::parse("ax1"), None);
+    }
+
+    #[test]
+    fn ties_get_the_mean_rank() {
+        assert_eq!(ranks(&[3.0, 1.0, 2.0, 2.0]), [4.0, 1.0, 2.5, 2.5]);
+    }
+
+    #[test]
+    fn rank_correlations() {
+        let a = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_close(spearman(&a, &[10.0, 20.0, 30.0, 40.0, 50.0]), 1.0);
+        assert_close(spearman(&a, &[5.0, 4.0, 3.0, 2.0, 1.0]), -1.0);
+        // two adjacent swaps: 1 - 6 * 4 / (5 * 24) and (8 - 2) / 10
+        let b = [2.0, 1.0, 4.0, 3.0, 5.0];
+        assert_close(spearman(&a, &b), 0.8);
+        assert_close(kendall_tau(&a, &b), 0.6);
+        assert_close(kendall_tau(&a, &[5.0, 4.0, 3.0, 2.0, 1.0]), -1.0);
+    }
+
+    #[test]
+    fn rank_correlations_with_ties() {
+        let a = [1.0, 2.0, 2.0, 3.0];
+        let b = [1.0, 2.0, 3.0, 4.0];
+        assert_close(spearman(&a, &b), 4.5 / 22.5_f64.sqrt());
+        // 5 concordant pairs and one tied in `a` only
+        assert_close(kendall_tau(&a, &b), 5.0 / 30.0_f64.sqrt());
+    }
+
+    #[test]
+    fn undefined_rank_correlations() {
+        assert_eq!(spearman(&[1.0], &[2.0]), None);
+        assert_eq!(spearman(&[1.0, 1.0, 1.0], &[1.0, 2.0, 3.0]), None);
+        assert_eq!(kendall_tau(&[1.0, 1.0], &[2.0, 2.0]), None);
+        assert_eq!(kendall_tau(&[], &[]), None);
+    }
+}
@@ -0,0 +1,659 @@
+use crate::{
+    dataset::parse_timed_bot_pos_csv,
+    json::Json,
+    trajectory::{RobotState, Trajectory},
+    SwarmPos, SwarmState, SWARM_SIZE,
+};
+use std::path::Path;
+
+/// File layouts trajectories can be read from and written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrajectoryFormat {
+    /// one frame per line, `[time,]bot0_x,bot0_y,...,bot14_y` like `all_bot_pos.csv`
+    #[default]
+    BotPosCsv,
+    /// one robot per line, `time,robot_id,x,y[,theta][,vx,vy][,state]` with a header
+    LongCsv,
+    /// whitespace separated `clock robot_id x y ...` lines of an ARGoS loop function, `#` starts a comment
+    ArgosLog,
+    /// the `%! i:<robot> x:<x> y:<y> [h:<heading>] [vx:<vx> vy:<vy>] [s:<state>]` lines AutoMoDe
+    /// prints to stdout
+    AutomodeOutput,
+    /// one frame per line, `{"time": t, "robots": [{"id": 0, "x": x, "y": y}, ...]}`, robots may
+    /// have `heading`, `vx`, `vy` and `state`
+    JsonLines,
+}
+
+impl TrajectoryFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrajectoryFormat::BotPosCsv => "botpos",
+            TrajectoryFormat::LongCsv => "long",
+            TrajectoryFormat::ArgosLog => "argos",
+            TrajectoryFormat::AutomodeOutput => "automode",
+            TrajectoryFormat::JsonLines => "jsonl",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "botpos" => Some(TrajectoryFormat::BotPosCsv),
+            "long" => Some(TrajectoryFormat::LongCsv),
+            "argos" => Some(TrajectoryFormat::ArgosLog),
+            "automode" => Some(TrajectoryFormat::AutomodeOutput),
+            "jsonl" => Some(TrajectoryFormat::JsonLines),
+            _ => None,
+        }
+    }
+
+    /// guesses the format from the file extension and, for ambiguous extensions, the content
+    pub fn detect(path: &Path, content: &str) -> Self {
+        let first_line = content
+            .lines()
+            .map(|x| x.trim())
+            .find(|x| !x.is_empty())
+            .unwrap_or("");
+
+        let extension = path
+            .extension()
+            .map(|x| x.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        return match extension.as_str() {
+            "jsonl" | "ndjson" | "json" => TrajectoryFormat::JsonLines,
+            "csv" => {
+                let head = first_line
+                    .split(",")
+                    .map(|x| x.trim())
+                    .collect::<Vec<&str>>();
+                if head.len() < 2 * SWARM_SIZE && head.iter().any(|x| ROBOT_ID_COLUMNS.contains(x))
+                {
+                    TrajectoryFormat::LongCsv
+                } else {
+                    TrajectoryFormat::BotPosCsv
+                }
+            }
+            _ if content.lines().any(|x| x.starts_with("%!")) => TrajectoryFormat::AutomodeOutput,
+            _ if first_line.starts_with('{') => TrajectoryFormat::JsonLines,
+            _ => TrajectoryFormat::ArgosLog,
+        };
+    }
+
+    /// `tick_rate` gives the frame times of formats without timestamps
+    pub fn import(&self, content: &str, tick_rate: f64) -> Result<Trajectory, String> {
+        return match self {
+            TrajectoryFormat::BotPosCsv => parse_timed_bot_pos_csv(content, tick_rate),
+            TrajectoryFormat::LongCsv => parse_long_csv(content),
+            TrajectoryFormat::ArgosLog => parse_argos_log(content, tick_rate),
+            TrajectoryFormat::AutomodeOutput => parse_automode_trajectory(content, tick_rate),
+            TrajectoryFormat::JsonLines => parse_json_lines(content, tick_rate),
+        };
+    }
+
+    /// `tick_rate` gives the clock of formats that count ticks instead of seconds, the robot states
+    /// are written by the formats that have room for them
+    pub fn export(&self, trajectory: &Trajectory, tick_rate: f64) -> String {
+        let mut out = String::new();
+        match self {
+            TrajectoryFormat::BotPosCsv => {
+                out.push_str("time");
+                for r in 0..SWARM_SIZE {
+                    out.push_str(&format!(",bot{r}_x,bot{r}_y"));
+                }
+                out.push('\n');
+                for (t, frame) in trajectory.times.iter().zip(&trajectory.frames) {
+                    out.push_str(&format!("{t}"));
+                    for (x, y) in frame {
+                        out.push_str(&format!(",{x},{y}"));
+                    }
+                    out.push('\n');
+                }
+            }
+            TrajectoryFormat::LongCsv => {
+                if !trajectory.has_states() {
+                    out.push_str("time,robot_id,x,y\n");
+                } else {
+                    out.push_str("time,robot_id,x,y,theta,vx,vy,state\n");
+                }
+                for (k, (t, frame)) in trajectory.times.iter().zip(&trajectory.frames).enumerate() {
+                    for (r, (x, y)) in frame.iter().enumerate() {
+                        out.push_str(&format!("{t},{r},{x},{y}"));
+                        if let Some(states) = trajectory.states.get(k) {
+                            let state = states[r];
+                            let (vx, vy) = state.velocity.unzip();
+                            out.push_str(&format!(
+                                ",{},{},{},{}",
+                                optional(state.heading),
+                                optional(vx),
+                                optional(vy),
+                                optional(state.state)
+                            ));
+                        }
+                        out.push('\n');
+                    }
+                }
+            }
+            TrajectoryFormat::ArgosLog => {
+                out.push_str("# clock\tid\tx\ty\n");
+                for (t, frame) in trajectory.times.iter().zip(&trajectory.frames) {
+                    let clock = (t * tick_rate).round() as i64;
+                    for (r, (x, y)) in frame.iter().enumerate() {
+                        out.push_str(&format!("{clock}\tep{r}\t{x}\t{y}\n"));
+                    }
+                }
+            }
+            TrajectoryFormat::AutomodeOutput => {
+                for (k, frame) in trajectory.frames.iter().enumerate() {
+                    for (r, (x, y)) in frame.iter().enumerate() {
+                        out.push_str(&format!("%! i:{r} x:{x} y:{y}"));
+                        let state = trajectory.states.get(k).map(|x| x[r]).unwrap_or_default();
+                        if let Some(heading) = state.heading {
+                            out.push_str(&format!(" h:{heading}"));
+                        }
+                        if let Some((vx, vy)) = state.velocity {
+                            out.push_str(&format!(" vx:{vx} vy:{vy}"));
+                        }
+                        if let Some(id) = state.state {
+                            out.push_str(&format!(" s:{id}"));
+                        }
+                        out.push('\n');
+                    }
+                }
+            }
+            TrajectoryFormat::JsonLines => {
+                for (k, (t, frame)) in trajectory.times.iter().zip(&trajectory.frames).enumerate() {
+                    let robots = frame
+                        .iter()
+                        .enumerate()
+                        .map(|(r, (x, y))| {
+                            let mut fields = vec![
+                                ("id".to_string(), Json::from(r as f64)),
+                                ("x".to_string(), Json::from(*x)),
+                                ("y".to_string(), Json::from(*y)),
+                            ];
+                            let state = trajectory.states.get(k).map(|x| x[r]).unwrap_or_default();
+                            if let Some(heading) = state.heading {
+                                fields.push(("heading".to_string(), Json::from(heading)));
+                            }
+                            if let Some((vx, vy)) = state.velocity {
+                                fields.push(("vx".to_string(), Json::from(vx)));
+                                fields.push(("vy".to_string(), Json::from(vy)));
+                            }
+                            if let Some(id) = state.state {
+                                fields.push(("state".to_string(), Json::from(id as f64)));
+                            }
+                            Json::Object(fields)
+                        })
+                        .collect();
+                    let line = Json::Object(vec![
+                        ("time".to_string(), Json::from(*t)),
+                        ("robots".to_string(), Json::Array(robots)),
+                    ]);
+                    out.push_str(&format!("{line}\n"));
+                }
+            }
+        }
+        return out;
+    }
+}
+
+const ROBOT_ID_COLUMNS: [&str; 4] = ["robot_id", "robot", "id", "bot"];
+
+/// the value, or an empty field if there is none
+fn optional<T: std::fmt::Display>(val: Option<T>) -> String {
+    return val.map(|x| format!("{x}")).unwrap_or_default();
+}
+
+/// reads a trajectory file, the format is detected if none is given
+pub fn read_trajectory(
+    path: &Path,
+    format: Option<TrajectoryFormat>,
+    tick_rate: f64,
+) -> Result<Trajectory, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let format = format.unwrap_or(TrajectoryFormat::detect(path, &content));
+    return format
+        .import(&content, tick_rate)
+        .map_err(|e| format!("{}: {e}", path.display()));
+}
+
+pub fn write_trajectory(
+    path: &Path,
+    format: TrajectoryFormat,
+    trajectory: &Trajectory,
+    tick_rate: f64,
+) -> Result<(), String> {
+    return std::fs::write(path, format.export(trajectory, tick_rate))
+        .map_err(|e| format!("{}: {e}", path.display()));
+}
+
+/// the positions AutoMoDe prints, a frame is complete once the last robot was printed
+pub fn parse_automode_output(content: &str) -> Result<Vec<SwarmPos>, String> {
+    return Ok(parse_automode_trajectory(content, 1.0)?.frames);
+}
+
+/// like `parse_automode_output` with the robot states if any were printed, the frames are
+/// `1 / tick_rate` apart
+pub fn parse_automode_trajectory(content: &str, tick_rate: f64) -> Result<Trajectory, String> {
+    let mut parser = AutomodeFrames::default();
+    let mut swarm_pos = Vec::new();
+    let mut states = Vec::new();
+    for line in content.lines() {
+        if let Some(frame) = parser.push_line(line)? {
+            swarm_pos.push(frame);
+            states.push(parser.state());
+        }
+    }
+    if !parser.has_states() {
+        states.clear();
+    }
+    let trajectory = Trajectory::from_tick_rate(swarm_pos, tick_rate);
+    return Ok(Trajectory::with_states(
+        trajectory.times,
+        trajectory.frames,
+        states,
+    ));
+}
+
+/// Builds frames from AutoMoDe's stdout one line at a time, so the output needs not be buffered.
+#[derive(Debug, Clone, Default)]
+pub struct AutomodeFrames {
+    current_pos: SwarmPos,
+    current_state: SwarmState,
+    /// whether any line had more than the position
+    has_states: bool,
+}
+
+impl AutomodeFrames {
+    /// the completed frame if `line` holds the last robot, other output than `%!` lines is ignored
+    ///
+    /// besides `i`, `x` and `y` a line may hold the heading `h`, the velocity `vx` and `vy` and the
+    /// FSM state `s`, unknown keys are skipped
+    pub fn push_line(&mut self, line: &str) -> Result<Option<SwarmPos>, String> {
+        let Some(line) = line.trim().strip_prefix("%!") else {
+            return Ok(None);
+        };
+
+        let mut i = None;
+        let mut x = None;
+        let mut y = None;
+        let mut vx = None;
+        let mut vy = None;
+        let mut state = RobotState::default();
+        for field in line.split_whitespace() {
+            let (key, value) = field
+                .split_once(":")
+                .ok_or(format!("invalid field {field} in %!{line}"))?;
+            match key {
+                "i" => i = value.parse::<usize>().ok(),
+                "x" => x = value.parse::<f64>().ok(),
+                "y" => y = value.parse::<f64>().ok(),
+                "h" | "heading" => state.heading = Some(parse_field(value, line)?),
+                "vx" => vx = Some(parse_field(value, line)?),
+                "vy" => vy = Some(parse_field(value, line)?),
+                "s" | "state" => state.state = Some(parse_field(value, line)?),
+                _ => {}
+            }
+        }
+        state.velocity = vx.zip(vy);
+
+        let (Some(i), Some(x), Some(y)) = (i, x, y) else {
+            return Err(format!("expected i:, x: and y: in %!{line}"));
+        };
+        if i >= SWARM_SIZE {
+            return Err(format!("robot {i} but the evaluator expects {SWARM_SIZE}"));
+        }
+
+        self.current_pos[i] = (x, y);
+        self.current_state[i] = state;
+        self.has_states |= !state.is_empty();
+        if i == SWARM_SIZE - 1 {
+            return Ok(Some(self.current_pos));
+        }
+        return Ok(None);
+    }
+}
+
+impl AutomodeFrames {
+    /// states of the robots in the last completed frame
+    pub fn state(&self) -> SwarmState {
+        return self.current_state;
+    }
+
+    /// whether any robot reported more than its position so far
+    pub fn has_states(&self) -> bool {
+        return self.has_states;
+    }
+}
+
+/// maps robot ids like `3`, `ep3` or `fb12` to 0..SWARM_SIZE by the order of their number
+struct RobotIds {
+    ids: Vec<String>,
+}
+
+impl RobotIds {
+    fn new(mut ids: Vec<String>) -> Result<Self, String> {
+        ids.sort_by_key(|x| (id_number(x), x.clone()));
+        ids.dedup();
+        if ids.len() != SWARM_SIZE {
+            return Err(format!(
+                "found {} robots but the evaluator expects {SWARM_SIZE}",
+                ids.len()
+            ));
+        }
+        return Ok(Self { ids });
+    }
+
+    fn index(&self, id: &str) -> usize {
+        return self.ids.iter().position(|x| x == id).unwrap();
+    }
+}
+
+fn id_number(id: &str) -> Option<u64> {
+    let digits = id
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<Vec<char>>();
+    return digits.into_iter().rev().collect::<String>().parse().ok();
+}
+
+/// one position of one robot at one time
+struct Sample {
+    time: f64,
+    id: String,
+    pos: (f64, f64),
+    state: RobotState,
+}
+
+/// groups samples with the same time into frames, every frame needs every robot
+fn to_trajectory(mut samples: Vec<Sample>) -> Result<Trajectory, String> {
+    if samples.is_empty() {
+        return Err("no positions".to_string());
+    }
+
+    let robots = RobotIds::new(samples.iter().map(|x| x.id.clone()).collect())?;
+    samples.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let has_states = samples.iter().any(|x| !x.state.is_empty());
+    let mut times = Vec::new();
+    let mut frames = Vec::new();
+    let mut states = Vec::new();
+    for group in samples.chunk_by(|a, b| a.time == b.time) {
+        let mut frame = SwarmPos::default();
+        let mut state = SwarmState::default();
+        let mut seen = [false; SWARM_SIZE];
+        for sample in group {
+            let r = robots.index(&sample.id);
+            frame[r] = sample.pos;
+            state[r] = sample.state;
+            seen[r] = true;
+        }
+        if let Some(r) = seen.iter().position(|x| !x) {
+            return Err(format!(
+                "robot {} is missing at time {}",
+                robots.ids[r], group[0].time
+            ));
+        }
+        times.push(group[0].time);
+        frames.push(frame);
+        if has_states {
+            states.push(state);
+        }
+    }
+
+    return Ok(Trajectory::with_states(times, frames, states));
+}
+
+fn parse_field<T: std::str::FromStr>(val: &str, line: &str) -> Result<T, String> {
+    return val
+        .trim()
+        .parse::<T>()
+        .map_err(|_| format!("invalid value {val} in line {line}"));
+}
+
+/// `time,robot_id,x,y[,theta][,vx,vy][,state]`, the columns are found by their header names, empty
+/// optional fields are missing values
+pub fn parse_long_csv(content: &str) -> Result<Trajectory, String> {
+    let mut line_it = content.lines();
+    let head = line_it
+        .next()
+        .ok_or("empty csv")?
+        .split(",")
+        .map(|s| s.trim())
+        .collect::<Vec<&str>>();
+
+    let column = |names: &[&str]| head.iter().position(|x| names.contains(x));
+    let time = column(&["time", "t", "timestamp"]).ok_or("missing time column")?;
+    let id = column(&ROBOT_ID_COLUMNS).ok_or("missing robot_id column")?;
+    let x = column(&["x"]).ok_or("missing x column")?;
+    let y = column(&["y"]).ok_or("missing y column")?;
+    let heading = column(&["theta", "heading", "h"]);
+    let vx = column(&["vx"]);
+    let vy = column(&["vy"]);
+    let state = column(&["state", "s"]);
+
+    let mut samples = Vec::new();
+    for line in line_it {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let vals = line.split(",").collect::<Vec<&str>>();
+        if vals.len() != head.len() {
+            return Err(format!(
+                "expected {} columns but got {}",
+                head.len(),
+                vals.len()
+            ));
+        }
+        let optional = |column: Option<usize>| match column.map(|i| vals[i].trim()) {
+            Some(val) if !val.is_empty() => parse_field(val, line).map(Some),
+            _ => Ok(None),
+        };
+        let (vx, vy): (Option<f64>, Option<f64>) = (optional(vx)?, optional(vy)?);
+        samples.push(Sample {
+            time: parse_field(vals[time], line)?,
+            id: vals[id].trim().to_string(),
+            pos: (parse_field(vals[x], line)?, parse_field(vals[y], line)?),
+            state: RobotState {
+                heading: optional(heading)?,
+                velocity: vx.zip(vy),
+                state: optional(state)?.map(|x: f64| x as u32),
+            },
+        });
+    }
+
+    return to_trajectory(samples);
+}
+
+/// `clock robot_id x y ...` with the clock in simulator ticks, lines not starting with a number are skipped
+pub fn parse_argos_log(content: &str, tick_rate: f64) -> Result<Trajectory, String> {
+    let mut samples = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+        let Ok(clock) = fields[0].parse::<f64>() else {
+            // header or other output of the loop function
+            continue;
+        };
+        if fields.len() < 4 {
+            return Err(format!("expected clock, id, x and y in line {line}"));
+        }
+        samples.push(Sample {
+            time: clock / tick_rate,
+            id: fields[1].to_string(),
+            pos: (parse_field(fields[2], line)?, parse_field(fields[3], line)?),
+            state: RobotState::default(),
+        });
+    }
+
+    return to_trajectory(samples);
+}
+
+/// one JSON object per frame, without a `time` the frames are `1 / tick_rate` apart
+pub fn parse_json_lines(content: &str, tick_rate: f64) -> Result<Trajectory, String> {
+    let mut samples = Vec::new();
+    for (k, line) in content.lines().filter(|x| !x.trim().is_empty()).enumerate() {
+        let frame = Json::parse(line).map_err(|e| format!("line {}: {e}", k + 1))?;
+        let time = match frame.get("time").or(frame.get("t")) {
+            Some(time) => time
+                .as_f64()
+                .ok_or(format!("line {}: time is not a number", k + 1))?,
+            None => k as f64 / tick_rate,
+        };
+        let robots = frame
+            .get("robots")
+            .and_then(|x| x.as_array())
+            .ok_or(format!("line {}: missing robots array", k + 1))?;
+
+        for (r, robot) in robots.iter().enumerate() {
+            let id = match robot.get("id") {
+                Some(Json::String(id)) => id.clone(),
+                Some(Json::Number(id)) => format!("{id}"),
+                _ => format!("{r}"),
+            };
+            let coord = |key: &str| {
+                robot
+                    .get(key)
+                    .and_then(|x| x.as_f64())
+                    .ok_or(format!("line {}: robot {id} has no {key}", k + 1))
+            };
+            let optional = |key: &str| robot.get(key).and_then(|x| x.as_f64());
+            let state = RobotState {
+                heading: optional("heading").or(optional("theta")),
+                velocity: optional("vx").zip(optional("vy")),
+                state: optional("state").map(|x| x as u32),
+            };
+            samples.push(Sample {
+                time,
+                pos: (coord("x")?, coord("y")?),
+                id,
+                state,
+            });
+        }
+    }
+
+    return to_trajectory(samples);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [TrajectoryFormat; 5] = [
+        TrajectoryFormat::BotPosCsv,
+        TrajectoryFormat::LongCsv,
+        TrajectoryFormat::ArgosLog,
+        TrajectoryFormat::AutomodeOutput,
+        TrajectoryFormat::JsonLines,
+    ];
+
+    /// three frames at 10 ticks per second, every state field is missing for some robots
+    fn trajectory() -> Trajectory {
+        let frames = (0..3)
+            .map(|t| {
+                let mut frame = SwarmPos::default();
+                for (r, pos) in frame.iter_mut().enumerate() {
+                    *pos = (0.1 * r as f64 - 0.7, 1.0 / 3.0 + 0.01 * t as f64);
+                }
+                frame
+            })
+            .collect::<Vec<SwarmPos>>();
+        let mut states = vec![SwarmState::default(); 3];
+        for (k, state) in states.iter_mut().flatten().enumerate() {
+            *state = RobotState {
+                heading: (k % 2 == 0).then_some(-0.5 + 0.1 * k as f64),
+                velocity: (k % 3 != 0).then_some((0.02 * k as f64, -0.03)),
+                state: (k % 4 != 0).then_some(k as u32 % 3),
+            };
+        }
+        let trajectory = Trajectory::from_tick_rate(frames, 10.0);
+        return Trajectory {
+            states,
+            ..trajectory
+        };
+    }
+
+    #[test]
+    fn names_parse_back() {
+        for format in FORMATS {
+            assert_eq!(TrajectoryFormat::parse(format.name()), Some(format));
+        }
+    }
+
+    #[test]
+    fn export_import_round_trip() {
+        let trajectory = trajectory();
+        for format in FORMATS {
+            let content = format.export(&trajectory, 10.0);
+            let imported = format.import(&content, 10.0).unwrap();
+            assert_eq!(imported.times, trajectory.times, "{}", format.name());
+            assert_eq!(imported.frames, trajectory.frames, "{}", format.name());
+
+            let keeps_states = !matches!(
+                format,
+                TrajectoryFormat::BotPosCsv | TrajectoryFormat::ArgosLog
+            );
+            if keeps_states {
+                assert_eq!(imported.states, trajectory.states, "{}", format.name());
+            } else {
+                assert!(!imported.has_states(), "{}", format.name());
+            }
+        }
+    }
+
+    #[test]
+    fn positions_only_round_trip() {
+        let trajectory = Trajectory {
+            states: Vec::new(),
+            ..trajectory()
+        };
+        for format in FORMATS {
+            let content = format.export(&trajectory, 10.0);
+            let imported = format.import(&content, 10.0).unwrap();
+            assert_eq!(imported, trajectory, "{}", format.name());
+        }
+    }
+
+    #[test]
+    fn exports_are_detected() {
+        let trajectory = trajectory();
+        for (format, file) in [
+            (TrajectoryFormat::BotPosCsv, "run.csv"),
+            (TrajectoryFormat::LongCsv, "run.csv"),
+            (TrajectoryFormat::ArgosLog, "run.log"),
+            (TrajectoryFormat::AutomodeOutput, "run.txt"),
+            (TrajectoryFormat::JsonLines, "run.jsonl"),
+        ] {
+            let content = format.export(&trajectory, 10.0);
+            assert_eq!(TrajectoryFormat::detect(Path::new(file), &content), format);
+        }
+    }
+
+    #[test]
+    fn long_csv_reads_theta_and_missing_fields() {
+        let mut csv = "t,id,x,y,theta,vx,vy,state\n".to_string();
+        for r in 0..SWARM_SIZE {
+            let theta = if r == 0 { "1.25" } else { "" };
+            csv.push_str(&format!("0.5,ep{r},{r},0,{theta},,,\n"));
+        }
+        let trajectory = parse_long_csv(&csv).unwrap();
+        assert_eq!(trajectory.times, [0.5]);
+        assert_eq!(trajectory.frames[0][3], (3.0, 0.0));
+        assert_eq!(trajectory.states[0][0].heading, Some(1.25));
+        assert!(trajectory.states[0][1].is_empty());
+    }
+
+    #[test]
+    fn missing_robots_are_rejected() {
+        let mut content = TrajectoryFormat::LongCsv.export(&trajectory(), 10.0);
+        let last = content.trim_end().rfind('\n').unwrap();
+        content.truncate(last + 1);
+        assert!(parse_long_csv(&content).unwrap_err().contains("missing"));
+    }
+}
@@ -0,0 +1,601 @@
+use rand::Rng;
+
+/// maximum number of states and of outgoing transitions per state AutoMoDe accepts
+pub const MAX_STATES: usize = 4;
+pub const MAX_TRANSITIONS: usize = 4;
+
+/// Low level behaviour of a state, `--sK` in the AutoMoDe flags.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Behaviour {
+    Exploration { rwm: u32 },
+    Stop,
+    Phototaxis,
+    AntiPhototaxis,
+    Attraction { att: f64 },
+    Repulsion { rep: f64 },
+}
+
+/// Condition of a transition, `--cKxJ` in the AutoMoDe flags.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Condition {
+    BlackFloor { p: f64 },
+    GrayFloor { p: f64 },
+    WhiteFloor { p: f64 },
+    NeighborsCount { p: f64, w: f64 },
+    InvertedNeighborsCount { p: f64, w: f64 },
+    FixedProbability { p: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    /// index of the target state, AutoMoDe itself skips the source state when numbering targets
+    pub to: usize,
+    pub condition: Condition,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+    pub behaviour: Behaviour,
+    pub transitions: Vec<Transition>,
+}
+
+/// Probabilistic finite state machine as configured with `--fsm-config`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fsm {
+    pub states: Vec<State>,
+}
+
+impl Behaviour {
+    pub fn id(&self) -> u32 {
+        match self {
+            Behaviour::Exploration { .. } => 0,
+            Behaviour::Stop => 1,
+            Behaviour::Phototaxis => 2,
+            Behaviour::AntiPhototaxis => 3,
+            Behaviour::Attraction { .. } => 4,
+            Behaviour::Repulsion { .. } => 5,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Behaviour::Exploration { .. } => "exploration",
+            Behaviour::Stop => "stop",
+            Behaviour::Phototaxis => "phototaxis",
+            Behaviour::AntiPhototaxis => "anti-phototaxis",
+            Behaviour::Attraction { .. } => "attraction",
+            Behaviour::Repulsion { .. } => "repulsion",
+        }
+    }
+
+    /// parses the behaviour of `--<key>` with its parameter suffixed by `suffix`
+    pub(crate) fn from_flags(flags: &Flags, key: &str, suffix: &str) -> Result<Self, String> {
+        return match flags.get::<u32>(key)? {
+            0 => Ok(Behaviour::Exploration {
+                rwm: flags.get(&format!("rwm{suffix}"))?,
+            }),
+            1 => Ok(Behaviour::Stop),
+            2 => Ok(Behaviour::Phototaxis),
+            3 => Ok(Behaviour::AntiPhototaxis),
+            4 => Ok(Behaviour::Attraction {
+                att: flags.get(&format!("att{suffix}"))?,
+            }),
+            5 => Ok(Behaviour::Repulsion {
+                rep: flags.get(&format!("rep{suffix}"))?,
+            }),
+            id => Err(format!("unknown behaviour {id} for --{key}")),
+        };
+    }
+
+    /// the inverse of `from_flags`
+    pub(crate) fn push_args(&self, args: &mut Vec<String>, key: &str, suffix: &str) {
+        args.push(format!("--{key}"));
+        args.push(format!("{}", self.id()));
+        match self {
+            Behaviour::Exploration { rwm } => {
+                args.push(format!("--rwm{suffix}"));
+                args.push(format!("{rwm}"));
+            }
+            Behaviour::Attraction { att } => {
+                args.push(format!("--att{suffix}"));
+                args.push(format!("{att}"));
+            }
+            Behaviour::Repulsion { rep } => {
+                args.push(format!("--rep{suffix}"));
+                args.push(format!("{rep}"));
+            }
+            Behaviour::Stop | Behaviour::Phototaxis | Behaviour::AntiPhototaxis => {}
+        }
+    }
+
+    /// nudges the parameter or, half of the time and for behaviours without one, draws a new
+    /// behaviour
+    pub fn mutate(&self, rng: &mut impl Rng) -> Self {
+        if rng.gen_bool(0.5) {
+            match *self {
+                Behaviour::Exploration { rwm } => {
+                    return Behaviour::Exploration {
+                        rwm: (rwm as i64 + rng.gen_range(-10..=10)).clamp(1, 100) as u32,
+                    };
+                }
+                Behaviour::Attraction { att } => {
+                    return Behaviour::Attraction {
+                        att: nudge(rng, att, 1.0..5.0),
+                    };
+                }
+                Behaviour::Repulsion { rep } => {
+                    return Behaviour::Repulsion {
+                        rep: nudge(rng, rep, 1.0..5.0),
+                    };
+                }
+                Behaviour::Stop | Behaviour::Phototaxis | Behaviour::AntiPhototaxis => {}
+            }
+        }
+        return Behaviour::random(rng);
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        match rng.gen_range(0..6) {
+            0 => Behaviour::Exploration {
+                rwm: rng.gen_range(1..=100),
+            },
+            1 => Behaviour::Stop,
+            2 => Behaviour::Phototaxis,
+            3 => Behaviour::AntiPhototaxis,
+            4 => Behaviour::Attraction {
+                att: round(rng.gen_range(1.0..5.0)),
+            },
+            _ => Behaviour::Repulsion {
+                rep: round(rng.gen_range(1.0..5.0)),
+            },
+        }
+    }
+}
+
+impl Condition {
+    pub fn id(&self) -> u32 {
+        match self {
+            Condition::BlackFloor { .. } => 0,
+            Condition::GrayFloor { .. } => 1,
+            Condition::WhiteFloor { .. } => 2,
+            Condition::NeighborsCount { .. } => 3,
+            Condition::InvertedNeighborsCount { .. } => 4,
+            Condition::FixedProbability { .. } => 5,
+        }
+    }
+
+    /// probability of the transition firing if the condition holds, 0 means it never fires
+    pub fn p(&self) -> f64 {
+        match self {
+            Condition::BlackFloor { p }
+            | Condition::GrayFloor { p }
+            | Condition::WhiteFloor { p }
+            | Condition::NeighborsCount { p, .. }
+            | Condition::InvertedNeighborsCount { p, .. }
+            | Condition::FixedProbability { p } => *p,
+        }
+    }
+
+    /// parses the condition of `--<key>` with its parameters suffixed by `suffix`
+    pub(crate) fn from_flags(flags: &Flags, key: &str, suffix: &str) -> Result<Self, String> {
+        let p = flags.get::<f64>(&format!("p{suffix}"))?;
+        return match flags.get::<u32>(key)? {
+            0 => Ok(Condition::BlackFloor { p }),
+            1 => Ok(Condition::GrayFloor { p }),
+            2 => Ok(Condition::WhiteFloor { p }),
+            3 => Ok(Condition::NeighborsCount {
+                p,
+                w: flags.get(&format!("w{suffix}"))?,
+            }),
+            4 => Ok(Condition::InvertedNeighborsCount {
+                p,
+                w: flags.get(&format!("w{suffix}"))?,
+            }),
+            5 => Ok(Condition::FixedProbability { p }),
+            id => Err(format!("unknown condition {id} for --{key}")),
+        };
+    }
+
+    /// the inverse of `from_flags`
+    pub(crate) fn push_args(&self, args: &mut Vec<String>, key: &str, suffix: &str) {
+        args.push(format!("--{key}"));
+        args.push(format!("{}", self.id()));
+        args.push(format!("--p{suffix}"));
+        args.push(format!("{}", self.p()));
+        match self {
+            Condition::NeighborsCount { w, .. } | Condition::InvertedNeighborsCount { w, .. } => {
+                args.push(format!("--w{suffix}"));
+                args.push(format!("{w}"));
+            }
+            _ => {}
+        }
+    }
+
+    /// nudges one parameter or, half of the time, draws a new condition
+    pub fn mutate(&self, rng: &mut impl Rng) -> Self {
+        if rng.gen_bool(0.5) {
+            return Condition::random(rng);
+        }
+        return match *self {
+            Condition::BlackFloor { p } => Condition::BlackFloor {
+                p: nudge(rng, p, 0.0..1.0),
+            },
+            Condition::GrayFloor { p } => Condition::GrayFloor {
+                p: nudge(rng, p, 0.0..1.0),
+            },
+            Condition::WhiteFloor { p } => Condition::WhiteFloor {
+                p: nudge(rng, p, 0.0..1.0),
+            },
+            Condition::FixedProbability { p } => Condition::FixedProbability {
+                p: nudge(rng, p, 0.0..1.0),
+            },
+            // the neighbour count is an integer
+            Condition::NeighborsCount { p, w } => Condition::NeighborsCount {
+                p: (p + rng.gen_range(-1..=1) as f64).clamp(1.0, 10.0),
+                w: nudge(rng, w, 0.0..20.0),
+            },
+            Condition::InvertedNeighborsCount { p, w } => Condition::InvertedNeighborsCount {
+                p: (p + rng.gen_range(-1..=1) as f64).clamp(1.0, 10.0),
+                w: nudge(rng, w, 0.0..20.0),
+            },
+        };
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        match rng.gen_range(0..6) {
+            0 => Condition::BlackFloor {
+                p: round(rng.gen_range(0.0..1.0)),
+            },
+            1 => Condition::GrayFloor {
+                p: round(rng.gen_range(0.0..1.0)),
+            },
+            2 => Condition::WhiteFloor {
+                p: round(rng.gen_range(0.0..1.0)),
+            },
+            3 => Condition::NeighborsCount {
+                p: rng.gen_range(1..=10) as f64,
+                w: round(rng.gen_range(0.0..20.0)),
+            },
+            4 => Condition::InvertedNeighborsCount {
+                p: rng.gen_range(1..=10) as f64,
+                w: round(rng.gen_range(0.0..20.0)),
+            },
+            _ => Condition::FixedProbability {
+                p: round(rng.gen_range(0.0..1.0)),
+            },
+        }
+    }
+}
+
+impl Fsm {
+    /// random machine with 1 to `MAX_STATES` states, like the ones AutoMoDe samples
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let num_states = rng.gen_range(1..=MAX_STATES);
+
+        let mut states = Vec::with_capacity(num_states);
+        for k in 0..num_states {
+            let num_transitions = if num_states == 1 {
+                0
+            } else {
+                rng.gen_range(1..=MAX_TRANSITIONS)
+            };
+
+            let mut transitions = Vec::with_capacity(num_transitions);
+            for _ in 0..num_transitions {
+                // any state but the source
+                let mut to = rng.gen_range(0..num_states - 1);
+                if to >= k {
+                    to += 1;
+                }
+                transitions.push(Transition {
+                    to,
+                    condition: Condition::random(rng),
+                });
+            }
+
+            states.push(State {
+                behaviour: Behaviour::random(rng),
+                transitions,
+            });
+        }
+
+        return Self { states };
+    }
+
+    /// copy with one random change: a behaviour, a condition or the target of a transition, or
+    /// a transition or a state added or removed, always a valid machine
+    pub fn mutate(&self, rng: &mut impl Rng) -> Self {
+        if self.states.is_empty() {
+            return Fsm::random(rng);
+        }
+        let mut fsm = self.clone();
+        let num_states = fsm.states.len();
+        let with_transitions = (0..num_states)
+            .filter(|&k| !fsm.states[k].transitions.is_empty())
+            .collect::<Vec<usize>>();
+        let with_room = (0..num_states)
+            .filter(|&k| fsm.states[k].transitions.len() < MAX_TRANSITIONS)
+            .collect::<Vec<usize>>();
+
+        // operators that do not apply are redrawn, changing a behaviour always applies
+        loop {
+            match rng.gen_range(0..7) {
+                0 => {
+                    let state = &mut fsm.states[rng.gen_range(0..num_states)];
+                    state.behaviour = state.behaviour.mutate(rng);
+                }
+                1 if !with_transitions.is_empty() => {
+                    let state =
+                        &mut fsm.states[with_transitions[rng.gen_range(0..with_transitions.len())]];
+                    let j = rng.gen_range(0..state.transitions.len());
+                    state.transitions[j].condition = state.transitions[j].condition.mutate(rng);
+                }
+                2 if !with_transitions.is_empty() && num_states > 2 => {
+                    let k = with_transitions[rng.gen_range(0..with_transitions.len())];
+                    let state = &mut fsm.states[k];
+                    let j = rng.gen_range(0..state.transitions.len());
+                    let current = state.transitions[j].to;
+                    let targets = (0..num_states)
+                        .filter(|&x| x != k && x != current)
+                        .collect::<Vec<usize>>();
+                    state.transitions[j].to = targets[rng.gen_range(0..targets.len())];
+                }
+                3 if !with_room.is_empty() && num_states > 1 => {
+                    let k = with_room[rng.gen_range(0..with_room.len())];
+                    let mut to = rng.gen_range(0..num_states - 1);
+                    if to >= k {
+                        to += 1;
+                    }
+                    fsm.states[k].transitions.push(Transition {
+                        to,
+                        condition: Condition::random(rng),
+                    });
+                }
+                4 if !with_transitions.is_empty() => {
+                    let state =
+                        &mut fsm.states[with_transitions[rng.gen_range(0..with_transitions.len())]];
+                    let j = rng.gen_range(0..state.transitions.len());
+                    state.transitions.remove(j);
+                }
+                5 if num_states < MAX_STATES => {
+                    // reachable from an existing state if one has room, with a way out
+                    let new = num_states;
+                    if !with_room.is_empty() {
+                        let k = with_room[rng.gen_range(0..with_room.len())];
+                        fsm.states[k].transitions.push(Transition {
+                            to: new,
+                            condition: Condition::random(rng),
+                        });
+                    }
+                    fsm.states.push(State {
+                        behaviour: Behaviour::random(rng),
+                        transitions: vec![Transition {
+                            to: rng.gen_range(0..num_states),
+                            condition: Condition::random(rng),
+                        }],
+                    });
+                }
+                6 if num_states > 1 => {
+                    let removed = rng.gen_range(0..num_states);
+                    fsm.states.remove(removed);
+                    for state in fsm.states.iter_mut() {
+                        state.transitions.retain(|x| x.to != removed);
+                        for transition in state.transitions.iter_mut() {
+                            if transition.to > removed {
+                                transition.to -= 1;
+                            }
+                        }
+                    }
+                }
+                _ => continue,
+            }
+            return fsm;
+        }
+    }
+
+    /// parses the arguments passed after `--fsm-config`
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let flags = Flags::new(args)?;
+
+        let num_states = flags.get::<usize>("nstates")?;
+        let mut states = Vec::with_capacity(num_states);
+        for k in 0..num_states {
+            let behaviour = Behaviour::from_flags(&flags, &format!("s{k}"), &format!("{k}"))?;
+
+            let num_transitions = flags.get_or::<usize>(&format!("n{k}"), 0)?;
+            let mut transitions = Vec::with_capacity(num_transitions);
+            for j in 0..num_transitions {
+                let mut to = flags.get::<usize>(&format!("n{k}x{j}"))?;
+                if to >= k {
+                    to += 1;
+                }
+                if to >= num_states {
+                    return Err(format!("transition {k}x{j} points to unknown state {to}"));
+                }
+
+                let condition =
+                    Condition::from_flags(&flags, &format!("c{k}x{j}"), &format!("{k}x{j}"))?;
+                transitions.push(Transition { to, condition });
+            }
+
+            states.push(State {
+                behaviour,
+                transitions,
+            });
+        }
+
+        return Ok(Self { states });
+    }
+
+    /// the arguments to pass after `--fsm-config`
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec!["--nstates".to_string(), format!("{}", self.states.len())];
+
+        for (k, state) in self.states.iter().enumerate() {
+            state
+                .behaviour
+                .push_args(&mut args, &format!("s{k}"), &format!("{k}"));
+
+            if state.transitions.is_empty() {
+                continue;
+            }
+            args.push(format!("--n{k}"));
+            args.push(format!("{}", state.transitions.len()));
+
+            for (j, transition) in state.transitions.iter().enumerate() {
+                let to = if transition.to > k {
+                    transition.to - 1
+                } else {
+                    transition.to
+                };
+                args.push(format!("--n{k}x{j}"));
+                args.push(format!("{to}"));
+                transition.condition.push_args(
+                    &mut args,
+                    &format!("c{k}x{j}"),
+                    &format!("{k}x{j}"),
+                );
+            }
+        }
+
+        return args;
+    }
+
+    pub fn to_cmd(&self) -> String {
+        return self.to_args().join(" ");
+    }
+}
+
+/// splits a controller string into the argument list passed to the simulator
+pub fn split_cmd(cmd: &str) -> Vec<String> {
+    return cmd
+        .split_whitespace()
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>();
+}
+
+/// `--key value` pairs of a controller string
+pub(crate) struct Flags<'a> {
+    pairs: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Flags<'a> {
+    pub(crate) fn new(args: &'a [String]) -> Result<Self, String> {
+        let mut pairs = Vec::with_capacity(args.len() / 2);
+        let mut it = args.iter().map(|x| x.trim()).filter(|x| !x.is_empty());
+        while let Some(key) = it.next() {
+            let key = key
+                .strip_prefix("--")
+                .ok_or(format!("expected a flag but got {key}"))?;
+            let value = it.next().ok_or(format!("missing value for --{key}"))?;
+            pairs.push((key, value));
+        }
+        return Ok(Self { pairs });
+    }
+
+    pub(crate) fn get<T: std::str::FromStr>(&self, key: &str) -> Result<T, String> {
+        let value = self
+            .pairs
+            .iter()
+            .find(|(k, _)| *k == key)
+            .ok_or(format!("missing --{key}"))?
+            .1;
+        return value
+            .parse::<T>()
+            .map_err(|_| format!("invalid value {value} for --{key}"));
+    }
+
+    pub(crate) fn get_or<T: std::str::FromStr>(&self, key: &str, default: T) -> Result<T, String> {
+        if self.has(key) {
+            return self.get(key);
+        }
+        return Ok(default);
+    }
+
+    /// whether `--key` is given
+    pub(crate) fn has(&self, key: &str) -> bool {
+        return self.pairs.iter().any(|(k, _)| *k == key);
+    }
+}
+
+/// two decimals, like the parameters of AutoMoDe's own controllers
+pub(crate) fn round(val: f64) -> f64 {
+    return (val * 100.0).round() / 100.0;
+}
+
+/// moves `val` by up to a tenth of the range and keeps it inside
+fn nudge(rng: &mut impl Rng, val: f64, range: std::ops::Range<f64>) -> f64 {
+    let step = (range.end - range.start) / 10.0;
+    return round((val + rng.gen_range(-step..=step)).clamp(range.start, range.end));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const FSM: &str = "--nstates 4 --s0 5 --rep0 4.85 --n0 4 --n0x0 1 --c0x0 0 --p0x0 0.96 --n0x1 0 --c0x1 0 --p0x1 0.36 --n0x2 2 --c0x2 2 --p0x2 0.4 --n0x3 2 --c0x3 3 --p0x3 7 --w0x3 5.59 --s1 2 --n1 3 --n1x0 2 --c1x0 4 --p1x0 6 --w1x0 19.16 --n1x1 1 --c1x1 3 --p1x1 10 --w1x1 7.73 --n1x2 1 --c1x2 4 --p1x2 7 --w1x2 6.72 --s2 3 --n2 3 --n2x0 0 --c2x0 0 --p2x0 0.17 --n2x1 1 --c2x1 2 --p2x1 0.65 --n2x2 2 --c2x2 4 --p2x2 9 --w2x2 5.19 --s3 0 --rwm3 61 --n3 2 --n3x0 0 --c3x0 0 --p3x0 0.83 --n3x1 2 --c3x1 4 --p3x1 4 --w3x1 14.54";
+
+    /// every transition leaves its state for an existing one and no limit is exceeded
+    fn assert_valid(fsm: &Fsm) {
+        assert!((1..=MAX_STATES).contains(&fsm.states.len()), "{fsm:?}");
+        for (k, state) in fsm.states.iter().enumerate() {
+            assert!(state.transitions.len() <= MAX_TRANSITIONS, "{fsm:?}");
+            for transition in &state.transitions {
+                assert!(
+                    transition.to != k && transition.to < fsm.states.len(),
+                    "{fsm:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parse_to_args_round_trip() {
+        let fsm = Fsm::parse(&split_cmd(FSM)).unwrap();
+        assert_eq!(fsm.states.len(), 4);
+        assert_eq!(fsm.states[0].behaviour, Behaviour::Repulsion { rep: 4.85 });
+        assert_eq!(fsm.states[3].behaviour, Behaviour::Exploration { rwm: 61 });
+        // targets skip the source state
+        assert_eq!(
+            fsm.states[0]
+                .transitions
+                .iter()
+                .map(|x| x.to)
+                .collect::<Vec<usize>>(),
+            [2, 1, 3, 3]
+        );
+        assert_eq!(
+            fsm.states[0].transitions[3].condition,
+            Condition::NeighborsCount { p: 7.0, w: 5.59 }
+        );
+        assert_eq!(fsm.to_cmd(), FSM);
+    }
+
+    #[test]
+    fn random_and_mutated_machines_round_trip() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..50 {
+            let mut fsm = Fsm::random(&mut rng);
+            for _ in 0..20 {
+                assert_valid(&fsm);
+                assert_eq!(Fsm::parse(&fsm.to_args()).unwrap(), fsm);
+                fsm = fsm.mutate(&mut rng);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_machines() {
+        let err = Fsm::parse(&split_cmd(
+            "--nstates 2 --s0 1 --n0 1 --n0x0 1 --c0x0 5 --p0x0 0.5 --s1 1",
+        ))
+        .unwrap_err();
+        assert!(err.contains("unknown state 2"), "{err}");
+        assert!(Fsm::parse(&split_cmd("--nstates 1")).is_err());
+        assert!(Fsm::parse(&split_cmd("--nstates 1 --s0 9")).is_err());
+        assert!(Fsm::parse(&split_cmd("--nstates 1 --s0 0")).is_err());
+        assert!(Fsm::parse(&split_cmd("--nstates 1 --s0")).is_err());
+        assert!(Fsm::parse(&split_cmd("nstates 1 --s0 1")).is_err());
+    }
+}
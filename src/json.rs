@@ -0,0 +1,373 @@
+/// Minimal JSON value, enough to read and write the files of this crate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// keys keep their order
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(pairs) => pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            chars: content.chars().collect(),
+            pos: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.chars.len() {
+            return Err(format!("unexpected trailing data at {}", parser.pos));
+        }
+        return Ok(value);
+    }
+}
+
+impl From<f64> for Json {
+    fn from(val: f64) -> Self {
+        Json::Number(val)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(val: &str) -> Self {
+        Json::String(val.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(val: String) -> Self {
+        Json::String(val)
+    }
+}
+
+impl From<&[f64]> for Json {
+    fn from(vals: &[f64]) -> Self {
+        Json::Array(vals.iter().map(|x| Json::Number(*x)).collect())
+    }
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(x) => write!(f, "{x}"),
+            // JSON has no NaN or infinity
+            Json::Number(x) if !x.is_finite() => write!(f, "null"),
+            Json::Number(x) => write!(f, "{x}"),
+            Json::String(x) => write_string(f, x),
+            Json::Array(vals) => {
+                write!(f, "[")?;
+                for (i, val) in vals.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{val}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(pairs) => {
+                write!(f, "{{")?;
+                for (i, (key, val)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{val}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, val: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in val.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.chars.get(self.pos) != Some(&c) {
+            return Err(format!("expected '{c}' at {}", self.pos));
+        }
+        self.pos += 1;
+        return Ok(());
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for c in word.chars() {
+            if self.chars.get(self.pos) != Some(&c) {
+                return Err(format!("invalid literal at {}", self.pos));
+            }
+            self.pos += 1;
+        }
+        return Ok(value);
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        return match self.chars.get(self.pos) {
+            Some('n') => self.literal("null", Json::Null),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('[') => {
+                self.pos += 1;
+                let mut vals = Vec::new();
+                self.skip_whitespace();
+                if self.chars.get(self.pos) == Some(&']') {
+                    self.pos += 1;
+                    return Ok(Json::Array(vals));
+                }
+                loop {
+                    vals.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.chars.get(self.pos) {
+                        Some(',') => self.pos += 1,
+                        Some(']') => {
+                            self.pos += 1;
+                            return Ok(Json::Array(vals));
+                        }
+                        _ => return Err(format!("expected ',' or ']' at {}", self.pos)),
+                    }
+                }
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut pairs = Vec::new();
+                self.skip_whitespace();
+                if self.chars.get(self.pos) == Some(&'}') {
+                    self.pos += 1;
+                    return Ok(Json::Object(pairs));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    pairs.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.chars.get(self.pos) {
+                        Some(',') => self.pos += 1,
+                        Some('}') => {
+                            self.pos += 1;
+                            return Ok(Json::Object(pairs));
+                        }
+                        _ => return Err(format!("expected ',' or '}}' at {}", self.pos)),
+                    }
+                }
+            }
+            Some(_) => self.number(),
+            None => Err("unexpected end of input".to_string()),
+        };
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while self.pos < self.chars.len()
+            && matches!(
+                self.chars[self.pos],
+                '0'..='9' | '-' | '+' | '.' | 'e' | 'E'
+            )
+        {
+            self.pos += 1;
+        }
+        let text = self.chars[start..self.pos].iter().collect::<String>();
+        return text
+            .parse::<f64>()
+            .map(Json::Number)
+            .map_err(|_| format!("invalid number {text} at {start}"));
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if self.chars.get(self.pos) != Some(&'"') {
+            return Err(format!("expected string at {}", self.pos));
+        }
+        self.pos += 1;
+
+        let mut result = String::new();
+        loop {
+            let c = *self
+                .chars
+                .get(self.pos)
+                .ok_or("unterminated string".to_string())?;
+            self.pos += 1;
+            match c {
+                '"' => return Ok(result),
+                '\\' => {
+                    let escaped = *self
+                        .chars
+                        .get(self.pos)
+                        .ok_or("unterminated string".to_string())?;
+                    self.pos += 1;
+                    match escaped {
+                        'n' => result.push('\n'),
+                        'r' => result.push('\r'),
+                        't' => result.push('\t'),
+                        'b' => result.push('\u{8}'),
+                        'f' => result.push('\u{c}'),
+                        'u' => {
+                            let hex = self
+                                .chars
+                                .get(self.pos..self.pos + 4)
+                                .ok_or("invalid unicode escape".to_string())?
+                                .iter()
+                                .collect::<String>();
+                            self.pos += 4;
+                            let code = u32::from_str_radix(&hex, 16)
+                                .map_err(|_| format!("invalid unicode escape {hex}"))?;
+                            result.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        c => result.push(c),
+                    }
+                }
+                c => result.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes() {
+        let json = Json::parse(r#""a\"b\\c\/d\n\té\u0001""#).unwrap();
+        assert_eq!(json, Json::from("a\"b\\c/d\n\té\u{1}"));
+        // printing escapes what needs it and parses back
+        let printed = format!("{json}");
+        assert_eq!(printed, r#""a\"b\\c/d\n\té\u0001""#);
+        assert_eq!(Json::parse(&printed).unwrap(), json);
+
+        assert!(Json::parse(r#""\u12""#).is_err());
+        assert!(Json::parse(r#""abc"#).is_err());
+    }
+
+    #[test]
+    fn nesting() {
+        let json = Json::parse(
+            r#" {"a": [1, -2.5e3, {"b": null}], "c": {"d": [true, false, []]}, "e": {}} "#,
+        )
+        .unwrap();
+        assert_eq!(
+            json,
+            Json::Object(vec![
+                (
+                    "a".to_string(),
+                    Json::Array(vec![
+                        Json::from(1.0),
+                        Json::from(-2500.0),
+                        Json::Object(vec![("b".to_string(), Json::Null)])
+                    ])
+                ),
+                (
+                    "c".to_string(),
+                    Json::Object(vec![(
+                        "d".to_string(),
+                        Json::Array(vec![
+                            Json::Bool(true),
+                            Json::Bool(false),
+                            Json::Array(vec![])
+                        ])
+                    )])
+                ),
+                ("e".to_string(), Json::Object(vec![])),
+            ])
+        );
+        assert_eq!(
+            json.get("a").and_then(|x| x.as_array()).map(|x| x.len()),
+            Some(3)
+        );
+        assert_eq!(Json::parse(&format!("{json}")).unwrap(), json);
+    }
+
+    #[test]
+    fn trailing_data_is_rejected() {
+        assert!(Json::parse("{} {}").is_err());
+        assert!(Json::parse("[1, 2] x").is_err());
+        assert!(Json::parse("1 2").is_err());
+        assert_eq!(
+            Json::parse("[1, 2] \n").unwrap(),
+            Json::from(&[1.0, 2.0][..])
+        );
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        for content in [
+            "",
+            "[1, 2",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "[1,]",
+            "nul",
+            "{a: 1}",
+            "-",
+        ] {
+            assert!(Json::parse(content).is_err(), "{content}");
+        }
+    }
+
+    #[test]
+    fn non_finite_numbers_are_written_as_null() {
+        assert_eq!(format!("{}", Json::from(f64::NAN)), "null");
+        assert_eq!(
+            format!("{}", Json::from(&[1.5, f64::INFINITY][..])),
+            "[1.5,null]"
+        );
+    }
+}
@@ -1,152 +1,1032 @@
-use metrics::to_metic;
+#![allow(clippy::needless_return)]
+
+use animation::{animate_svg, AnimationOptions, Arena};
+use canonical::equivalent;
+use cleaning::{Cleaning, DEFAULT_STEPS};
+use comparison::{Comparison, ComparisonOptions};
+use controller::{Controller, ControllerKind};
+use curves::CurveOptions;
+use dataset::{Dataset, DEFAULT_TICK_RATE};
+use fidelity::{kendall_tau, spearman, Fidelity, MultiFidelity};
+use formats::{read_trajectory, write_trajectory, TrajectoryFormat};
+use fsm::split_cmd;
+use metrics::{METRIC_NAMES, STATE_METRIC_NAMES};
+use normalization::NormMode;
+use occupancy::{occupancy_json, occupancy_table, Occupancy};
+use perturbation::{robustness_json, robustness_table, PerturbationMode, Robustness};
 use rand::Rng;
+use report::Report;
+use scenario::Scenario;
+use std::path::Path;
+use trajectory::Trajectory;
 use utilities::*;
+use variants::{eval_variants, variants_json, variants_table, Variant};
 
+pub mod alignment;
+pub mod animation;
+pub mod bt;
+pub mod canonical;
+pub mod cleaning;
+pub mod comparison;
+pub mod controller;
+pub mod curves;
+pub mod dataset;
+pub mod distance;
+pub mod fidelity;
+pub mod formats;
+pub mod fsm;
+pub mod json;
+pub mod matching;
 pub mod metrics;
+pub mod normalization;
+pub mod occupancy;
+pub mod perturbation;
+pub mod phases;
+pub mod report;
+pub mod scenario;
+pub mod store;
+pub mod streaming;
+pub mod trajectory;
 pub mod utilities;
+pub mod variants;
+pub mod xml;
 
 pub const SWARM_SIZE: usize = 15;
 pub type SwarmPos = [(f64, f64); SWARM_SIZE];
 pub type SwarmMetric = [f64; 9];
+pub type SwarmState = [trajectory::RobotState; SWARM_SIZE];
 
 fn main() {
-    let real = "--nstates 4 --s0 5 --rep0 4.85 --n0 4 --n0x0 1 --c0x0 0 --p0x0 0.96 --n0x1 0 --c0x1 0 --p0x1 0.36 --n0x2 2 --c0x2 2 --p0x2 0.4 --n0x3 2 --c0x3 3 --p0x3 7 --w0x3 5.59 --s1 2 --n1 3 --n1x0 2 --c1x0 4 --p1x0 6 --w1x0 19.16 --n1x1 1 --c1x1 3 --p1x1 10 --w1x1 7.73 --n1x2 1 --c1x2 4 --p1x2 7 --w1x2 6.72 --s2 3 --n2 3 --n2x0 0 --c2x0 0 --p2x0 0.17 --n2x1 1 --c2x1 2 --p2x1 0.65 --n2x2 2 --c2x2 4 --p2x2 9 --w2x2 5.19 --s3 0 --rwm3 61 --n3 2 --n3x0 0 --c3x0 0 --p3x0 0.83 --n3x1 2 --c3x1 4 --p3x1 4 --w3x1 14.54";
-    let explore = "--nstates 1 --s0 0 --rwm0 50";
-    let random = "--nstates 4 --s0 4 --att0 4.08 --n0 3 --n0x0 2 --c0x0 1 --p0x0 0.95 --n0x1 2 --c0x1 4 --p0x1 1 --w0x1 16.11 --n0x2 1 --c0x2 3 --p0x2 5 --w0x2 1.13 --s1 1 --n1 1 --n1x0 0 --c1x0 3 --p1x0 10 --w1x0 10.7 --s2 0 --rwm2 69 --n2 3 --n2x0 2 --c2x0 1 --p2x0 0.76 --n2x1 2 --c2x1 5 --p2x1 0.25 --n2x2 1 --c2x2 0 --p2x2 0.47 --s3 0 --rwm3 78 --n3 4 --n3x0 0 --c3x0 4 --p3x0 8 --w3x0 1.53 --n3x1 1 --c3x1 0 --p3x1 0.26 --n3x2 2 --c3x2 4 --p3x2 4 --w3x2 5.4 --n3x3 2 --c3x3 4 --p3x3 10 --w3x3 14.41";
+    let mut args = std::env::args().collect::<Vec<String>>();
+    args.remove(0);
+    let command = if args.is_empty() {
+        "--compare".to_string()
+    } else {
+        args.remove(0)
+    };
+    let result = match command.as_str() {
+        "--fit-normalization" => fit_normalization(args),
+        "--verify" => verify(args),
+        "--convert" => convert(args),
+        "--clean" => clean(args),
+        "--canonical" => canonical(args),
+        "--simulate" => simulate(args),
+        "--score" => score(args),
+        "--curves" => curves(args),
+        "--rescore" => rescore(args),
+        "--eval-controller" => eval_controller(args),
+        "--random-search" => random_search(args),
+        "--fidelity-report" => fidelity_report(args),
+        "--compare" => compare(args),
+        "--report" => report(args),
+        "--animate" => animate(args),
+        "--scenario" => scenario(args),
+        "--variants" => variants(args),
+        "--robustness" => robustness(args),
+        "--occupancy" => occupancy(args),
+        _ => Err(format!("unexpected argument {command}")),
+    };
+
+    if let Err(e) = result {
+        println!("{e}");
+        print_usage();
+    }
+}
+
+fn print_usage() {
+    println!("\nvalid arguments are:");
+    println!("\t(none)\tcompare the real, explore and random controller, like --compare");
+    println!("\t--compare [--seeds <n>] [--resamples <n>] [--confidence <c>] [--permutations <n>] [--json <file>] [--controllers <file>] [--random <n>] [--kind fsm|bt]");
+    println!("\t--animate -o <svg> [--real <run>] [--trail <ticks>] [--stride <ticks>] [--speed <ticks/s>] [--arena <w>x<h>] [--no-com] [--no-radius] [--links] (--trajectory <file> | -s <seed> <controller>)");
+    println!("\t--scenario [<file>]\tshow what an ARGoS scenario configures, SCENARIO by default");
+    println!(
+        "\t--variants <file> [--base <argos>] [--dir <dir>] [--seeds <n>] [--json <file>] <controller>"
+    );
+    println!(
+        "\t--robustness [--perturb <mode>]... [--repeats <n>] [--seeds <n>] [--json <file>] <controller>"
+    );
+    println!("\t\tmodes join noise:<sigma>, dropout:<p> and freeze:<robots>@<tick> with +, real. perturbs the real runs");
+    println!("\t--occupancy [--seeds <n>] [--json <file>] [--controllers <file>] [--random <n>] [--kind fsm|bt]\tFSM state usage per controller, needs state ids in the simulator output");
+    println!("\t--report [-o <file>] (--db <n> | <--compare options>)");
+    println!("\t--fit-normalization [--mode minmax|zscore|quantile|robust] [--random <n>] [--kind fsm|bt] [--controllers <file>] [--seeds <n>] [-o <file>]");
+    println!("\t--verify [--tolerance <tol>] [--run <name>] <reference csv>");
+    println!("\t--convert [--from <format>] [--to <format>] [--tick-rate <hz>] <input> <output>");
+    println!("\t--clean [--steps <steps>] [--log <csv>] [--from <format>] [--to <format>] [--tick-rate <hz>] (<input> <output> | --dataset <dir>)");
+    println!("\t\tsteps are frozen, outliers:<m/s>, swaps:<m>, median:<frames>, kalman:<accel>/<noise> and clip[:<w>x<h>], default {DEFAULT_STEPS}");
+    println!("\t--canonical [--against <controller string>] <controller>\tsimplified FSM, its hash and whether it is equivalent to the other");
+    println!("\t--simulate -s <seed> -o <file> [--to <format>] <controller>");
+    println!("\t--score [--from <format>] [--tick-rate <hz>] <trajectory>");
+    println!("\t--curves [--smoothing <ticks>] [--threshold <diff>] [--sustain <ticks>] [--csv <file>] [--json <file>] (--trajectory <file> | -s <seed> <controller>)");
+    println!("\t--eval-controller [--bound <cost>] <controller>");
+    println!("\t--random-search [--candidates <n>] [--mutations <n>] [--kind fsm|bt]");
+    println!(
+        "\t--fidelity-report [--fidelity <ticks>x<seeds>] [--random <n>] [--kind fsm|bt] [--controllers <file>]"
+    );
+    println!("\t--rescore\trecompute stored evaluations under the active metric configuration");
+    println!("\tformats are botpos, long, argos, automode and jsonl");
+    println!(
+        "\ta controller is given by its --fsm-config or, starting with --nroot, --bt-config flags"
+    );
+}
+
+/// removes the flag `name` from the arguments and returns whether it was given
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let Some(i) = args.iter().position(|x| x == name) else {
+        return false;
+    };
+    args.remove(i);
+    return true;
+}
+
+/// removes `name <value>` from the arguments and returns the value
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let Some(i) = args.iter().position(|x| x == name) else {
+        return Ok(None);
+    };
+    if i + 1 >= args.len() {
+        return Err(format!("missing value for {name}"));
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    return Ok(Some(value));
+}
 
-    let random = random
-        .split(" ")
-        .map(|x| x.trim().to_string())
-        .collect::<Vec<String>>();
-    let real = real
-        .split(" ")
-        .map(|x| x.trim().to_string())
-        .collect::<Vec<String>>();
-    let explore = explore
-        .split(" ")
-        .map(|x| x.trim().to_string())
-        .collect::<Vec<String>>();
+fn parse_option<T: std::str::FromStr>(
+    args: &mut Vec<String>,
+    name: &str,
+    default: T,
+) -> Result<T, String> {
+    return match take_option(args, name)? {
+        Some(value) => value
+            .parse::<T>()
+            .map_err(|_| format!("invalid value {value} for {name}")),
+        None => Ok(default),
+    };
+}
+
+/// the controllers of `--controllers <file>`, one per line, and `--random <n>` random
+/// controllers of `--kind`, 100 random FSMs if neither is given
+fn take_controllers(args: &mut Vec<String>) -> Result<Vec<Vec<String>>, String> {
+    let controllers_file = take_option(args, "--controllers")?;
+    let num_random = parse_option(args, "--random", 0)?;
+    let kind = take_kind(args)?;
+
+    let mut controllers = Vec::new();
+    if let Some(path) = controllers_file {
+        let content = std::fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+        for line in content.lines().filter(|x| !x.trim().is_empty()) {
+            let controller = split_cmd(line);
+            Controller::parse(&controller).map_err(|e| format!("{path}: {e}"))?;
+            controllers.push(controller);
+        }
+    }
+    let num_random = if controllers.is_empty() && num_random == 0 {
+        100
+    } else {
+        num_random
+    };
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..num_random {
+        controllers.push(Controller::random(kind, &mut rng).to_args());
+    }
+    return Ok(controllers);
+}
+
+/// the architecture of `--kind fsm|bt`, FSMs by default
+fn take_kind(args: &mut Vec<String>) -> Result<ControllerKind, String> {
+    return match take_option(args, "--kind")? {
+        Some(name) => ControllerKind::parse(&name).ok_or(format!("unknown controller kind {name}")),
+        None => Ok(ControllerKind::Fsm),
+    };
+}
+
+/// fits the normalization on random FSMs or the controllers listed in a file, one per line
+fn fit_normalization(mut args: Vec<String>) -> Result<(), String> {
+    let mode = take_option(&mut args, "--mode")?.unwrap_or("minmax".to_string());
+    let mode = NormMode::parse(&mode).ok_or(format!("unknown normalization mode {mode}"))?;
+    let controllers = take_controllers(&mut args)?;
+    let num_seeds = parse_option(&mut args, "--seeds", 1)?;
+    let out = take_option(&mut args, "-o")?.unwrap_or("normalization.csv".to_string());
+    if let Some(arg) = args.first() {
+        return Err(format!("unexpected argument {arg}"));
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut seeds: Vec<i32> = Vec::with_capacity(num_seeds);
+    for _ in 0..num_seeds {
+        seeds.push(rng.gen_range(0..0x7FFFFFFF));
+    }
+
+    let eval: Evaluator = Evaluator::new();
+    let normalization = eval.fit_normalization(controllers, seeds, mode);
+    normalization.save(&out)?;
+    println!("wrote {out}");
+    return Ok(());
+}
+
+/// checks an external reference csv against the real metrics recomputed for the active configuration
+fn verify(mut args: Vec<String>) -> Result<(), String> {
+    let tolerance = parse_option(&mut args, "--tolerance", 1e-9)?;
+    let run = take_option(&mut args, "--run")?;
+    if args.len() != 1 {
+        return Err("expected exactly one reference csv".to_string());
+    }
+
+    let path = &args[0];
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let reference = parse_metrics_csv(&content).map_err(|e| format!("{path}: {e}"))?;
+
+    let eval: Evaluator = Evaluator::new();
+    let run = run.unwrap_or(eval.real[0].name.clone());
+    let max_diff = eval.verify_reference(&run, &reference)?;
+    for real in &eval.real {
+        println!("real run {} uses ticks {}", real.name, real.window);
+    }
+    println!("max abs difference per metric: {max_diff:?}");
+
+    if max_diff.iter().any(|&x| x > tolerance) {
+        println!("{path} does not match the recomputed metrics");
+        std::process::exit(1);
+    }
+    println!("{path} matches the recomputed metrics");
+    return Ok(());
+}
+
+fn parse_format(name: Option<String>) -> Result<Option<TrajectoryFormat>, String> {
+    return match name {
+        Some(name) => TrajectoryFormat::parse(&name)
+            .map(Some)
+            .ok_or(format!("unknown trajectory format {name}")),
+        None => Ok(None),
+    };
+}
+
+/// the output format, detected from the extension of the output path if not given
+fn output_format(name: Option<String>, path: &str) -> Result<TrajectoryFormat, String> {
+    return Ok(parse_format(name)?.unwrap_or(TrajectoryFormat::detect(Path::new(path), "")));
+}
+
+/// reads a trajectory in one format and writes it in another
+fn convert(mut args: Vec<String>) -> Result<(), String> {
+    let from = parse_format(take_option(&mut args, "--from")?)?;
+    let to = take_option(&mut args, "--to")?;
+    let tick_rate = parse_option(&mut args, "--tick-rate", 10.0)?;
+    if args.len() != 2 {
+        return Err("expected an input and an output file".to_string());
+    }
+
+    let trajectory = read_trajectory(Path::new(&args[0]), from, tick_rate)?;
+    let to = output_format(to, &args[1])?;
+    write_trajectory(Path::new(&args[1]), to, &trajectory, tick_rate)?;
+    println!("wrote {} frames to {}", trajectory.len(), args[1]);
+    return Ok(());
+}
+
+/// repairs tracking errors of a real recording, or of every run of REAL_DATA which is written
+/// to a directory with a manifest that can be used as REAL_DATA
+fn clean(mut args: Vec<String>) -> Result<(), String> {
+    let from = parse_format(take_option(&mut args, "--from")?)?;
+    let to = take_option(&mut args, "--to")?;
+    let tick_rate = parse_option(&mut args, "--tick-rate", 10.0)?;
+    let steps = take_option(&mut args, "--steps")?.unwrap_or(DEFAULT_STEPS.to_string());
+    let log = take_option(&mut args, "--log")?;
+    let dataset = take_option(&mut args, "--dataset")?;
+
+    let arena = Scenario::load(env!("SCENARIO")).ok().map(|x| x.bounds());
+    let cleaning = Cleaning::parse(&steps, arena)?;
+    if cleaning.steps.is_empty() {
+        return Err("no cleaning steps".to_string());
+    }
+
+    let mut log_csv = "run,step,tick,robot,other,from_x,from_y,to_x,to_y\n".to_string();
+    match dataset {
+        Some(dir) => {
+            if !args.is_empty() {
+                return Err(format!("unexpected arguments {}", args.join(" ")));
+            }
+            let dataset = match runtime_env("REAL_DATA", option_env!("REAL_DATA")) {
+                Some(path) => Dataset::load(&path)?,
+                None => Dataset::builtin(),
+            };
+            std::fs::create_dir_all(&dir).map_err(|e| format!("{dir}: {e}"))?;
+            let to = parse_format(to)?.unwrap_or_default();
+            let extension = match to {
+                TrajectoryFormat::JsonLines => "jsonl",
+                TrajectoryFormat::BotPosCsv | TrajectoryFormat::LongCsv => "csv",
+                TrajectoryFormat::ArgosLog | TrajectoryFormat::AutomodeOutput => "log",
+            };
+
+            let mut manifest = "file, mission, date, robot_count, tick_rate\n".to_string();
+            for run in &dataset.runs {
+                let (trajectory, run_log) = cleaning.clean(&run.trajectory);
+                println!("{}", run.name);
+                print!("{}", run_log.summary());
+                log_csv.push_str(&run_log.to_csv(&run.name));
+
+                let file = format!("{}.{extension}", run.name);
+                let tick_rate = run.tick_rate.unwrap_or(DEFAULT_TICK_RATE);
+                write_trajectory(&Path::new(&dir).join(&file), to, &trajectory, tick_rate)?;
+                // unknown metadata stays empty instead of being made up
+                let known = |x: Option<String>| x.unwrap_or_default();
+                manifest.push_str(&format!(
+                    "{file}, {}, {}, {}, {}\n",
+                    known(run.mission.clone()),
+                    known(run.date.clone()),
+                    known(run.robot_count.map(|x| x.to_string())),
+                    known(run.tick_rate.map(|x| x.to_string()))
+                ));
+            }
+            let path = Path::new(&dir).join("manifest.csv");
+            std::fs::write(&path, manifest).map_err(|e| format!("{}: {e}", path.display()))?;
+            println!("wrote {} runs to {dir}", dataset.runs.len());
+        }
+        None => {
+            if args.len() != 2 {
+                return Err("expected an input and an output file or --dataset <dir>".to_string());
+            }
+            let trajectory = read_trajectory(Path::new(&args[0]), from, tick_rate)?;
+            let (trajectory, run_log) = cleaning.clean(&trajectory);
+            print!("{}", run_log.summary());
+            let name = Path::new(&args[0])
+                .file_stem()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default();
+            log_csv.push_str(&run_log.to_csv(&name));
+
+            let to = output_format(to, &args[1])?;
+            write_trajectory(Path::new(&args[1]), to, &trajectory, tick_rate)?;
+            println!("wrote {} frames to {}", trajectory.len(), args[1]);
+        }
+    }
+
+    if let Some(path) = log {
+        std::fs::write(&path, log_csv).map_err(|e| format!("{path}: {e}"))?;
+        println!("wrote the changes to {path}");
+    }
+    return Ok(());
+}
+
+/// prints the simplified, canonically numbered controller and its hash
+fn canonical(mut args: Vec<String>) -> Result<(), String> {
+    let against = take_option(&mut args, "--against")?;
+    let controller = Controller::parse(&args)?;
+
+    if let Controller::Fsm(fsm) = &controller {
+        let simplification = fsm.simplify();
+        println!(
+            "{} of {} states left, {}",
+            simplification.fsm.states.len(),
+            fsm.states.len(),
+            simplification.summary()
+        );
+    }
+    println!("{}", controller.canonical_cmd());
+    println!("hash {}", controller.canonical_hash());
+
+    if let Some(other) = against {
+        match equivalent(&args, &split_cmd(&other))? {
+            true => println!("equivalent"),
+            false => println!("not equivalent"),
+        }
+    }
+    return Ok(());
+}
+
+/// runs one simulation and saves the trajectory
+fn simulate(mut args: Vec<String>) -> Result<(), String> {
+    let seed = take_option(&mut args, "-s")?.ok_or("missing -s <seed>")?;
+    let seed = seed
+        .parse::<i32>()
+        .map_err(|_| format!("invalid seed {seed}"))?;
+    let out = take_option(&mut args, "-o")?.ok_or("missing -o <file>")?;
+    let to = output_format(take_option(&mut args, "--to")?, &out)?;
+    Controller::parse(&args)?;
+
+    let eval: Evaluator = Evaluator::new();
+    let run = eval.run_streaming(args, seed);
+    let trajectory = Trajectory::from_tick_rate(run.frames, eval.sim_tick_rate);
+    let trajectory = Trajectory {
+        states: run.states,
+        ..trajectory
+    };
+    write_trajectory(Path::new(&out), to, &trajectory, eval.sim_tick_rate)?;
+    println!("wrote {} frames to {out}", trajectory.len());
+    return Ok(());
+}
+
+/// metric distance of a saved trajectory to the real runs
+fn score(mut args: Vec<String>) -> Result<(), String> {
+    let from = parse_format(take_option(&mut args, "--from")?)?;
+    let eval: Evaluator = Evaluator::new();
+    let tick_rate = parse_option(&mut args, "--tick-rate", eval.sim_tick_rate)?;
+    if args.len() != 1 {
+        return Err("expected exactly one trajectory file".to_string());
+    }
+
+    let trajectory = read_trajectory(Path::new(&args[0]), from, tick_rate)?;
+    let trajectory = trajectory
+        .resample_to_rate(eval.sim_tick_rate, eval.resampling)
+        .map_err(|e| format!("{}: {e}", args[0]))?;
+    if trajectory.len() < eval.experiment_len {
+        return Err(format!(
+            "{} has {} ticks but {} are needed",
+            args[0],
+            trajectory.len(),
+            eval.experiment_len
+        ));
+    }
+
+    let evaluation = eval.eval_states(&trajectory.frames, &trajectory.states);
+    println!("{:?}", evaluation.metric);
+    for (phase, metric) in eval.phases.iter().zip(&evaluation.phases) {
+        println!(
+            "{}\t{}-{}\tweight {}\t{metric:?}",
+            phase.name, phase.ticks.start, phase.ticks.end, phase.weight
+        );
+    }
+    println!("{}", eval.cost(&evaluation));
+    if let Some(error) = evaluation.trajectory_error {
+        println!("path error\t{}", error.path_error);
+        println!("frechet\t{}", error.frechet);
+        println!("final position error\t{}", error.final_position_error);
+    }
+
+    for (name, val) in STATE_METRIC_NAMES.iter().zip(evaluation.state_metrics) {
+        if let Some(val) = val {
+            println!("mean {name}\t{val}");
+        }
+    }
+    return Ok(());
+}
+
+/// per-tick differences of a saved trajectory or a new simulation and the tick each metric diverges
+fn curves(mut args: Vec<String>) -> Result<(), String> {
+    let defaults = CurveOptions::default();
+    let options = CurveOptions {
+        smoothing: parse_option(&mut args, "--smoothing", defaults.smoothing)?,
+        threshold: parse_option(&mut args, "--threshold", defaults.threshold)?,
+        sustain: parse_option(&mut args, "--sustain", defaults.sustain)?,
+    };
+    let csv = take_option(&mut args, "--csv")?;
+    let json = take_option(&mut args, "--json")?;
+    let trajectory = take_option(&mut args, "--trajectory")?;
+    let seed = take_option(&mut args, "-s")?;
+
+    let eval: Evaluator = Evaluator::new();
+    let sim_pos = match (trajectory, seed) {
+        (Some(path), None) => {
+            if let Some(arg) = args.first() {
+                return Err(format!("unexpected argument {arg}"));
+            }
+            read_trajectory(Path::new(&path), None, eval.sim_tick_rate)?
+                .resample_to_rate(eval.sim_tick_rate, eval.resampling)
+                .map_err(|e| format!("{path}: {e}"))?
+                .frames
+        }
+        (None, Some(seed)) => {
+            let seed = seed
+                .parse::<i32>()
+                .map_err(|_| format!("invalid seed {seed}"))?;
+            Controller::parse(&args)?;
+            eval.run_experiment(args, seed)
+        }
+        _ => {
+            return Err("expected either --trajectory <file> or -s <seed> <controller>".to_string())
+        }
+    };
+    let curves = eval.distance_curves(&sim_pos, options)?;
+    for (i, name) in METRIC_NAMES.iter().enumerate() {
+        let mean = curves.per_tick.iter().map(|m| m[i]).sum::<f64>() / curves.per_tick.len() as f64;
+        let divergence = match curves.divergence[i] {
+            Some(t) => format!(
+                "diverges at tick {t} ({:.1} s)",
+                t as f64 / eval.sim_tick_rate
+            ),
+            None => "does not diverge".to_string(),
+        };
+        println!("{name:<24} mean {mean:.4}, {divergence}");
+    }
+
+    if let Some(path) = csv {
+        std::fs::write(&path, curves.to_csv()).map_err(|e| format!("{path}: {e}"))?;
+        println!("wrote {path}");
+    }
+    if let Some(path) = json {
+        std::fs::write(&path, format!("{}\n", curves.to_json()))
+            .map_err(|e| format!("{path}: {e}"))?;
+        println!("wrote {path}");
+    }
+    return Ok(());
+}
+
+/// cost of a controller, simulations are stopped early once the cost is certain to exceed the bound
+fn eval_controller(mut args: Vec<String>) -> Result<(), String> {
+    let bound = take_option(&mut args, "--bound")?;
+    Controller::parse(&args)?;
+
+    let eval: Evaluator = Evaluator::new();
+    let cost = match bound {
+        Some(bound) => {
+            let bound = bound
+                .parse::<f64>()
+                .map_err(|_| format!("invalid bound {bound}"))?;
+            eval.eval_controller_bounded(args, bound)
+        }
+        None => eval.eval_controller(args),
+    };
+    println!("{cost}");
+    return Ok(());
+}
+
+/// samples random controllers and keeps the best, then tries `--mutations` mutations of the best
+/// so far, in multi-fidelity mode if `LOW_FIDELITY` is set, otherwise simulations are stopped
+/// once they cannot beat the best cost so far
+fn random_search(mut args: Vec<String>) -> Result<(), String> {
+    let num_candidates = parse_option(&mut args, "--candidates", 100)?;
+    let num_mutations = parse_option(&mut args, "--mutations", 0)?;
+    let kind = take_kind(&mut args)?;
+    if let Some(arg) = args.first() {
+        return Err(format!("unexpected argument {arg}"));
+    }
+
+    let eval: Evaluator = Evaluator::new();
+    let mut multi_fidelity = match eval.low_fidelity {
+        Some(low) => Some(MultiFidelity::new(&eval, low, eval.promote_fraction)?),
+        None => None,
+    };
+
+    let mut rng = rand::thread_rng();
+    let mut best: (f64, Option<Controller>) = (f64::INFINITY, None);
+    // candidates equivalent to an evaluated one are skipped
+    let mut evaluated = std::collections::HashSet::new();
+    let mut duplicates = 0;
+    for i in 0..num_candidates + num_mutations {
+        let controller = match &best.1 {
+            Some(best) if i >= num_candidates => best.mutate(&mut rng),
+            _ => Controller::random(kind, &mut rng),
+        };
+        if !evaluated.insert(controller.canonical_hash()) {
+            duplicates += 1;
+            continue;
+        }
+        let controller_cmd = controller.to_args();
+        let cost = match &mut multi_fidelity {
+            Some(multi_fidelity) => multi_fidelity.eval_controller(controller_cmd),
+            None => eval.eval_controller_bounded(controller_cmd, best.0),
+        };
+        if cost < best.0 {
+            println!("{cost}\t{}", controller.to_cmd());
+            best = (cost, Some(controller));
+        }
+    }
+
+    println!("skipped {duplicates} candidates equivalent to evaluated ones");
+    if let Some(multi_fidelity) = multi_fidelity {
+        let (evaluated, promoted) = multi_fidelity.counts();
+        println!("promoted {promoted} of {evaluated} candidates to full fidelity");
+        match multi_fidelity.rank_correlation() {
+            Some(rho) => println!("rank correlation of low and full fidelity: {rho:.3}"),
+            None => println!("too few promoted candidates for a rank correlation"),
+        }
+    }
+    let best_cmd = best.1.map(|x| x.to_cmd()).unwrap_or_default();
+    println!("best {}\t{best_cmd}", best.0);
+    return Ok(());
+}
+
+/// evaluates controllers at low and full fidelity on shared seeds and compares the rankings
+fn fidelity_report(mut args: Vec<String>) -> Result<(), String> {
+    let fidelity = take_option(&mut args, "--fidelity")?;
+    let controllers = take_controllers(&mut args)?;
+    if let Some(arg) = args.first() {
+        return Err(format!("unexpected argument {arg}"));
+    }
+
+    let eval: Evaluator = Evaluator::new();
+    let fidelity = match fidelity {
+        Some(name) => Fidelity::parse(&name).ok_or(format!("invalid fidelity {name}"))?,
+        None => eval
+            .low_fidelity
+            .ok_or("missing --fidelity and LOW_FIDELITY is not set")?,
+    };
+    let low = eval.at_fidelity(fidelity)?;
 
     let mut rng = rand::thread_rng();
     let mut seeds: Vec<i32> = Vec::new();
-    for _ in 0..3 {
+    for _ in 0..usize::max(eval.num_of_experiments, fidelity.seeds) {
         seeds.push(rng.gen_range(0..0x7FFFFFFF));
     }
 
-    // let eval: Evaluator = Evaluator::new();
-    // let random = eval.eval(random, seeds[0]);
-    // let exp = eval.eval(exp, seeds[0]);
-    // let real = eval.eval(real, seeds[0]);
-    // let real_exp = get_real_bot_data(1200)
-    //dbg!(real);
-    //dbg!(exp);
-    //dbg!(random);
+    let mut low_costs = Vec::with_capacity(controllers.len());
+    let mut full_costs = Vec::with_capacity(controllers.len());
+    println!("low\tfull\tcontroller");
+    for controller in controllers {
+        let low_cost =
+            low.cost(&low.eval_all(controller.clone(), seeds[..fidelity.seeds].to_vec()));
+        let full_cost = eval.cost(&eval.eval_all(
+            controller.clone(),
+            seeds[..eval.num_of_experiments].to_vec(),
+        ));
+        println!("{low_cost:.4}\t{full_cost:.4}\t{}", controller.join(" "));
+        low_costs.push(low_cost);
+        full_costs.push(full_cost);
+    }
 
-    let [real_sim, real_exp, explore, random] =
-        eval((real, seeds[0]), (explore, seeds[1]), (random, seeds[2]));
+    let format = |x: Option<f64>| x.map(|x| format!("{x:.3}")).unwrap_or("-".to_string());
+    println!(
+        "{} against full fidelity: spearman {}, kendall {}",
+        fidelity.name(),
+        format(spearman(&low_costs, &full_costs)),
+        format(kendall_tau(&low_costs, &full_costs))
+    );
+    return Ok(());
+}
 
-    //  let real_sim = dist(real_sim, real_exp);
-    //  let explore = dist(explore, real_exp);
-    //  let random = dist(random, real_exp);
+/// new evaluation rows for all stored trajectories, see `TRAJECTORY_STORE`
+fn rescore(args: Vec<String>) -> Result<(), String> {
+    if let Some(arg) = args.first() {
+        return Err(format!("unexpected argument {arg}"));
+    }
 
-    dbg!(real_sim);
-    dbg!(real_exp);
-    dbg!(explore);
-    dbg!(random);
+    let eval: Evaluator = Evaluator::new();
+    let (rescored, skipped) = eval.rescore()?;
+    println!("rescored {rescored} evaluations, skipped {skipped} without stored trajectories");
+    return Ok(());
 }
 
-fn eval(
-    real: (Vec<String>, i32),
-    explore: (Vec<String>, i32),
-    random: (Vec<String>, i32),
-) -> [SwarmMetric; 4] {
+/// animated SVG of a saved trajectory or a new simulation next to a real run
+fn animate(mut args: Vec<String>) -> Result<(), String> {
+    let defaults = AnimationOptions::default();
+    let out = take_option(&mut args, "-o")?.ok_or("missing -o <file>")?;
+    let arena = take_option(&mut args, "--arena")?
+        .map(|x| Arena::parse(&x).ok_or(format!("invalid arena {x}")))
+        .transpose()?;
     let eval: Evaluator = Evaluator::new();
-    let real_sim = eval.run_experiment(real.0, real.1);
-    let explore = eval.run_experiment(explore.0, explore.1);
-    let random = eval.run_experiment(random.0, random.1);
-    let real_exp = get_real_bot_data(1200);
-
-    assert_eq!(real_sim.len(), 1200);
-    assert_eq!(explore.len(), 1200);
-    assert_eq!(random.len(), 1200);
-    assert_eq!(real_exp.len(), 1200);
-
-    let swarm_mode_dist = eval.swarm_mode_dist;
-    let density_radius = eval.density_radius;
-
-    let real_sim = to_metic(&real_sim, swarm_mode_dist, density_radius);
-    let real_exp = to_metic(&real_exp, swarm_mode_dist, density_radius);
-    let explore = to_metic(&explore, swarm_mode_dist, density_radius);
-    let random = to_metic(&random, swarm_mode_dist, density_radius);
-
-    let min = eval.metics_norm_min;
-    let max = eval.metics_norm_max;
-
-    // let real_sim = real_sim
-    //     .iter()
-    //     .zip(real_exp.iter())
-    //     .map(|(sim, exp)| dist(sim.to_owned(), exp.clone()))
-    //     .collect::<Vec<_>>();
-    // let explore = explore
-    //     .iter()
-    //     .zip(real_exp.iter())
-    //     .map(|(explore, exp)| dist(explore.to_owned(), exp.clone()))
-    //     .collect::<Vec<_>>();
-    // let random = random
-    //     .iter()
-    //     .zip(real_exp.iter())
-    //     .map(|(random, exp)| dist(random.to_owned(), exp.clone()))
-    //     .collect::<Vec<_>>();
-
-    // let real_sim = real_sim
-    //     .iter()
-    //     .map(|x| norm(x.to_owned(), min, max))
-    //     .collect();
-    // let real_exp = real_exp
-    //     .iter()
-    //     .map(|x| norm(x.to_owned(), min, max))
-    //     .collect();
-    // let explore = explore
-    //     .iter()
-    //     .map(|x| norm(x.to_owned(), min, max))
-    //     .collect();
-    // let random = random
-    //     .iter()
-    //     .map(|x| norm(x.to_owned(), min, max))
-    //     .collect();
-
-    let real_sim = sum(real_sim);
-    let real_exp = sum(real_exp);
-    let exp = sum(explore);
-    let random = sum(random);
-
-    return [real_sim, real_exp, exp, random];
+    let arena = arena.or(eval.argos.as_ref().map(|x| x.bounds()));
+    let options = AnimationOptions {
+        trail: parse_option(&mut args, "--trail", defaults.trail)?,
+        center_of_mass: !take_flag(&mut args, "--no-com"),
+        max_radius: !take_flag(&mut args, "--no-radius"),
+        neighbor_links: take_flag(&mut args, "--links"),
+        arena,
+        stride: parse_option(&mut args, "--stride", defaults.stride)?,
+        speed: parse_option(&mut args, "--speed", defaults.speed)?,
+    };
+    if options.speed.is_nan() || options.speed <= 0.0 {
+        return Err(format!("speed {} is not positive", options.speed));
+    }
+    let real = take_option(&mut args, "--real")?;
+    let trajectory = take_option(&mut args, "--trajectory")?;
+    let seed = take_option(&mut args, "-s")?;
+
+    let real = match real {
+        Some(name) => eval
+            .real
+            .iter()
+            .find(|x| x.name == name)
+            .ok_or(format!("unknown real run {name}"))?,
+        None => eval.real.first().ok_or("no real runs")?,
+    };
+    let (name, sim_pos) = match (trajectory, seed) {
+        (Some(path), None) => {
+            if let Some(arg) = args.first() {
+                return Err(format!("unexpected argument {arg}"));
+            }
+            let frames = read_trajectory(Path::new(&path), None, eval.sim_tick_rate)?
+                .resample_to_rate(eval.sim_tick_rate, eval.resampling)
+                .map_err(|e| format!("{path}: {e}"))?
+                .frames;
+            (path, frames)
+        }
+        (None, Some(seed)) => {
+            let seed = seed
+                .parse::<i32>()
+                .map_err(|_| format!("invalid seed {seed}"))?;
+            Controller::parse(&args)?;
+            (format!("seed {seed}"), eval.run_experiment(args, seed))
+        }
+        _ => {
+            return Err("expected either --trajectory <file> or -s <seed> <controller>".to_string())
+        }
+    };
+    let sim_pos = eval.align_sim(&sim_pos)?;
+    let real_name = format!("real {}", real.name);
+    let panels = [
+        (name.as_str(), sim_pos.as_slice()),
+        (real_name.as_str(), real.pos.as_slice()),
+    ];
+    std::fs::write(&out, animate_svg(&panels, options)).map_err(|e| format!("{out}: {e}"))?;
+    println!("wrote {out}");
+    return Ok(());
 }
 
-fn dist(sm1: SwarmMetric, sm2: SwarmMetric) -> SwarmMetric {
-    let mut dist = SwarmMetric::default();
-    for i in 0..dist.len() {
-        dist[i] = (sm1[i].powi(2) - sm2[i].powi(2)).sqrt();
+/// the settings of an ARGoS scenario and whether they fit the evaluator
+fn scenario(args: Vec<String>) -> Result<(), String> {
+    let path = match args.as_slice() {
+        [] => env!("SCENARIO").to_string(),
+        [path] => path.clone(),
+        _ => return Err("expected at most one scenario file".to_string()),
+    };
+
+    let scenario = Scenario::load(&path)?;
+    let experiment_len = scenario
+        .experiment_len()
+        .map(|x| format!("{x} ticks"))
+        .unwrap_or("unlimited".to_string());
+    let seed = scenario
+        .random_seed
+        .map(|x| format!("{x}"))
+        .unwrap_or("-".to_string());
+    let bounds = scenario.bounds();
+    println!("length\t{} s, {experiment_len}", scenario.length);
+    println!("tick rate\t{} ticks/s", scenario.ticks_per_second);
+    println!("random seed\t{seed}");
+    println!("robots\t{}", scenario.robot_count);
+    println!(
+        "arena\t{} x {} x {} at {:?}",
+        scenario.arena_size.0, scenario.arena_size.1, scenario.arena_size.2, scenario.arena_center
+    );
+    println!(
+        "floor\t({}, {}) to ({}, {}) inside {} walls",
+        bounds.min.0,
+        bounds.min.1,
+        bounds.max.0,
+        bounds.max.1,
+        scenario.walls.len()
+    );
+    for light in &scenario.lights {
+        println!(
+            "light {}\t{:?}, {} intensity {}",
+            light.id, light.position, light.color, light.intensity
+        );
     }
-    return dist;
+
+    let sim_tick_rate = option_env!("SIM_TICK_RATE")
+        .map(|v| v.parse::<f64>().unwrap())
+        .unwrap_or(10.0);
+    let experiment_len = env!("EXPERIMENT_LEN").parse::<usize>().unwrap();
+    match scenario.validate(experiment_len, sim_tick_rate, SWARM_SIZE) {
+        Ok(()) => println!("matches the evaluator configuration"),
+        Err(e) => println!("{e}"),
+    }
+    return Ok(());
+}
+
+/// evaluates a controller in variants of the scenario, one per line of the variants file
+fn variants(mut args: Vec<String>) -> Result<(), String> {
+    let base = take_option(&mut args, "--base")?.unwrap_or(env!("SCENARIO").to_string());
+    let dir = match take_option(&mut args, "--dir")? {
+        Some(dir) => std::path::PathBuf::from(dir),
+        None => std::env::temp_dir().join(format!("automode-eval-variants-{}", std::process::id())),
+    };
+    let num_seeds = parse_option(&mut args, "--seeds", 10)?;
+    let json = take_option(&mut args, "--json")?;
+    if args.is_empty() {
+        return Err("missing variants file".to_string());
+    }
+    let file = args.remove(0);
+    Controller::parse(&args)?;
+
+    let content = std::fs::read_to_string(&file).map_err(|e| format!("{file}: {e}"))?;
+    let variants = Variant::parse_all(&content).map_err(|e| format!("{file}: {e}"))?;
+
+    let mut rng = rand::thread_rng();
+    let mut seeds: Vec<i32> = Vec::new();
+    for _ in 0..num_seeds {
+        seeds.push(rng.gen_range(0..0x7FFFFFFF));
+    }
+
+    let eval: Evaluator = Evaluator::new();
+    let results = eval_variants(&eval, Path::new(&base), &variants, &dir, args, &seeds)?;
+    print!("{}", variants_table(&results));
+    println!("scenarios are in {}", dir.display());
+
+    if let Some(path) = json {
+        std::fs::write(&path, format!("{}\n", variants_json(&results)))
+            .map_err(|e| format!("{path}: {e}"))?;
+        println!("wrote {path}");
+    }
+    return Ok(());
+}
+
+/// cost distribution of a controller under perturbations of the simulated and real positions
+fn robustness(mut args: Vec<String>) -> Result<(), String> {
+    let mut modes = Vec::new();
+    while let Some(name) = take_option(&mut args, "--perturb")? {
+        modes.push(PerturbationMode::parse(&name).ok_or(format!("invalid perturbation {name}"))?);
+    }
+    let repeats = parse_option(&mut args, "--repeats", 5)?;
+    let num_seeds = parse_option(&mut args, "--seeds", 10)?;
+    let json = take_option(&mut args, "--json")?;
+    Controller::parse(&args)?;
+    if repeats == 0 {
+        return Err("--repeats must be at least 1".to_string());
+    }
+
+    let eval: Evaluator = Evaluator::new();
+    if modes.is_empty() {
+        let half = eval.experiment_len / 2;
+        for name in [
+            "noise:0.01".to_string(),
+            "dropout:0.05".to_string(),
+            format!("freeze:3@{half}"),
+            "real.noise:0.01".to_string(),
+        ] {
+            modes.push(PerturbationMode::parse(&name).unwrap());
+        }
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut seeds: Vec<i32> = Vec::new();
+    for _ in 0..num_seeds {
+        seeds.push(rng.gen_range(0..0x7FFFFFFF));
+    }
+    let sim_pos = eval
+        .run_seeds(args, seeds)
+        .into_iter()
+        .map(|x| x.1)
+        .filter(|x| x.len() >= eval.experiment_len)
+        .collect::<Vec<Vec<SwarmPos>>>();
+    if sim_pos.is_empty() {
+        return Err("no simulation finished".to_string());
+    }
+
+    let clean = PerturbationMode {
+        name: "clean".to_string(),
+        sim: Vec::new(),
+        real: Vec::new(),
+    };
+    let results = std::iter::once((clean, 1))
+        .chain(modes.into_iter().map(|x| (x, repeats)))
+        .map(|(mode, repeats)| Robustness::new(&eval, mode, &sim_pos, repeats, &mut rng))
+        .collect::<Vec<Robustness>>();
+    print!("{}", robustness_table(&results));
+
+    if let Some(path) = json {
+        std::fs::write(&path, format!("{}\n", robustness_json(&results)))
+            .map_err(|e| format!("{path}: {e}"))?;
+        println!("wrote {path}");
+    }
+    return Ok(());
+}
+
+/// state occupancy, transitions and behaviour entropy of controllers on shared seeds
+fn occupancy(mut args: Vec<String>) -> Result<(), String> {
+    let num_seeds = parse_option(&mut args, "--seeds", 10)?;
+    let json = take_option(&mut args, "--json")?;
+    let controllers = if args.iter().any(|x| x == "--controllers" || x == "--random") {
+        take_controllers(&mut args)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, controller)| (format!("c{i}"), controller))
+            .collect()
+    } else {
+        default_controllers()
+    };
+    if let Some(arg) = args.first() {
+        return Err(format!("unexpected argument {arg}"));
+    }
+
+    let eval: Evaluator = Evaluator::new();
+    let mut rng = rand::thread_rng();
+    let mut seeds: Vec<i32> = Vec::new();
+    for _ in 0..num_seeds {
+        seeds.push(rng.gen_range(0..0x7FFFFFFF));
+    }
+
+    let results = controllers
+        .into_iter()
+        .map(|(name, controller_cmd)| {
+            let runs = eval
+                .eval_seeds(controller_cmd.clone(), seeds.clone())
+                .into_iter()
+                .filter_map(|(_, evaluation)| evaluation.occupancy)
+                .collect::<Vec<Occupancy>>();
+            (name, controller_cmd, runs)
+        })
+        .collect::<Vec<_>>();
+    print!("{}", occupancy_table(&results));
+
+    if let Some(path) = json {
+        std::fs::write(&path, format!("{}\n", occupancy_json(&results)))
+            .map_err(|e| format!("{path}: {e}"))?;
+        println!("wrote {path}");
+    }
+    return Ok(());
+}
+
+/// simulates controllers on shared seeds and reports confidence intervals and pairwise tests,
+/// the real, explore and random controller if no controllers are given
+fn compare(mut args: Vec<String>) -> Result<(), String> {
+    let json = take_option(&mut args, "--json")?;
+    let eval: Evaluator = Evaluator::new();
+    let comparison = take_comparison(&eval, &mut args)?;
+    print!("{}", comparison.to_table());
+
+    if let Some(path) = json {
+        std::fs::write(&path, format!("{}\n", comparison.to_json()))
+            .map_err(|e| format!("{path}: {e}"))?;
+        println!("wrote {path}");
+    }
+    return Ok(());
 }
 
-fn sum(metrics: Vec<SwarmMetric>) -> SwarmMetric {
-    let mut sum = SwarmMetric::default();
-    let len = metrics.len() as f64;
+/// HTML report of a new comparison or of the best evaluations in the database
+fn report(mut args: Vec<String>) -> Result<(), String> {
+    let out = take_option(&mut args, "-o")?.unwrap_or("report.html".to_string());
+    let from_db = take_option(&mut args, "--db")?
+        .map(|x| {
+            x.parse::<usize>()
+                .map_err(|_| format!("invalid value {x} for --db"))
+        })
+        .transpose()?;
 
-    for m in metrics {
-        for i in 0..sum.len() {
-            sum[i] += m[i];
+    let eval: Evaluator = Evaluator::new();
+    let report = match from_db {
+        Some(num) => {
+            if let Some(arg) = args.first() {
+                return Err(format!("unexpected argument {arg}"));
+            }
+            Report::from_db(&eval, num)?
         }
+        None => {
+            let comparison = take_comparison(&eval, &mut args)?;
+            print!("{}", comparison.to_table());
+            Report::from_comparison(&eval, &comparison)?
+        }
+    };
+
+    std::fs::write(&out, report.to_html()).map_err(|e| format!("{out}: {e}"))?;
+    println!("wrote {out}");
+    return Ok(());
+}
+
+/// runs the comparison described by the remaining arguments
+fn take_comparison(eval: &Evaluator, args: &mut Vec<String>) -> Result<Comparison, String> {
+    let defaults = ComparisonOptions::default();
+    let options = ComparisonOptions {
+        resamples: parse_option(args, "--resamples", defaults.resamples)?,
+        confidence: parse_option(args, "--confidence", defaults.confidence)?,
+        permutations: parse_option(args, "--permutations", defaults.permutations)?,
+    };
+    let num_seeds = parse_option(args, "--seeds", 10)?;
+    let controllers = if args.iter().any(|x| x == "--controllers" || x == "--random") {
+        take_controllers(args)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, controller)| (format!("c{i}"), controller))
+            .collect()
+    } else {
+        default_controllers()
+    };
+    if let Some(arg) = args.first() {
+        return Err(format!("unexpected argument {arg}"));
+    }
+    if !(options.confidence > 0.0 && options.confidence < 1.0) {
+        return Err(format!(
+            "confidence {} is not in (0, 1)",
+            options.confidence
+        ));
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut seeds: Vec<i32> = Vec::new();
+    for _ in 0..num_seeds {
+        seeds.push(rng.gen_range(0..0x7FFFFFFF));
+    }
+
+    let comparison = Comparison::run(eval, &controllers, &seeds, options, &mut rng)?;
+    if comparison.seeds.len() < seeds.len() {
+        println!(
+            "dropped {} seeds that did not finish for every controller",
+            seeds.len() - comparison.seeds.len()
+        );
     }
+    return Ok(comparison);
+}
 
-    for i in 0..sum.len() {
-        sum[i] /= len;
+fn default_controllers() -> Vec<(String, Vec<String>)> {
+    let real = "--nstates 4 --s0 5 --rep0 4.85 --n0 4 --n0x0 1 --c0x0 0 --p0x0 0.96 --n0x1 0 --c0x1 0 --p0x1 0.36 --n0x2 2 --c0x2 2 --p0x2 0.4 --n0x3 2 --c0x3 3 --p0x3 7 --w0x3 5.59 --s1 2 --n1 3 --n1x0 2 --c1x0 4 --p1x0 6 --w1x0 19.16 --n1x1 1 --c1x1 3 --p1x1 10 --w1x1 7.73 --n1x2 1 --c1x2 4 --p1x2 7 --w1x2 6.72 --s2 3 --n2 3 --n2x0 0 --c2x0 0 --p2x0 0.17 --n2x1 1 --c2x1 2 --p2x1 0.65 --n2x2 2 --c2x2 4 --p2x2 9 --w2x2 5.19 --s3 0 --rwm3 61 --n3 2 --n3x0 0 --c3x0 0 --p3x0 0.83 --n3x1 2 --c3x1 4 --p3x1 4 --w3x1 14.54";
+    let explore = "--nstates 1 --s0 0 --rwm0 50";
+    let random = "--nstates 4 --s0 4 --att0 4.08 --n0 3 --n0x0 2 --c0x0 1 --p0x0 0.95 --n0x1 2 --c0x1 4 --p0x1 1 --w0x1 16.11 --n0x2 1 --c0x2 3 --p0x2 5 --w0x2 1.13 --s1 1 --n1 1 --n1x0 0 --c1x0 3 --p1x0 10 --w1x0 10.7 --s2 0 --rwm2 69 --n2 3 --n2x0 2 --c2x0 1 --p2x0 0.76 --n2x1 2 --c2x1 5 --p2x1 0.25 --n2x2 1 --c2x2 0 --p2x2 0.47 --s3 0 --rwm3 78 --n3 4 --n3x0 0 --c3x0 4 --p3x0 8 --w3x0 1.53 --n3x1 1 --c3x1 0 --p3x1 0.26 --n3x2 2 --c3x2 4 --p3x2 4 --w3x2 5.4 --n3x3 2 --c3x3 4 --p3x3 10 --w3x3 14.41";
+
+    return [("real", real), ("explore", explore), ("random", random)]
+        .into_iter()
+        .map(|(name, controller)| (name.to_string(), split_cmd(controller)))
+        .collect();
+}
+
+#[allow(dead_code)]
+fn dist(sm1: SwarmMetric, sm2: SwarmMetric) -> SwarmMetric {
+    let mut dist = SwarmMetric::default();
+    for i in 0..dist.len() {
+        dist[i] = (sm1[i].powi(2) - sm2[i].powi(2)).sqrt();
     }
-    return sum;
+    return dist;
 }
 
+#[allow(dead_code)]
 fn norm(metrics: SwarmMetric, min: SwarmMetric, max: SwarmMetric) -> SwarmMetric {
     let mut norm = SwarmMetric::default();
     // (x - x_min) / (x_max - x_min)
@@ -0,0 +1,305 @@
+use crate::{metrics::euclidean_dist, utilities::mean, SwarmPos, SWARM_SIZE};
+
+/// Optimal assignment between simulated and real robots and the per-robot errors along it.
+///
+/// Robot ids are arbitrary, so simulated robot i is compared with the real robot
+/// `assignments[w][i]` in window w, chosen to minimise the summed distance of the window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrajectoryMatch {
+    /// number of ticks per window, the last window may be shorter
+    pub window: usize,
+    pub assignments: Vec<[usize; SWARM_SIZE]>,
+    /// average distance per tick between a simulated robot and its matched real robot
+    pub path_error: [f64; SWARM_SIZE],
+    /// discrete Fréchet distance between a simulated robot and its matched real path
+    pub frechet: [f64; SWARM_SIZE],
+    /// distance between the last positions
+    pub final_position_error: [f64; SWARM_SIZE],
+}
+
+/// Errors of a simulation against the real runs, each a mean over the matched robots.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrajectoryError {
+    pub path_error: f64,
+    pub frechet: f64,
+    pub final_position_error: f64,
+}
+
+impl TrajectoryError {
+    /// the errors of a simulation that was terminated early
+    pub fn infinite() -> Self {
+        return Self {
+            path_error: f64::INFINITY,
+            frechet: f64::INFINITY,
+            final_position_error: f64::INFINITY,
+        };
+    }
+
+    pub fn is_finite(&self) -> bool {
+        return self.path_error.is_finite()
+            && self.frechet.is_finite()
+            && self.final_position_error.is_finite();
+    }
+
+    /// element wise mean, `None` for no errors
+    pub fn mean(errors: &[TrajectoryError]) -> Option<Self> {
+        if errors.is_empty() {
+            return None;
+        }
+        let mean = |f: fn(&TrajectoryError) -> f64| {
+            errors.iter().map(f).sum::<f64>() / errors.len() as f64
+        };
+        return Some(Self {
+            path_error: mean(|x| x.path_error),
+            frechet: mean(|x| x.frechet),
+            final_position_error: mean(|x| x.final_position_error),
+        });
+    }
+}
+
+impl TrajectoryMatch {
+    pub fn mean_path_error(&self) -> f64 {
+        return mean(&self.path_error);
+    }
+
+    pub fn mean_frechet(&self) -> f64 {
+        return mean(&self.frechet);
+    }
+
+    pub fn mean_final_position_error(&self) -> f64 {
+        return mean(&self.final_position_error);
+    }
+
+    pub fn error(&self) -> TrajectoryError {
+        return TrajectoryError {
+            path_error: self.mean_path_error(),
+            frechet: self.mean_frechet(),
+            final_position_error: self.mean_final_position_error(),
+        };
+    }
+}
+
+/// matches the simulated to the real robots, either once for the whole trajectory or,
+/// if `window` is set, independently for each window of that many ticks
+pub fn match_trajectories(
+    sim_pos: &[SwarmPos],
+    real_pos: &[SwarmPos],
+    window: Option<usize>,
+) -> TrajectoryMatch {
+    assert_eq!(sim_pos.len(), real_pos.len());
+    assert!(!sim_pos.is_empty());
+    let window = window.unwrap_or(sim_pos.len()).max(1);
+
+    let mut assignments = Vec::new();
+    // the real path each simulated robot is matched to, stitched together over the windows
+    let mut matched_pos = Vec::with_capacity(real_pos.len());
+    for (sim, real) in sim_pos.chunks(window).zip(real_pos.chunks(window)) {
+        let mut cost = vec![vec![0.0; SWARM_SIZE]; SWARM_SIZE];
+        for (sim_frame, real_frame) in sim.iter().zip(real.iter()) {
+            for i in 0..SWARM_SIZE {
+                for j in 0..SWARM_SIZE {
+                    cost[i][j] += euclidean_dist(&sim_frame[i], &real_frame[j]);
+                }
+            }
+        }
+
+        let assignment = hungarian(&cost);
+        let mut robots = [0; SWARM_SIZE];
+        robots.copy_from_slice(&assignment);
+
+        for real_frame in real {
+            let mut frame = SwarmPos::default();
+            for i in 0..SWARM_SIZE {
+                frame[i] = real_frame[robots[i]];
+            }
+            matched_pos.push(frame);
+        }
+        assignments.push(robots);
+    }
+
+    let mut path_error = [0.0; SWARM_SIZE];
+    let mut frechet = [0.0; SWARM_SIZE];
+    let mut final_position_error = [0.0; SWARM_SIZE];
+    for i in 0..SWARM_SIZE {
+        let sim_path = sim_pos.iter().map(|x| x[i]).collect::<Vec<(f64, f64)>>();
+        let real_path = matched_pos
+            .iter()
+            .map(|x| x[i])
+            .collect::<Vec<(f64, f64)>>();
+
+        for (s, r) in sim_path.iter().zip(real_path.iter()) {
+            path_error[i] += euclidean_dist(s, r);
+        }
+        path_error[i] /= sim_path.len() as f64;
+        frechet[i] = frechet_dist(&sim_path, &real_path);
+        final_position_error[i] =
+            euclidean_dist(sim_path.last().unwrap(), real_path.last().unwrap());
+    }
+
+    return TrajectoryMatch {
+        window,
+        assignments,
+        path_error,
+        frechet,
+        final_position_error,
+    };
+}
+
+/// discrete Fréchet distance between two paths
+pub fn frechet_dist(path1: &[(f64, f64)], path2: &[(f64, f64)]) -> f64 {
+    let m = path2.len();
+    let mut pre = vec![f64::INFINITY; m];
+    let mut cur = vec![f64::INFINITY; m];
+
+    for (i, p1) in path1.iter().enumerate() {
+        for (j, p2) in path2.iter().enumerate() {
+            let d = euclidean_dist(p1, p2);
+            cur[j] = match (i, j) {
+                (0, 0) => d,
+                (0, _) => f64::max(cur[j - 1], d),
+                (_, 0) => f64::max(pre[0], d),
+                _ => f64::max(f64::min(pre[j], f64::min(pre[j - 1], cur[j - 1])), d),
+            };
+        }
+        std::mem::swap(&mut pre, &mut cur);
+    }
+
+    return pre[m - 1];
+}
+
+/// Hungarian algorithm for a square cost matrix, returns the column assigned to each row
+pub fn hungarian(cost: &[Vec<f64>]) -> Vec<usize> {
+    let n = cost.len();
+    // potentials and matching are 1 indexed, index 0 is a virtual column
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; n + 1];
+    let mut row_of = vec![0; n + 1];
+    let mut way = vec![0; n + 1];
+
+    for row in 1..=n {
+        row_of[0] = row;
+        let mut col0 = 0;
+        let mut min_v = vec![f64::INFINITY; n + 1];
+        let mut used = vec![false; n + 1];
+
+        loop {
+            used[col0] = true;
+            let row0 = row_of[col0];
+            let mut delta = f64::INFINITY;
+            let mut col1 = 0;
+
+            for col in 1..=n {
+                if used[col] {
+                    continue;
+                }
+                let reduced = cost[row0 - 1][col - 1] - u[row0] - v[col];
+                if reduced < min_v[col] {
+                    min_v[col] = reduced;
+                    way[col] = col0;
+                }
+                if min_v[col] < delta {
+                    delta = min_v[col];
+                    col1 = col;
+                }
+            }
+
+            for col in 0..=n {
+                if used[col] {
+                    u[row_of[col]] += delta;
+                    v[col] -= delta;
+                } else {
+                    min_v[col] -= delta;
+                }
+            }
+
+            col0 = col1;
+            if row_of[col0] == 0 {
+                break;
+            }
+        }
+
+        loop {
+            let col1 = way[col0];
+            row_of[col0] = row_of[col1];
+            col0 = col1;
+            if col0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut assignment = vec![0; n];
+    for col in 1..=n {
+        assignment[row_of[col] - 1] = col - 1;
+    }
+    return assignment;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hungarian_finds_the_cheapest_assignment() {
+        // the greedy choice of row 0 (column 1) forces a worse total
+        let cost = vec![
+            vec![4.0, 1.0, 3.0],
+            vec![2.0, 0.0, 5.0],
+            vec![3.0, 2.0, 2.0],
+        ];
+        assert_eq!(hungarian(&cost), vec![1, 0, 2]);
+
+        let cost = vec![vec![1.0, 2.0], vec![1.0, 10.0]];
+        assert_eq!(hungarian(&cost), vec![1, 0]);
+    }
+
+    #[test]
+    fn frechet_of_known_paths() {
+        let path = [(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)];
+        assert_eq!(frechet_dist(&path, &path), 0.0);
+
+        let shifted = path.map(|(x, y)| (x, y + 1.0));
+        assert_eq!(frechet_dist(&path, &shifted), 1.0);
+
+        // the middle point has to be paired with an end of the short path
+        let short = [(0.0, 1.0), (2.0, 1.0)];
+        assert!((frechet_dist(&path, &short) - 2.0f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn matching_undoes_a_permutation_of_the_robots() {
+        let sim = (0..5)
+            .map(|t| {
+                let mut frame = SwarmPos::default();
+                for (i, robot) in frame.iter_mut().enumerate() {
+                    *robot = (i as f64, t as f64 * 0.1 * i as f64);
+                }
+                frame
+            })
+            .collect::<Vec<SwarmPos>>();
+        // real robot j is simulated robot (j + 1) % SWARM_SIZE
+        let real = sim
+            .iter()
+            .map(|frame| {
+                let mut real = SwarmPos::default();
+                for j in 0..SWARM_SIZE {
+                    real[j] = frame[(j + 1) % SWARM_SIZE];
+                }
+                real
+            })
+            .collect::<Vec<SwarmPos>>();
+
+        let matched = match_trajectories(&sim, &real, None);
+        for i in 0..SWARM_SIZE {
+            assert_eq!(matched.assignments[0][i], (i + SWARM_SIZE - 1) % SWARM_SIZE);
+        }
+        assert_eq!(
+            matched.error(),
+            TrajectoryError {
+                path_error: 0.0,
+                frechet: 0.0,
+                final_position_error: 0.0
+            }
+        );
+    }
+}
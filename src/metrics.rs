@@ -1,41 +1,67 @@
-use crate::{SwarmMetric, SwarmPos, SWARM_SIZE};
+use crate::{SwarmMetric, SwarmPos, SwarmState, SWARM_SIZE};
 
-pub fn metric_dist(
-    sim_swarm_pos: &Vec<SwarmPos>,
-    swarm_mode_dist: f64,
-    density_radius: f64,
-    real_swarm_metic: &Vec<SwarmMetric>,
-    metics_norm_min: &SwarmMetric,
-    metics_norm_max: &SwarmMetric,
-) -> SwarmMetric {
-    let sim_swarm_metic = to_metic(sim_swarm_pos, swarm_mode_dist, density_radius);
-    let mut sum = SwarmMetric::default();
+/// names of the values of a `SwarmMetric`, in order
+pub const METRIC_NAMES: [&str; 9] = [
+    "center_of_mass_x",
+    "center_of_mass_y",
+    "max_swarm_shift",
+    "swarm_mode_index",
+    "longest_path",
+    "max_radius",
+    "local_density",
+    "nears_neighbor_distance",
+    "beta_index",
+];
 
-    assert_eq!(sim_swarm_metic.len(), real_swarm_metic.len());
-    for (sim, real) in sim_swarm_metic.iter().zip(real_swarm_metic.iter()) {
-        let mut metric_norm = SwarmMetric::default();
-        // (x - x_min) / (x_max - x_min)
-        for i in 0..metric_norm.len() {
-            metric_norm[i] =
-                (sim[i] - metics_norm_min[i]) / (metics_norm_max[i] - metics_norm_min[i]);
+/// names of the values of `state_metric`, in order
+pub const STATE_METRIC_NAMES: [&str; 3] = ["polarization", "milling", "mean_speed"];
+
+/// Revision of the metric definitions used to turn positions into a [`SwarmMetric`].
+///
+/// `V1` reproduces the original implementation bit for bit, including its deviations from the
+/// documented definitions: [`dist`] applies `sqrt` only to the x term, the nearest neighbour
+/// distance keeps the largest distance, the local density is summed inside the inner loop and
+/// robots are excluded from their own neighbourhood by comparing positions by value.
+///
+/// `V2` follows the documented definitions. Results of different versions are not comparable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetricVersion {
+    #[default]
+    V1,
+    V2,
+}
+
+impl MetricVersion {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MetricVersion::V1 => "v1",
+            MetricVersion::V2 => "v2",
         }
+    }
 
-        for i in 0..sum.len() {
-            sum[i] += (&metric_norm[i] - real[i]).powi(2);
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "v1" | "1" => Some(MetricVersion::V1),
+            "v2" | "2" => Some(MetricVersion::V2),
+            _ => None,
         }
     }
 
-    for i in 0..sum.len() {
-        sum[i] = sum[i].sqrt();
+    /// distance between two points as defined by this version
+    pub fn dist(&self, vec1: &(f64, f64), vec2: &(f64, f64)) -> f64 {
+        match self {
+            MetricVersion::V1 => dist(vec1, vec2),
+            MetricVersion::V2 => euclidean_dist(vec1, vec2),
+        }
     }
-    return sum;
 }
 
 /// apples the swarm_metic function to all positions
 pub fn to_metic(
-    all_swarm_pos: &Vec<SwarmPos>,
+    all_swarm_pos: &[SwarmPos],
     swarm_mode_dist: f64,
     density_radius: f64,
+    version: MetricVersion,
 ) -> Vec<SwarmMetric> {
     let mut result = Vec::with_capacity(all_swarm_pos.len() - 1);
 
@@ -46,10 +72,11 @@ pub fn to_metic(
     for swarm_pos in all_swarm_pos_it {
         result.push(swarm_metic(
             swarm_pos,
-            &swarm_origen,
-            &pre_swarm_pos,
+            swarm_origen,
+            pre_swarm_pos,
             swarm_mode_dist,
             density_radius,
+            version,
         ));
         pre_swarm_pos = swarm_pos;
     }
@@ -66,14 +93,15 @@ pub fn swarm_metic(
     pre_swarm_pos: &SwarmPos,
     swarm_mode_dist: f64,
     density_radius: f64,
+    version: MetricVersion,
 ) -> SwarmMetric {
     let center_of_mass = center_of_mass(swarm_pos);
-    let max_swarm_shift = max_dist(swarm_pos, pre_swarm_pos);
-    let swarm_mode_index = swarm_mode_index(swarm_pos, &center_of_mass, swarm_mode_dist);
-    let longest_path = max_dist(swarm_pos, swarm_origen);
-    let max_radius = max_dist(swarm_pos, &[center_of_mass; SWARM_SIZE]);
-    let local_density = local_density(swarm_pos, density_radius);
-    let nears_neighbor_distance = nears_neighbor_distance(swarm_pos);
+    let max_swarm_shift = max_dist(swarm_pos, pre_swarm_pos, version);
+    let swarm_mode_index = swarm_mode_index(swarm_pos, &center_of_mass, swarm_mode_dist, version);
+    let longest_path = max_dist(swarm_pos, swarm_origen, version);
+    let max_radius = max_dist(swarm_pos, &[center_of_mass; SWARM_SIZE], version);
+    let local_density = local_density(swarm_pos, density_radius, version);
+    let nears_neighbor_distance = nears_neighbor_distance(swarm_pos, version);
     let beta_index = beta_index(swarm_pos);
 
     return [
@@ -96,14 +124,28 @@ pub fn center_of_mass(swarm_pos: &SwarmPos) -> (f64, f64) {
         sum.1 += pos.1;
     }
 
-    sum.0 = sum.0 / SWARM_SIZE as f64;
-    sum.1 = sum.1 / SWARM_SIZE as f64;
+    sum.0 /= SWARM_SIZE as f64;
+    sum.1 /= SWARM_SIZE as f64;
 
     return sum;
 }
 
 /// 7-Average nearest neighbour distance is the sum of the distance to the nearest neighbour of each agent averaged over the total number of agents.
-pub fn nears_neighbor_distance(swarm_pos: &SwarmPos) -> f64 {
+pub fn nears_neighbor_distance(swarm_pos: &SwarmPos, version: MetricVersion) -> f64 {
+    if version == MetricVersion::V2 {
+        let mut sum = 0.0;
+        for (i, self_pos) in swarm_pos.iter().enumerate() {
+            let mut nn_dist = f64::INFINITY;
+            for (j, other_pos) in swarm_pos.iter().enumerate() {
+                if i != j && euclidean_dist(self_pos, other_pos) < nn_dist {
+                    nn_dist = euclidean_dist(self_pos, other_pos);
+                }
+            }
+            sum += nn_dist;
+        }
+        return sum / SWARM_SIZE as f64;
+    }
+
     let mut sum = 0.0;
     for self_pos in swarm_pos {
         let mut nn_dist = 0.0;
@@ -118,7 +160,19 @@ pub fn nears_neighbor_distance(swarm_pos: &SwarmPos) -> f64 {
 }
 
 /// 6-Average local density is the sum of the number of agents in the local radius r of each agent averaged over the total number of agents.
-pub fn local_density(swarm_pos: &SwarmPos, radius: f64) -> f64 {
+pub fn local_density(swarm_pos: &SwarmPos, radius: f64, version: MetricVersion) -> f64 {
+    if version == MetricVersion::V2 {
+        let mut sum = 0;
+        for (i, self_pos) in swarm_pos.iter().enumerate() {
+            for (j, other_pos) in swarm_pos.iter().enumerate() {
+                if i != j && euclidean_dist(self_pos, other_pos) < radius {
+                    sum += 1;
+                }
+            }
+        }
+        return sum as f64 / SWARM_SIZE as f64;
+    }
+
     let mut sum = 0;
     for self_pos in swarm_pos {
         let mut num_of_neighbor = 0;
@@ -133,12 +187,12 @@ pub fn local_density(swarm_pos: &SwarmPos, radius: f64) -> f64 {
 }
 
 /// calculates the distance of all the points of swarm_pos1 and swarm_pos2 and returns the maximum
-pub fn max_dist(swarm_pos1: &SwarmPos, swarm_pos2: &SwarmPos) -> f64 {
+pub fn max_dist(swarm_pos1: &SwarmPos, swarm_pos2: &SwarmPos, version: MetricVersion) -> f64 {
     let mut max_dist = 0.0;
 
     for (pos1, pos2) in swarm_pos1.iter().zip(swarm_pos2.iter()) {
-        if dist(pos1, pos2) > max_dist {
-            max_dist = dist(pos1, pos2);
+        if version.dist(pos1, pos2) > max_dist {
+            max_dist = version.dist(pos1, pos2);
         }
     }
     return max_dist;
@@ -157,16 +211,22 @@ pub fn max_dist(swarm_pos1: &SwarmPos, swarm_pos2: &SwarmPos) -> f64 {
 ///                i=0
 ///             distance(l, li) < 0.1
 ///
+/// In `V2` an agent is only excluded from its own frequency count by index, so co-located agents count.
 pub fn swarm_mode_index(
     swarm_pos: &SwarmPos,
     center_of_mass: &(f64, f64),
     swarm_mode_dist: f64,
+    version: MetricVersion,
 ) -> f64 {
     let mut swarm_mode: Vec<(usize, usize)> = Vec::with_capacity(SWARM_SIZE);
-    for pos in swarm_pos {
+    for (i, pos) in swarm_pos.iter().enumerate() {
         let mut neighbor_count = (0, 0);
-        for other_pos in swarm_pos {
-            if pos == other_pos {
+        for (j, other_pos) in swarm_pos.iter().enumerate() {
+            let is_self = match version {
+                MetricVersion::V1 => pos == other_pos,
+                MetricVersion::V2 => i == j,
+            };
+            if is_self {
                 continue;
             }
             if f64::abs(pos.0 - other_pos.0) < swarm_mode_dist {
@@ -195,15 +255,21 @@ pub fn swarm_mode_index(
 
     let swarm_mode_x = swarm_pos[index.0].0;
     let swarm_mode_y = swarm_pos[index.1].1;
-    let swarm_mode_index = dist(center_of_mass, &(swarm_mode_x, swarm_mode_y));
+    let swarm_mode_index = version.dist(center_of_mass, &(swarm_mode_x, swarm_mode_y));
     return swarm_mode_index;
 }
 
+/// `V1` distance, only the x term is under the square root
 pub fn dist(vec1: &(f64, f64), vec2: &(f64, f64)) -> f64 {
     return f64::sqrt((vec1.0 - vec2.0) * (vec1.0 - vec2.0))
         + ((vec1.1 - vec2.1) * (vec1.1 - vec2.1));
 }
 
+/// `V2` distance, the euclidean distance between two points
+pub fn euclidean_dist(vec1: &(f64, f64), vec2: &(f64, f64)) -> f64 {
+    return f64::sqrt((vec1.0 - vec2.0).powi(2) + (vec1.1 - vec2.1).powi(2));
+}
+
 /// The beta index is a metric that measures
 /// the connectivity of the graph by dividing the number of paths between nodes
 /// by the number of nodes in the graph. For the swarm beta index, the path is
@@ -238,3 +304,270 @@ pub fn beta_index(swarm_pos: &SwarmPos) -> f64 {
     }
     return paths_count as f64 / SWARM_SIZE as f64;
 }
+
+/// metrics of the reported robot states of one frame, `None` if a robot lacks what a metric needs
+///
+/// polarization is the length of the mean heading vector, 1 if all robots head the same way.
+/// milling is the length of the mean angular momentum of the headings around the center of mass,
+/// 1 if all robots circle it in the same direction. mean_speed is the mean length of the velocities.
+pub fn state_metric(swarm_pos: &SwarmPos, swarm_state: &SwarmState) -> [Option<f64>; 3] {
+    let headings = swarm_state
+        .iter()
+        .map(|x| x.heading)
+        .collect::<Option<Vec<f64>>>();
+    let speeds = swarm_state
+        .iter()
+        .map(|x| x.velocity.map(|(vx, vy)| f64::hypot(vx, vy)))
+        .collect::<Option<Vec<f64>>>();
+
+    let polarization = headings.as_ref().map(|headings| {
+        let (sin, cos) = headings.iter().fold((0.0, 0.0), |(s, c), h| {
+            (
+                s + h.sin() / SWARM_SIZE as f64,
+                c + h.cos() / SWARM_SIZE as f64,
+            )
+        });
+        f64::hypot(sin, cos)
+    });
+    let milling = headings.as_ref().map(|headings| {
+        let com = center_of_mass(swarm_pos);
+        let mut sum = 0.0;
+        for (pos, heading) in swarm_pos.iter().zip(headings) {
+            let r = (pos.0 - com.0, pos.1 - com.1);
+            let len = f64::hypot(r.0, r.1);
+            if len > 0.0 {
+                sum += (r.0 * heading.sin() - r.1 * heading.cos()) / len;
+            }
+        }
+        (sum / SWARM_SIZE as f64).abs()
+    });
+    let mean_speed = speeds.map(|x| x.iter().sum::<f64>() / SWARM_SIZE as f64);
+
+    return [polarization, milling, mean_speed];
+}
+
+/// mean of `state_metric` over the frames, `None` for the metrics no frame has, all `None` without
+/// states
+pub fn mean_state_metric(all_swarm_pos: &[SwarmPos], states: &[SwarmState]) -> [Option<f64>; 3] {
+    let per_frame = all_swarm_pos
+        .iter()
+        .zip(states)
+        .map(|(pos, state)| state_metric(pos, state))
+        .collect::<Vec<[Option<f64>; 3]>>();
+    return std::array::from_fn(|i| {
+        let vals = per_frame.iter().filter_map(|x| x[i]).collect::<Vec<f64>>();
+        (!vals.is_empty()).then(|| vals.iter().sum::<f64>() / vals.len() as f64)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_metric_eq(got: SwarmMetric, expected: SwarmMetric) {
+        for i in 0..got.len() {
+            assert!(
+                (got[i] - expected[i]).abs() < 1e-12,
+                "metric {i}: got {} expected {}",
+                got[i],
+                expected[i]
+            );
+        }
+    }
+
+    /// 14 robots at the origin and robot 14 at (3, 4), it came from (3, 0) and started at the origin
+    fn reference_frame() -> [SwarmPos; 3] {
+        let swarm_origen = [(0.0, 0.0); SWARM_SIZE];
+        let mut pre_swarm_pos = [(0.0, 0.0); SWARM_SIZE];
+        pre_swarm_pos[14] = (3.0, 0.0);
+        let mut swarm_pos = [(0.0, 0.0); SWARM_SIZE];
+        swarm_pos[14] = (3.0, 4.0);
+        return [swarm_pos, swarm_origen, pre_swarm_pos];
+    }
+
+    #[test]
+    fn reference_frame_v1() {
+        let [swarm_pos, swarm_origen, pre_swarm_pos] = reference_frame();
+        let metric = swarm_metic(
+            &swarm_pos,
+            &swarm_origen,
+            &pre_swarm_pos,
+            0.5,
+            0.5,
+            MetricVersion::V1,
+        );
+
+        // center of mass is (1/5, 4/15), v1 distance of (dx, dy) is |dx| + dy^2
+        assert_metric_eq(
+            metric,
+            [
+                1.0 / 5.0,
+                4.0 / 15.0,
+                16.0,
+                1.0 / 5.0 + 16.0 / 225.0,
+                19.0,
+                14.0 / 5.0 + 3136.0 / 225.0,
+                0.0,
+                19.0,
+                91.0 / 15.0,
+            ],
+        );
+    }
+
+    #[test]
+    fn reference_frame_v2() {
+        let [swarm_pos, swarm_origen, pre_swarm_pos] = reference_frame();
+        let metric = swarm_metic(
+            &swarm_pos,
+            &swarm_origen,
+            &pre_swarm_pos,
+            0.5,
+            0.5,
+            MetricVersion::V2,
+        );
+
+        // co-located robots count as neighbours at distance 0
+        assert_metric_eq(
+            metric,
+            [
+                1.0 / 5.0,
+                4.0 / 15.0,
+                4.0,
+                1.0 / 3.0,
+                5.0,
+                14.0 / 3.0,
+                182.0 / 15.0,
+                1.0 / 3.0,
+                91.0 / 15.0,
+            ],
+        );
+    }
+
+    /// robot 0 and 1 are 0.3 apart, all others are far away from each other
+    fn sparse_frame() -> SwarmPos {
+        let mut swarm_pos = SwarmPos::default();
+        for (i, pos) in swarm_pos.iter_mut().enumerate() {
+            *pos = (10.0 * i as f64, 10.0 * i as f64);
+        }
+        swarm_pos[1] = (0.3, 0.0);
+        return swarm_pos;
+    }
+
+    #[test]
+    fn local_density_reference() {
+        let swarm_pos = sparse_frame();
+
+        // v1 adds the running neighbour count once per inner iteration: 14 for robot 0, 15 for robot 1
+        assert!((local_density(&swarm_pos, 0.5, MetricVersion::V1) - 29.0 / 15.0).abs() < 1e-12);
+        assert!((local_density(&swarm_pos, 0.5, MetricVersion::V2) - 2.0 / 15.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn nears_neighbor_distance_reference() {
+        let mut swarm_pos = [(0.0, 0.0); SWARM_SIZE];
+        for (i, pos) in swarm_pos.iter_mut().enumerate() {
+            *pos = (i as f64, 0.0);
+        }
+
+        // on a line with unit spacing the nearest neighbour is always 1 away, the farthest 14 for the ends
+        assert!((nears_neighbor_distance(&swarm_pos, MetricVersion::V2) - 1.0).abs() < 1e-12);
+
+        let farthest = (0..SWARM_SIZE)
+            .map(|i| usize::max(i, SWARM_SIZE - 1 - i) as f64)
+            .sum::<f64>()
+            / SWARM_SIZE as f64;
+        assert!((nears_neighbor_distance(&swarm_pos, MetricVersion::V1) - farthest).abs() < 1e-12);
+    }
+
+    #[test]
+    fn version_names() {
+        for version in [MetricVersion::V1, MetricVersion::V2] {
+            assert_eq!(MetricVersion::parse(version.name()), Some(version));
+        }
+        assert_eq!(MetricVersion::parse("v3"), None);
+    }
+
+    #[test]
+    fn state_metric_of_aligned_and_circling_robots() {
+        let mut swarm_pos = [(0.0, 0.0); SWARM_SIZE];
+        let mut aligned = SwarmState::default();
+        let mut circling = SwarmState::default();
+        for r in 0..SWARM_SIZE {
+            let angle = std::f64::consts::TAU * r as f64 / SWARM_SIZE as f64;
+            swarm_pos[r] = (angle.cos(), angle.sin());
+            aligned[r].heading = Some(0.5);
+            aligned[r].velocity = Some((0.3, 0.4));
+            circling[r].heading = Some(angle + std::f64::consts::FRAC_PI_2);
+        }
+
+        let [polarization, milling, mean_speed] = state_metric(&swarm_pos, &aligned);
+        assert!((polarization.unwrap() - 1.0).abs() < 1e-12);
+        assert!(milling.unwrap() < 1e-12);
+        assert!((mean_speed.unwrap() - 0.5).abs() < 1e-12);
+
+        let [polarization, milling, mean_speed] = state_metric(&swarm_pos, &circling);
+        assert!(polarization.unwrap() < 1e-12);
+        assert!((milling.unwrap() - 1.0).abs() < 1e-12);
+        assert_eq!(mean_speed, None);
+    }
+
+    #[test]
+    fn mean_state_metric_skips_frames_without_states() {
+        let swarm_pos = [[(0.0, 0.0); SWARM_SIZE]; 3];
+        let mut states = [SwarmState::default(); 3];
+        for state in states[0].iter_mut() {
+            state.velocity = Some((0.3, 0.4));
+        }
+        for state in states[1].iter_mut() {
+            state.velocity = Some((0.0, 0.1));
+            state.heading = Some(0.0);
+        }
+
+        let [polarization, _, mean_speed] = mean_state_metric(&swarm_pos, &states);
+        assert!((polarization.unwrap() - 1.0).abs() < 1e-12);
+        assert!((mean_speed.unwrap() - 0.3).abs() < 1e-12);
+        assert_eq!(mean_state_metric(&swarm_pos, &[]), [None; 3]);
+    }
+
+    #[test]
+    fn v1_scores_like_the_original_evaluator() {
+        use crate::normalization::Normalization;
+        use crate::utilities::{get_metics_normalization, get_real_bot_data, Evaluator};
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let experiment_len = 300;
+        let real_pos = get_real_bot_data(experiment_len);
+        let [min, max] = get_metics_normalization();
+        let mut eval = Evaluator::for_tests(std::slice::from_ref(&real_pos), experiment_len);
+        eval.swarm_mode_dist = 0.01;
+        eval.density_radius = 0.01;
+        eval.normalization = Normalization::min_max(min, max, "builtin");
+        eval.real[0].metric =
+            eval.normalization
+                .norm_all(&to_metic(&real_pos, 0.01, 0.01, MetricVersion::V1));
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let sim_pos: Vec<SwarmPos> = real_pos
+            .iter()
+            .map(|frame| {
+                frame.map(|(x, y)| (x + rng.gen_range(-0.1..0.1), y + rng.gen_range(-0.1..0.1)))
+            })
+            .collect();
+
+        // metric_dist of the original evaluator
+        let mut expected = SwarmMetric::default();
+        let sim_metric = to_metic(&sim_pos, 0.01, 0.01, MetricVersion::V1);
+        for (sim, real) in sim_metric.iter().zip(eval.real[0].metric.iter()) {
+            for i in 0..expected.len() {
+                let norm = (sim[i] - min[i]) / (max[i] - min[i]);
+                expected[i] += (norm - real[i]).powi(2);
+            }
+        }
+        for val in expected.iter_mut() {
+            *val = val.sqrt();
+        }
+
+        assert!(expected.iter().all(|val| *val > 0.0));
+        assert_eq!(eval.eval_pos(&sim_pos).metric, expected);
+    }
+}
@@ -0,0 +1,434 @@
+use crate::{distance::quantile, metrics::MetricVersion, SwarmMetric};
+
+/// version of the normalization file format written by `Normalization::save`
+pub const NORMALIZATION_FILE_VERSION: u32 = 1;
+
+/// number of stored quantiles per metric, from 0% to 100% in equal steps
+pub const NUM_QUANTILES: usize = 21;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormMode {
+    /// (x - x_min) / (x_max - x_min)
+    #[default]
+    MinMax,
+    /// (x - mean) / std
+    ZScore,
+    /// x is mapped through the piecewise linear empirical distribution function onto [0, 1]
+    Quantile,
+    /// like `MinMax` but with the 5% and 95% quantiles as bounds
+    Robust,
+}
+
+impl NormMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NormMode::MinMax => "minmax",
+            NormMode::ZScore => "zscore",
+            NormMode::Quantile => "quantile",
+            NormMode::Robust => "robust",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "minmax" => Some(NormMode::MinMax),
+            "zscore" => Some(NormMode::ZScore),
+            "quantile" => Some(NormMode::Quantile),
+            "robust" => Some(NormMode::Robust),
+            _ => None,
+        }
+    }
+}
+
+/// Per-metric statistics used to normalize swarm metrics.
+///
+/// All statistics are stored, so the mode can be switched without refitting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Normalization {
+    pub mode: NormMode,
+    /// where the bounds came from, a file path or `builtin`
+    pub source: String,
+    /// metric definitions and parameters the statistics were computed with, unknown for the builtin bounds
+    pub metric_version: Option<MetricVersion>,
+    pub swarm_mode_dist: Option<f64>,
+    pub density_radius: Option<f64>,
+    pub samples: usize,
+    pub min: SwarmMetric,
+    pub max: SwarmMetric,
+    pub mean: SwarmMetric,
+    pub std: SwarmMetric,
+    pub quantiles: [SwarmMetric; NUM_QUANTILES],
+}
+
+impl Normalization {
+    /// min-max bounds without any further statistics, as in `metics_normalization.csv`
+    ///
+    /// the builtin bounds have `min` above `max`, `MinMax` keeps that reversed mapping of the
+    /// original evaluator while the derived statistics are taken from the lower to the upper bound
+    pub fn min_max(min: SwarmMetric, max: SwarmMetric, source: &str) -> Self {
+        let mut mean = SwarmMetric::default();
+        let mut std = SwarmMetric::default();
+        let mut quantiles = [SwarmMetric::default(); NUM_QUANTILES];
+        for i in 0..mean.len() {
+            let (low, high) = (f64::min(min[i], max[i]), f64::max(min[i], max[i]));
+            mean[i] = (low + high) / 2.0;
+            std[i] = (high - low) / 2.0;
+            for (q, quantile) in quantiles.iter_mut().enumerate() {
+                quantile[i] = low + (high - low) * quantile_level(q);
+            }
+        }
+
+        return Self {
+            mode: NormMode::MinMax,
+            source: source.to_string(),
+            metric_version: None,
+            swarm_mode_dist: None,
+            density_radius: None,
+            samples: 0,
+            min,
+            max,
+            mean,
+            std,
+            quantiles,
+        };
+    }
+
+    /// computes the statistics of raw per-tick metrics
+    pub fn fit(
+        metrics: &[SwarmMetric],
+        mode: NormMode,
+        metric_version: MetricVersion,
+        swarm_mode_dist: f64,
+        density_radius: f64,
+    ) -> Self {
+        assert!(!metrics.is_empty());
+
+        let mut result = Self::min_max(SwarmMetric::default(), SwarmMetric::default(), "");
+        result.mode = mode;
+        result.metric_version = Some(metric_version);
+        result.swarm_mode_dist = Some(swarm_mode_dist);
+        result.density_radius = Some(density_radius);
+        result.samples = metrics.len();
+
+        let len = metrics.len() as f64;
+        for i in 0..result.min.len() {
+            let mut vals = metrics.iter().map(|m| m[i]).collect::<Vec<f64>>();
+            vals.sort_by(f64::total_cmp);
+
+            let mean = vals.iter().sum::<f64>() / len;
+            let variance = vals.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / len;
+
+            result.min[i] = vals[0];
+            result.max[i] = vals[vals.len() - 1];
+            result.mean[i] = mean;
+            result.std[i] = variance.sqrt();
+            for (q, quantile_vals) in result.quantiles.iter_mut().enumerate() {
+                quantile_vals[i] = quantile(&vals, quantile_level(q));
+            }
+        }
+
+        return result;
+    }
+
+    /// a metric that was constant when fitting, e.g. the local density with a tiny radius, is
+    /// normalized to 0 instead of dividing by a zero span
+    pub fn norm(&self, metric: &SwarmMetric) -> SwarmMetric {
+        let mut norm = SwarmMetric::default();
+        for i in 0..norm.len() {
+            norm[i] = match self.mode {
+                NormMode::MinMax => scale(metric[i] - self.min[i], self.max[i] - self.min[i]),
+                NormMode::ZScore => scale(metric[i] - self.mean[i], self.std[i]),
+                NormMode::Quantile => self.cdf(i, metric[i]),
+                NormMode::Robust => {
+                    let low = self.quantiles[1][i];
+                    let high = self.quantiles[NUM_QUANTILES - 2][i];
+                    scale(metric[i] - low, high - low)
+                }
+            };
+        }
+        return norm;
+    }
+
+    pub fn norm_all(&self, metrics: &[SwarmMetric]) -> Vec<SwarmMetric> {
+        return metrics.iter().map(|m| self.norm(m)).collect();
+    }
+
+    /// position of `val` in the stored quantiles of metric `i`, clamped to [0, 1]
+    fn cdf(&self, i: usize, val: f64) -> f64 {
+        if val <= self.quantiles[0][i] {
+            return 0.0;
+        }
+        for q in 1..NUM_QUANTILES {
+            let low = self.quantiles[q - 1][i];
+            let high = self.quantiles[q][i];
+            if val <= high {
+                let frac = if high > low {
+                    (val - low) / (high - low)
+                } else {
+                    1.0
+                };
+                return quantile_level(q - 1) + frac * (quantile_level(q) - quantile_level(q - 1));
+            }
+        }
+        return 1.0;
+    }
+
+    /// short description stored with every evaluation
+    pub fn id(&self) -> String {
+        return format!("{}@{}", self.mode.name(), self.source);
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read normalization file {path}: {e}"))?;
+        let mut result = Self::parse(&content)?;
+        result.source = path.to_string();
+        return Ok(result);
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_file_string())
+            .map_err(|e| format!("failed to write normalization file {path}: {e}"))
+    }
+
+    /// one `key,values` pair per line
+    pub fn to_file_string(&self) -> String {
+        let join = |metric: &SwarmMetric| {
+            metric
+                .iter()
+                .map(|x| format!("{x}"))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let optional = |val: Option<String>| val.unwrap_or("unknown".to_string());
+
+        let mut lines = vec![
+            format!("version, {NORMALIZATION_FILE_VERSION}"),
+            format!("mode, {}", self.mode.name()),
+            format!(
+                "metric_version, {}",
+                optional(self.metric_version.map(|x| x.name().to_string()))
+            ),
+            format!(
+                "swarm_mode_dist, {}",
+                optional(self.swarm_mode_dist.map(|x| format!("{x}")))
+            ),
+            format!(
+                "density_radius, {}",
+                optional(self.density_radius.map(|x| format!("{x}")))
+            ),
+            format!("samples, {}", self.samples),
+            format!("min, {}", join(&self.min)),
+            format!("max, {}", join(&self.max)),
+            format!("mean, {}", join(&self.mean)),
+            format!("std, {}", join(&self.std)),
+        ];
+        for (q, quantile) in self.quantiles.iter().enumerate() {
+            lines.push(format!("q{}, {}", quantile_level(q), join(quantile)));
+        }
+
+        return lines.join("\n") + "\n";
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut result = Self::min_max(SwarmMetric::default(), SwarmMetric::default(), "");
+        let mut num_quantiles = 0;
+        let mut version = None;
+
+        for line in content.split("\n") {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once(",")
+                .ok_or(format!("invalid normalization line: {line}"))?;
+            let value = value.trim();
+            let known = |v: &str| v != "unknown";
+
+            match key.trim() {
+                "version" => version = Some(value.parse::<u32>().map_err(|e| e.to_string())?),
+                "mode" => {
+                    result.mode = NormMode::parse(value)
+                        .ok_or(format!("unknown normalization mode {value}"))?
+                }
+                "metric_version" if known(value) => {
+                    result.metric_version = Some(
+                        MetricVersion::parse(value)
+                            .ok_or(format!("unknown metric version {value}"))?,
+                    )
+                }
+                "swarm_mode_dist" if known(value) => {
+                    result.swarm_mode_dist = Some(value.parse().map_err(|_| value.to_string())?)
+                }
+                "density_radius" if known(value) => {
+                    result.density_radius = Some(value.parse().map_err(|_| value.to_string())?)
+                }
+                "metric_version" | "swarm_mode_dist" | "density_radius" => {}
+                "samples" => result.samples = value.parse().map_err(|_| value.to_string())?,
+                "min" => result.min = parse_metric(value)?,
+                "max" => result.max = parse_metric(value)?,
+                "mean" => result.mean = parse_metric(value)?,
+                "std" => result.std = parse_metric(value)?,
+                key if key.starts_with('q') => {
+                    if num_quantiles >= NUM_QUANTILES {
+                        return Err("too many quantiles".to_string());
+                    }
+                    result.quantiles[num_quantiles] = parse_metric(value)?;
+                    num_quantiles += 1;
+                }
+                key => return Err(format!("unknown normalization key {key}")),
+            }
+        }
+
+        if version != Some(NORMALIZATION_FILE_VERSION) {
+            return Err(format!(
+                "unsupported normalization file version {version:?}, expected {NORMALIZATION_FILE_VERSION}"
+            ));
+        }
+        if num_quantiles != NUM_QUANTILES {
+            return Err(format!(
+                "expected {NUM_QUANTILES} quantiles but got {num_quantiles}"
+            ));
+        }
+
+        return Ok(result);
+    }
+}
+
+/// `val / span`, 0 for an empty span, a negative span like the one of the builtin bounds is kept
+fn scale(val: f64, span: f64) -> f64 {
+    if span.abs() > f64::EPSILON {
+        return val / span;
+    }
+    return 0.0;
+}
+
+fn quantile_level(q: usize) -> f64 {
+    return q as f64 / (NUM_QUANTILES - 1) as f64;
+}
+
+fn parse_metric(value: &str) -> Result<SwarmMetric, String> {
+    let vals = value
+        .split(",")
+        .map(|s| {
+            s.trim()
+                .parse::<f64>()
+                .map_err(|_| format!("invalid value {s}"))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+
+    let mut metric = SwarmMetric::default();
+    if vals.len() != metric.len() {
+        return Err(format!(
+            "expected {} values but got {}",
+            metric.len(),
+            vals.len()
+        ));
+    }
+    metric.copy_from_slice(&vals);
+    return Ok(metric);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::get_metics_normalization;
+
+    /// metric 0 counts up from 0 to 10, all others are constant
+    fn counting() -> Vec<SwarmMetric> {
+        return (0..=10)
+            .map(|x| {
+                let mut metric = [1.5; 9];
+                metric[0] = x as f64;
+                metric
+            })
+            .collect();
+    }
+
+    #[test]
+    fn fit_computes_the_statistics() {
+        let fitted =
+            Normalization::fit(&counting(), NormMode::MinMax, MetricVersion::V2, 0.01, 0.1);
+        assert_eq!(fitted.samples, 11);
+        assert_eq!(
+            (fitted.min[0], fitted.max[0], fitted.mean[0]),
+            (0.0, 10.0, 5.0)
+        );
+        assert!((fitted.std[0] - 10.0f64.sqrt()).abs() < 1e-12);
+        assert_eq!(fitted.quantiles[NUM_QUANTILES / 2][0], 5.0);
+        assert_eq!(fitted.norm(&counting()[4])[0], 0.4);
+    }
+
+    #[test]
+    fn constant_metrics_normalize_to_zero() {
+        for mode in [
+            NormMode::MinMax,
+            NormMode::ZScore,
+            NormMode::Quantile,
+            NormMode::Robust,
+        ] {
+            let fitted = Normalization::fit(&counting(), mode, MetricVersion::V1, 0.01, 0.01);
+            let mut metric = counting()[3];
+            metric[5] = 7.0;
+            let norm = fitted.norm(&metric);
+            assert!(norm.iter().all(|x| x.is_finite()), "{mode:?}: {norm:?}");
+            assert_eq!(norm[1], 0.0, "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let fitted =
+            Normalization::fit(&counting(), NormMode::Robust, MetricVersion::V2, 0.02, 0.3);
+        let path = std::env::temp_dir().join(format!("normalization-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        fitted.save(path).unwrap();
+        let loaded = Normalization::load(path);
+        std::fs::remove_file(path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.source, path);
+        assert_eq!(
+            Normalization {
+                source: String::new(),
+                ..loaded
+            },
+            fitted
+        );
+    }
+
+    #[test]
+    fn builtin_bounds_keep_the_original_mapping() {
+        let [min, max] = get_metics_normalization();
+        let builtin = Normalization::min_max(min, max, "builtin");
+        let metric = [1.0, 5.0, 0.121, 15.0, 20.0, 17.0, 10.0, 18.0, 8.0];
+        let norm = builtin.norm(&metric);
+        for i in 0..norm.len() {
+            // (x - x_min) / (x_max - x_min) as in the original evaluator, bit for bit
+            assert_eq!(norm[i], (metric[i] - min[i]) / (max[i] - min[i]), "{i}");
+            assert!(norm[i] != 0.0, "{i}");
+        }
+        assert_eq!(builtin.norm(&min), [0.0; 9]);
+        assert_eq!(builtin.norm(&max), [1.0; 9]);
+
+        assert!(builtin.std.iter().all(|x| *x > 0.0));
+        for q in 1..NUM_QUANTILES {
+            assert!((0..9).all(|i| builtin.quantiles[q][i] > builtin.quantiles[q - 1][i]));
+        }
+        let zscore = Normalization {
+            mode: NormMode::ZScore,
+            ..builtin.clone()
+        };
+        assert!(zscore.norm(&metric).iter().all(|x| *x != 0.0));
+    }
+
+    #[test]
+    fn files_of_other_versions_are_rejected() {
+        let content = Normalization::min_max([0.0; 9], [1.0; 9], "")
+            .to_file_string()
+            .replace("version, 1", "version, 0");
+        assert!(Normalization::parse(&content).is_err());
+    }
+}
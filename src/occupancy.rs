@@ -0,0 +1,363 @@
+use crate::{fsm::Fsm, json::Json, utilities::std_dev, SwarmState};
+
+/// How the robots of a swarm used the states of their FSM, from the state ids the simulator
+/// reports. Vectors are indexed by the state id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occupancy {
+    /// ticks per entry of `over_time`
+    pub bin: usize,
+    /// share of the robot ticks spent in every state
+    pub fractions: Vec<f64>,
+    /// `fractions` of consecutive bins of `bin` ticks, the last bin may be shorter
+    pub over_time: Vec<Vec<f64>>,
+    /// `transitions[from][to]` state changes of all robots, a mean over runs is not rounded
+    pub transitions: Vec<Vec<f64>>,
+    /// Shannon entropy of `fractions` in bits, the mean over the runs for a mean
+    pub entropy: f64,
+}
+
+impl Occupancy {
+    /// `None` if no robot reported a state, ticks where a robot reported none are skipped
+    pub fn new(states: &[SwarmState], bin: usize) -> Option<Self> {
+        let bin = bin.max(1);
+        let num_states = states.iter().flatten().filter_map(|x| x.state).max()? as usize + 1;
+
+        let mut counts = vec![0.0; num_states];
+        let mut over_time = Vec::new();
+        for chunk in states.chunks(bin) {
+            let mut bin_counts = vec![0.0; num_states];
+            for id in chunk.iter().flatten().filter_map(|x| x.state) {
+                bin_counts[id as usize] += 1.0;
+                counts[id as usize] += 1.0;
+            }
+            over_time.push(normalize(bin_counts));
+        }
+
+        let mut transitions = vec![vec![0.0; num_states]; num_states];
+        for pair in states.windows(2) {
+            for (pre, cur) in pair[0].iter().zip(&pair[1]) {
+                if let (Some(from), Some(to)) = (pre.state, cur.state) {
+                    if from != to {
+                        transitions[from as usize][to as usize] += 1.0;
+                    }
+                }
+            }
+        }
+
+        let fractions = normalize(counts);
+        return Some(Self {
+            bin,
+            entropy: entropy(&fractions),
+            fractions,
+            over_time,
+            transitions,
+        });
+    }
+
+    /// element wise mean of several runs, runs with fewer states count 0 for the others
+    pub fn mean(runs: &[&Occupancy]) -> Option<Self> {
+        let first = runs.first()?;
+        let num_states = runs.iter().map(|x| x.num_states()).max().unwrap();
+        let num_bins = runs.iter().map(|x| x.over_time.len()).max().unwrap();
+        let n = runs.len() as f64;
+
+        let mut fractions = vec![0.0; num_states];
+        let mut transitions = vec![vec![0.0; num_states]; num_states];
+        for run in runs {
+            for (sum, x) in fractions.iter_mut().zip(&run.fractions) {
+                *sum += x / n;
+            }
+            for (row, run_row) in transitions.iter_mut().zip(&run.transitions) {
+                for (sum, x) in row.iter_mut().zip(run_row) {
+                    *sum += x / n;
+                }
+            }
+        }
+        // a bin is averaged over the runs that lasted long enough
+        let over_time = (0..num_bins)
+            .map(|k| {
+                let bins = runs
+                    .iter()
+                    .filter_map(|x| x.over_time.get(k))
+                    .collect::<Vec<_>>();
+                let mut mean = vec![0.0; num_states];
+                for bin in &bins {
+                    for (sum, x) in mean.iter_mut().zip(bin.iter()) {
+                        *sum += x / bins.len() as f64;
+                    }
+                }
+                mean
+            })
+            .collect();
+
+        return Some(Self {
+            bin: first.bin,
+            fractions,
+            over_time,
+            transitions,
+            entropy: runs.iter().map(|x| x.entropy).sum::<f64>() / n,
+        });
+    }
+
+    pub fn num_states(&self) -> usize {
+        return self.fractions.len();
+    }
+
+    /// Jensen-Shannon divergence of the occupancy fractions in bits, 0 for the same usage and 1
+    /// for disjoint states
+    pub fn divergence(&self, other: &Occupancy) -> f64 {
+        let n = self.num_states().max(other.num_states());
+        let p = (0..n)
+            .map(|i| self.fractions.get(i).copied().unwrap_or(0.0))
+            .collect::<Vec<f64>>();
+        let q = (0..n)
+            .map(|i| other.fractions.get(i).copied().unwrap_or(0.0))
+            .collect::<Vec<f64>>();
+        let m = p
+            .iter()
+            .zip(&q)
+            .map(|(a, b)| (a + b) / 2.0)
+            .collect::<Vec<f64>>();
+        let kl = |x: &[f64]| {
+            x.iter()
+                .zip(&m)
+                .filter(|(a, _)| **a > 0.0)
+                .map(|(a, b)| a * (a / b).log2())
+                .sum::<f64>()
+        };
+        return (kl(&p) + kl(&q)) / 2.0;
+    }
+
+    pub fn to_json(&self) -> Json {
+        let rows =
+            |x: &[Vec<f64>]| Json::Array(x.iter().map(|x| Json::from(x.as_slice())).collect());
+        return Json::Object(vec![
+            ("bin".to_string(), Json::from(self.bin as f64)),
+            (
+                "fractions".to_string(),
+                Json::from(self.fractions.as_slice()),
+            ),
+            ("entropy".to_string(), Json::from(self.entropy)),
+            ("over_time".to_string(), rows(&self.over_time)),
+            ("transitions".to_string(), rows(&self.transitions)),
+        ]);
+    }
+}
+
+/// `s<id> <behaviour>` for the states of the controller, `s<id>` if it is no FSM
+pub fn state_labels(controller_cmd: &[String], num_states: usize) -> Vec<String> {
+    let fsm = Fsm::parse(controller_cmd).ok();
+    return (0..num_states)
+        .map(|i| match fsm.as_ref().and_then(|x| x.states.get(i)) {
+            Some(state) => format!("s{i} {}", state.behaviour.name()),
+            None => format!("s{i}"),
+        })
+        .collect();
+}
+
+/// occupancy of every controller, its entropy over the seeds and the pairwise divergences, the
+/// controllers are given as name, command and one occupancy per seed
+pub fn occupancy_table(controllers: &[(String, Vec<String>, Vec<Occupancy>)]) -> String {
+    let mut out = String::new();
+    let mut means = Vec::with_capacity(controllers.len());
+    for (name, controller_cmd, runs) in controllers {
+        let Some(mean) = Occupancy::mean(&runs.iter().collect::<Vec<_>>()) else {
+            out.push_str(&format!("{name}\tno state ids reported\n\n"));
+            means.push(None);
+            continue;
+        };
+        let entropies = runs.iter().map(|x| x.entropy).collect::<Vec<f64>>();
+        let sd = std_dev(&entropies);
+        out.push_str(&format!(
+            "{name}\tentropy {:.4} ± {sd:.4} bits over {} runs\n",
+            mean.entropy,
+            runs.len()
+        ));
+
+        let labels = state_labels(controller_cmd, mean.num_states());
+        out.push_str(&format!("{:<24}\t{:>8}", "state", "share"));
+        for i in 0..mean.num_states() {
+            out.push_str(&format!("\t{:>8}", format!("-> s{i}")));
+        }
+        out.push('\n');
+        for (i, label) in labels.iter().enumerate() {
+            out.push_str(&format!("{label:<24}\t{:>8.4}", mean.fractions[i]));
+            for count in &mean.transitions[i] {
+                out.push_str(&format!("\t{count:>8.1}"));
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+        means.push(Some(mean));
+    }
+
+    out.push_str(&format!("{:<24}", "divergence"));
+    for (name, _, _) in controllers {
+        out.push_str(&format!("\t{name:>8}"));
+    }
+    out.push('\n');
+    for (a, (name, _, _)) in controllers.iter().enumerate() {
+        out.push_str(&format!("{name:<24}"));
+        for b in 0..controllers.len() {
+            match (&means[a], &means[b]) {
+                (Some(a), Some(b)) => out.push_str(&format!("\t{:>8.4}", a.divergence(b))),
+                _ => out.push_str(&format!("\t{:>8}", "-")),
+            }
+        }
+        out.push('\n');
+    }
+    return out;
+}
+
+pub fn occupancy_json(controllers: &[(String, Vec<String>, Vec<Occupancy>)]) -> Json {
+    let means = controllers
+        .iter()
+        .map(|(_, _, runs)| Occupancy::mean(&runs.iter().collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    let entries = controllers
+        .iter()
+        .zip(&means)
+        .map(|((name, controller_cmd, runs), mean)| {
+            let divergences = means
+                .iter()
+                .map(|other| match (mean, other) {
+                    (Some(a), Some(b)) => Json::from(a.divergence(b)),
+                    _ => Json::Null,
+                })
+                .collect();
+            let num_states = mean.as_ref().map(|x| x.num_states()).unwrap_or(0);
+            Json::Object(vec![
+                ("name".to_string(), Json::from(name.as_str())),
+                (
+                    "controller".to_string(),
+                    Json::from(controller_cmd.join(" ")),
+                ),
+                (
+                    "states".to_string(),
+                    Json::Array(
+                        state_labels(controller_cmd, num_states)
+                            .into_iter()
+                            .map(Json::from)
+                            .collect(),
+                    ),
+                ),
+                (
+                    "mean".to_string(),
+                    mean.as_ref().map(|x| x.to_json()).unwrap_or(Json::Null),
+                ),
+                (
+                    "runs".to_string(),
+                    Json::Array(runs.iter().map(|x| x.to_json()).collect()),
+                ),
+                ("divergence".to_string(), Json::Array(divergences)),
+            ])
+        })
+        .collect();
+    return Json::Array(entries);
+}
+
+/// divides by the sum, all zeros stay zeros
+fn normalize(counts: Vec<f64>) -> Vec<f64> {
+    let total = counts.iter().sum::<f64>();
+    if total == 0.0 {
+        return counts;
+    }
+    return counts.into_iter().map(|x| x / total).collect();
+}
+
+fn entropy(fractions: &[f64]) -> f64 {
+    // subtracting from 0 avoids a negative zero for a single state
+    return 0.0
+        - fractions
+            .iter()
+            .filter(|x| **x > 0.0)
+            .map(|x| x * x.log2())
+            .sum::<f64>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fsm::split_cmd, trajectory::RobotState, SWARM_SIZE};
+
+    /// `ids[t][robot]` for the first robots, the others report no state
+    fn states(ids: &[&[Option<u32>]]) -> Vec<SwarmState> {
+        return ids
+            .iter()
+            .map(|tick| {
+                let mut swarm_state = [RobotState::default(); SWARM_SIZE];
+                for (robot, &state) in tick.iter().enumerate() {
+                    swarm_state[robot].state = state;
+                }
+                swarm_state
+            })
+            .collect();
+    }
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        assert_eq!(a.len(), b.len(), "{a:?} != {b:?}");
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() < 1e-9, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn occupancy_of_a_state_sequence() {
+        let states = states(&[
+            &[Some(0), Some(1), Some(2)],
+            &[Some(0), Some(1), None],
+            &[Some(1), Some(1), Some(0)],
+            &[Some(1), Some(0), None],
+        ]);
+        let occupancy = Occupancy::new(&states, 2).unwrap();
+
+        assert_eq!(occupancy.num_states(), 3);
+        assert_close(&occupancy.fractions, &[0.4, 0.5, 0.1]);
+        assert_eq!(occupancy.over_time.len(), 2);
+        assert_close(&occupancy.over_time[0], &[0.4, 0.4, 0.2]);
+        assert_close(&occupancy.over_time[1], &[0.4, 0.6, 0.0]);
+        // the third robot changes from 2 to 0 across a tick without a state, which is no transition
+        assert_eq!(
+            occupancy.transitions,
+            [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 0.0]]
+        );
+        let expected = -(0.4 * 0.4_f64.log2() + 0.5 * 0.5_f64.log2() + 0.1 * 0.1_f64.log2());
+        assert!((occupancy.entropy - expected).abs() < 1e-9);
+
+        // the last bin is shorter
+        assert_eq!(Occupancy::new(&states, 3).unwrap().over_time.len(), 2);
+    }
+
+    #[test]
+    fn no_states_no_occupancy() {
+        assert_eq!(Occupancy::new(&states(&[&[None], &[None]]), 10), None);
+        assert_eq!(Occupancy::new(&[], 10), None);
+
+        let single = Occupancy::new(&states(&[&[Some(0)], &[Some(0)]]), 10).unwrap();
+        assert_eq!(single.entropy.to_bits(), 0.0_f64.to_bits());
+    }
+
+    #[test]
+    fn mean_and_divergence() {
+        let a = Occupancy::new(&states(&[&[Some(0)], &[Some(0)]]), 1).unwrap();
+        let b = Occupancy::new(&states(&[&[Some(1)], &[Some(1)], &[Some(1)]]), 1).unwrap();
+
+        assert_eq!(a.divergence(&a), 0.0);
+        assert!((a.divergence(&b) - 1.0).abs() < 1e-9);
+        assert_eq!(a.divergence(&b), b.divergence(&a));
+
+        let mean = Occupancy::mean(&[&a, &b]).unwrap();
+        assert_close(&mean.fractions, &[0.5, 0.5]);
+        assert_eq!(mean.over_time, [[0.5, 0.5], [0.5, 0.5], [0.0, 1.0]]);
+        assert_eq!(mean.entropy, 0.0);
+        assert_eq!(Occupancy::mean(&[]), None);
+    }
+
+    #[test]
+    fn labels_of_fsm_states() {
+        let cmd =
+            split_cmd("--nstates 2 --s0 0 --rwm0 50 --n0 1 --n0x0 0 --c0x0 5 --p0x0 0.5 --s1 1");
+        assert_eq!(state_labels(&cmd, 3), ["s0 exploration", "s1 stop", "s2"]);
+        assert_eq!(state_labels(&split_cmd("--nroot 3"), 1), ["s0"]);
+    }
+}
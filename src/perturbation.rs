@@ -0,0 +1,304 @@
+use crate::{
+    distance::quantile,
+    json::Json,
+    utilities::{mean, std_dev, Evaluator, RealReference},
+    SwarmPos, SWARM_SIZE,
+};
+use rand::{seq::index::sample, Rng};
+
+/// Imperfection applied to positions before their metrics are computed.
+///
+/// Failed robots can only be frozen, not removed: positions are a fixed size `SwarmPos` of
+/// `SWARM_SIZE` robots and every metric is defined over all of them, so a swarm with fewer
+/// robots has no metrics to compare against the real runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Perturbation {
+    /// gaussian tracking noise with the standard deviation `sigma` on both coordinates
+    Noise { sigma: f64 },
+    /// every robot is lost by the tracking with probability `p` per tick, its last position is held
+    Dropout { p: f64 },
+    /// `robots` randomly chosen robots stop moving from tick `tick` on
+    Freeze { robots: usize, tick: usize },
+}
+
+/// Perturbations of the simulated and of the real positions that are evaluated together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerturbationMode {
+    pub name: String,
+    pub sim: Vec<Perturbation>,
+    pub real: Vec<Perturbation>,
+}
+
+/// Costs of a controller under one perturbation mode, one per seed and repetition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Robustness {
+    pub mode: PerturbationMode,
+    pub costs: Vec<f64>,
+}
+
+impl Perturbation {
+    pub fn name(&self) -> String {
+        match self {
+            Perturbation::Noise { sigma } => format!("noise:{sigma}"),
+            Perturbation::Dropout { p } => format!("dropout:{p}"),
+            Perturbation::Freeze { robots, tick } => format!("freeze:{robots}@{tick}"),
+        }
+    }
+
+    /// parses the format returned by `name`
+    pub fn parse(name: &str) -> Option<Self> {
+        let (kind, args) = name.trim().split_once(":")?;
+        return match kind {
+            "noise" => {
+                let sigma = args.parse::<f64>().ok()?;
+                (sigma >= 0.0).then_some(Perturbation::Noise { sigma })
+            }
+            "dropout" => {
+                let p = args.parse::<f64>().ok()?;
+                (0.0..=1.0)
+                    .contains(&p)
+                    .then_some(Perturbation::Dropout { p })
+            }
+            "freeze" => {
+                let (robots, tick) = args.split_once("@")?;
+                let robots = robots.parse::<usize>().ok()?;
+                let tick = tick.parse::<usize>().ok()?;
+                (robots <= SWARM_SIZE).then_some(Perturbation::Freeze { robots, tick })
+            }
+            _ => None,
+        };
+    }
+
+    pub fn apply(&self, pos: &[SwarmPos], rng: &mut impl Rng) -> Vec<SwarmPos> {
+        let mut result = pos.to_vec();
+        match *self {
+            Perturbation::Noise { sigma } => {
+                for frame in result.iter_mut() {
+                    for (x, y) in frame.iter_mut() {
+                        *x += sigma * gaussian(rng);
+                        *y += sigma * gaussian(rng);
+                    }
+                }
+            }
+            Perturbation::Dropout { p } => {
+                for t in 1..result.len() {
+                    let prev = result[t - 1];
+                    for (robot, prev) in result[t].iter_mut().zip(prev) {
+                        if rng.gen_bool(p) {
+                            *robot = prev;
+                        }
+                    }
+                }
+            }
+            Perturbation::Freeze { robots, tick } => {
+                let Some(&frozen) = result.get(tick) else {
+                    return result;
+                };
+                let chosen = sample(rng, SWARM_SIZE, robots);
+                for frame in result.iter_mut().skip(tick + 1) {
+                    for robot in chosen.iter() {
+                        frame[robot] = frozen[robot];
+                    }
+                }
+            }
+        }
+        return result;
+    }
+}
+
+impl PerturbationMode {
+    /// perturbations joined by `+`, those prefixed with `real.` apply to the real runs, e.g.
+    /// `noise:0.01+real.noise:0.01` or `freeze:3@600`
+    pub fn parse(name: &str) -> Option<Self> {
+        let mut sim = Vec::new();
+        let mut real = Vec::new();
+        for part in name.trim().split("+") {
+            match part.trim().strip_prefix("real.") {
+                Some(part) => real.push(Perturbation::parse(part)?),
+                None => sim.push(Perturbation::parse(part)?),
+            }
+        }
+        return Some(Self {
+            name: name.trim().to_string(),
+            sim,
+            real,
+        });
+    }
+
+    pub fn apply_sim(&self, pos: &[SwarmPos], rng: &mut impl Rng) -> Vec<SwarmPos> {
+        let mut pos = pos.to_vec();
+        for perturbation in &self.sim {
+            pos = perturbation.apply(&pos, rng);
+        }
+        return pos;
+    }
+
+    /// evaluator whose real runs are perturbed and their metrics recomputed, `eval` itself if
+    /// the mode does not touch the real runs
+    pub fn perturb_real(&self, eval: &Evaluator, rng: &mut impl Rng) -> Evaluator {
+        if self.real.is_empty() {
+            return eval.clone();
+        }
+
+        let real = eval
+            .real
+            .iter()
+            .map(|run| {
+                let mut pos = run.pos.clone();
+                for perturbation in &self.real {
+                    pos = perturbation.apply(&pos, rng);
+                }
+                RealReference {
+                    metric: eval.norm_metric(&pos),
+                    pos,
+                    ..run.clone()
+                }
+            })
+            .collect();
+        return Evaluator {
+            real,
+            ..eval.clone()
+        };
+    }
+}
+
+impl Robustness {
+    /// scores already simulated positions `repeats` times under the mode, the real runs are
+    /// perturbed anew for every repetition
+    pub fn new(
+        eval: &Evaluator,
+        mode: PerturbationMode,
+        sim_pos: &[Vec<SwarmPos>],
+        repeats: usize,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let mut costs = Vec::with_capacity(sim_pos.len() * repeats);
+        for _ in 0..repeats {
+            let eval = mode.perturb_real(eval, rng);
+            for pos in sim_pos {
+                let pos = mode.apply_sim(pos, rng);
+                costs.push(eval.cost(&eval.eval_pos(&pos)));
+            }
+        }
+        return Self { mode, costs };
+    }
+
+    pub fn mean(&self) -> f64 {
+        return mean(&self.costs);
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        return std_dev(&self.costs);
+    }
+
+    /// 5 %, 50 % and 95 % quantile of the costs
+    pub fn quantiles(&self) -> [f64; 3] {
+        let mut sorted = self.costs.clone();
+        sorted.sort_by(f64::total_cmp);
+        return [0.05, 0.5, 0.95].map(|q| quantile(&sorted, q));
+    }
+}
+
+/// one row per mode with the cost distribution and the degradation relative to the first,
+/// unperturbed result
+pub fn robustness_table(results: &[Robustness]) -> String {
+    let clean = results[0].mean();
+    let mut out = format!(
+        "{:<32}\t{:>8}\t{:>8}\t{:>8}\t{:>8}\t{:>8}\t{:>10}\t{:>8}\n",
+        "mode", "mean", "std", "q05", "median", "q95", "degrade", "rel"
+    );
+    for result in results {
+        let [q05, median, q95] = result.quantiles();
+        let degradation = result.mean() - clean;
+        // a clean cost of 0 has no relative degradation
+        let relative = if clean > 0.0 {
+            format!("{:+.1}%", 100.0 * degradation / clean)
+        } else {
+            "-".to_string()
+        };
+        out.push_str(&format!(
+            "{:<32}\t{:>8.4}\t{:>8.4}\t{q05:>8.4}\t{median:>8.4}\t{q95:>8.4}\t{degradation:>+10.4}\t{relative:>8}\n",
+            result.mode.name,
+            result.mean(),
+            result.std_dev(),
+        ));
+    }
+    return out;
+}
+
+pub fn robustness_json(results: &[Robustness]) -> Json {
+    let clean = results[0].mean();
+    let modes = results
+        .iter()
+        .map(|result| {
+            let [q05, median, q95] = result.quantiles();
+            Json::Object(vec![
+                ("mode".to_string(), Json::from(result.mode.name.as_str())),
+                ("mean".to_string(), Json::from(result.mean())),
+                ("std".to_string(), Json::from(result.std_dev())),
+                ("q05".to_string(), Json::from(q05)),
+                ("median".to_string(), Json::from(median)),
+                ("q95".to_string(), Json::from(q95)),
+                ("degradation".to_string(), Json::from(result.mean() - clean)),
+                ("costs".to_string(), Json::from(result.costs.as_slice())),
+            ])
+        })
+        .collect();
+    return Json::Array(modes);
+}
+
+/// standard normal sample by the Box-Muller transform
+fn gaussian(rng: &mut impl Rng) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen_range(0.0..1.0);
+    return (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn robustness(name: &str, costs: &[f64]) -> Robustness {
+        return Robustness {
+            mode: PerturbationMode::parse(name).unwrap(),
+            costs: costs.to_vec(),
+        };
+    }
+
+    #[test]
+    fn table_reports_relative_degradation() {
+        let table = robustness_table(&[
+            robustness("noise:0", &[2.0, 2.0]),
+            robustness("noise:0.1", &[3.0, 3.0]),
+        ]);
+        let row = table.lines().nth(2).unwrap();
+        assert!(row.starts_with("noise:0.1"));
+        assert!(row.ends_with("+50.0%"));
+    }
+
+    #[test]
+    fn table_has_no_relative_degradation_for_a_clean_cost_of_zero() {
+        let table = robustness_table(&[
+            robustness("noise:0", &[0.0, 0.0]),
+            robustness("noise:0.1", &[1.0, 1.0]),
+        ]);
+        assert!(!table.contains("inf") && !table.contains("NaN"));
+        assert!(table.lines().nth(2).unwrap().ends_with("-"));
+    }
+
+    #[test]
+    fn freeze_holds_the_chosen_robots() {
+        let pos = (0..5)
+            .map(|t| [(t as f64, 0.0); SWARM_SIZE])
+            .collect::<Vec<SwarmPos>>();
+        let mut rng = rand::thread_rng();
+        let frozen = Perturbation::Freeze { robots: 3, tick: 2 }.apply(&pos, &mut rng);
+        assert_eq!(frozen[..3], pos[..3]);
+        let held = frozen[4].iter().filter(|p| p.0 == 2.0).count();
+        assert_eq!(held, 3);
+        assert_eq!(
+            frozen[4].iter().filter(|p| p.0 == 4.0).count(),
+            SWARM_SIZE - 3
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::ops::Range;
+
+/// A named part of the experiment that is scored on its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase {
+    pub name: String,
+    /// ticks of the metric series, as in the distance curves
+    pub ticks: Range<usize>,
+    pub weight: f64,
+}
+
+/// How the experiment is split into phases.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Phases {
+    /// the whole run is scored at once
+    #[default]
+    Whole,
+    /// windows of `size` ticks every `step` ticks, named `w0`, `w1`, ... and weighted equally
+    Sliding { size: usize, step: usize },
+    /// user defined tick ranges
+    Ranges(Vec<Phase>),
+}
+
+impl Phases {
+    pub fn name(&self) -> String {
+        match self {
+            Phases::Whole => "whole".to_string(),
+            Phases::Sliding { size, step } if size == step => format!("sliding:{size}"),
+            Phases::Sliding { size, step } => format!("sliding:{size}:{step}"),
+            Phases::Ranges(phases) => phases
+                .iter()
+                .map(|x| format!("{}:{}-{}:{}", x.name, x.ticks.start, x.ticks.end, x.weight))
+                .collect::<Vec<String>>()
+                .join(", "),
+        }
+    }
+
+    /// parses the format returned by `name`, e.g. `sliding:300`, `sliding:300:150` or
+    /// `dispersal:0-600:1, aggregation:600-1199:2`, the weight of a range defaults to 1
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if name.is_empty() || name == "whole" {
+            return Some(Phases::Whole);
+        }
+
+        if let Some(args) = name.strip_prefix("sliding:") {
+            let (size, step) = match args.split_once(":") {
+                Some((size, step)) => (size.trim().parse().ok()?, step.trim().parse().ok()?),
+                None => {
+                    let size = args.trim().parse().ok()?;
+                    (size, size)
+                }
+            };
+            if size == 0 || step == 0 {
+                return None;
+            }
+            return Some(Phases::Sliding { size, step });
+        }
+
+        let mut phases = Vec::new();
+        for phase in name.split(",") {
+            let fields = phase.split(":").map(|x| x.trim()).collect::<Vec<&str>>();
+            if fields.len() < 2 || fields.len() > 3 || fields[0].is_empty() {
+                return None;
+            }
+            let (start, end) = fields[1].split_once("-")?;
+            let ticks = start.trim().parse().ok()?..end.trim().parse().ok()?;
+            let weight: f64 = match fields.get(2) {
+                Some(weight) => weight.parse().ok()?,
+                None => 1.0,
+            };
+            if ticks.is_empty() || weight.is_nan() || weight < 0.0 {
+                return None;
+            }
+            phases.push(Phase {
+                name: fields[0].to_string(),
+                ticks,
+                weight,
+            });
+        }
+        return Some(Phases::Ranges(phases));
+    }
+
+    /// the phases of a metric series of `len` ticks, ranges are clipped to it and dropped if
+    /// nothing is left, `Whole` gives no phases
+    pub fn resolve(&self, len: usize) -> Vec<Phase> {
+        return match self {
+            Phases::Whole => Vec::new(),
+            Phases::Sliding { size, step } => {
+                let mut phases = Vec::new();
+                let mut start = 0;
+                while start < len {
+                    phases.push(Phase {
+                        name: format!("w{}", phases.len()),
+                        ticks: start..usize::min(start + size, len),
+                        weight: 1.0,
+                    });
+                    if start + size >= len {
+                        break;
+                    }
+                    start += step;
+                }
+                phases
+            }
+            Phases::Ranges(phases) => phases
+                .iter()
+                .map(|x| Phase {
+                    ticks: x.ticks.start..usize::min(x.ticks.end, len),
+                    ..x.clone()
+                })
+                .filter(|x| !x.ticks.is_empty())
+                .collect(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(name: &str, ticks: Range<usize>, weight: f64) -> Phase {
+        return Phase {
+            name: name.to_string(),
+            ticks,
+            weight,
+        };
+    }
+
+    #[test]
+    fn names_parse_back() {
+        for name in [
+            "whole",
+            "sliding:300",
+            "sliding:300:150",
+            "dispersal:0-600:1, aggregation:600-1199:2.5",
+        ] {
+            assert_eq!(Phases::parse(name).unwrap().name(), name);
+        }
+        assert_eq!(Phases::parse(""), Some(Phases::Whole));
+        assert_eq!(
+            Phases::parse("sliding:300:300"),
+            Some(Phases::Sliding {
+                size: 300,
+                step: 300
+            })
+        );
+        assert_eq!(
+            Phases::parse(" it's:10-20 "),
+            Some(Phases::Ranges(vec![phase("it's", 10..20, 1.0)]))
+        );
+    }
+
+    #[test]
+    fn invalid_phases_are_rejected() {
+        for name in [
+            "sliding:0",
+            "sliding:10:0",
+            "sliding:x",
+            "a:20-10",
+            "a:10-10",
+            "a:0-10:-1",
+            "a:0-10:NaN",
+            ":0-10",
+            "a",
+            "a:0-10:1:2",
+            "a:0-10, b",
+        ] {
+            assert_eq!(Phases::parse(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn sliding_windows_cover_the_run() {
+        let phases = Phases::Sliding { size: 4, step: 3 }.resolve(10);
+        assert_eq!(
+            phases,
+            [
+                phase("w0", 0..4, 1.0),
+                phase("w1", 3..7, 1.0),
+                phase("w2", 6..10, 1.0)
+            ]
+        );
+        // the last window is cut at the end
+        let phases = Phases::Sliding { size: 4, step: 4 }.resolve(10);
+        assert_eq!(phases.last().unwrap().ticks, 8..10);
+        assert!(Phases::Whole.resolve(10).is_empty());
+    }
+
+    #[test]
+    fn ranges_are_clipped_to_the_run() {
+        let phases = Phases::parse("a:0-6:2, b:6-20, c:12-20")
+            .unwrap()
+            .resolve(10);
+        assert_eq!(phases, [phase("a", 0..6, 2.0), phase("b", 6..10, 1.0)]);
+    }
+}
@@ -0,0 +1,642 @@
+use crate::{
+    bt::Node,
+    comparison::{Comparison, Interval},
+    controller::Controller,
+    fsm::{split_cmd, Behaviour, Condition, Fsm},
+    metrics::METRIC_NAMES,
+    utilities::{Evaluator, RealReference},
+    SwarmMetric, SwarmPos,
+};
+
+pub(crate) const COLORS: [&str; 10] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+
+/// One controller of a report with one of its simulations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportController {
+    pub name: String,
+    pub controller_cmd: Vec<String>,
+    /// mean metric distance to the real runs
+    pub metric: SwarmMetric,
+    /// confidence intervals of `metric`, `None` if they are unknown
+    pub intervals: Option<[Interval; 9]>,
+    /// one simulation, aligned like the real runs
+    pub sim_pos: Vec<SwarmPos>,
+    /// normalized per-tick metrics of `sim_pos`
+    pub sim_metric: Vec<SwarmMetric>,
+}
+
+/// Self-contained HTML report with SVG charts of controllers against the real runs.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub title: String,
+    pub controllers: Vec<ReportController>,
+    pub real: Vec<RealReference>,
+}
+
+impl ReportController {
+    /// the simulation of `seed` is taken from the trajectory store if it is kept there and
+    /// simulated otherwise
+    pub fn new(
+        eval: &Evaluator,
+        name: &str,
+        controller_cmd: Vec<String>,
+        metric: SwarmMetric,
+        intervals: Option<[Interval; 9]>,
+        seed: i32,
+    ) -> Result<Self, String> {
+        let stored = eval
+            .trajectory_store
+            .as_ref()
+            .filter(|store| store.contains(&controller_cmd, seed))
+            .map(|store| store.load(&controller_cmd, seed))
+            .transpose()?;
+        let sim_pos = match stored {
+            Some(sim_pos) => sim_pos,
+            None => eval.run_experiment(controller_cmd.clone(), seed),
+        };
+        let sim_pos = eval
+            .align_sim(&sim_pos)
+            .map_err(|e| format!("{name} with seed {seed}: {e}"))?;
+        return Ok(Self {
+            name: name.to_string(),
+            controller_cmd,
+            metric,
+            intervals,
+            sim_metric: eval.norm_metric(&sim_pos),
+            sim_pos,
+        });
+    }
+}
+
+impl Report {
+    /// the controllers of a comparison with their intervals, plotted on the first shared seed
+    pub fn from_comparison(eval: &Evaluator, comparison: &Comparison) -> Result<Self, String> {
+        let controllers = comparison
+            .controllers
+            .iter()
+            .map(|controller| {
+                ReportController::new(
+                    eval,
+                    &controller.name,
+                    controller.controller_cmd.clone(),
+                    controller.metric_intervals.map(|x| x.mean),
+                    Some(controller.metric_intervals),
+                    comparison.seeds[0],
+                )
+            })
+            .collect::<Result<Vec<ReportController>, String>>()?;
+
+        return Ok(Self {
+            title: format!("comparison on {} seeds", comparison.seeds.len()),
+            controllers,
+            real: eval.real.clone(),
+        });
+    }
+
+    /// the `num` controllers with the lowest mean metric distance in the database, among the full
+    /// fidelity evaluations of the active metric version and normalization, plotted on their first seed
+    pub fn from_db(eval: &Evaluator, num: usize) -> Result<Self, String> {
+        let db_con: sqlite::Connection = sqlite::open(&eval.db_path).map_err(|e| e.to_string())?;
+        let query = format!(
+            "SELECT controller_cmd, seeds, metric_norm FROM data WHERE metric_version = '{}' AND normalization = '{}' AND fidelity = 'full';",
+            eval.metric_version.name(),
+            eval.normalization.id()
+        );
+
+        let mut rows: Vec<(String, i32, SwarmMetric)> = Vec::new();
+        let mut error = None;
+        db_con
+            .iterate(query, |pairs| {
+                let value = |i: usize| pairs[i].1.unwrap_or("");
+                let seed = value(1).split(",").next().unwrap_or("").trim().parse();
+                let metric = value(2)
+                    .split(",")
+                    .map(|x| x.trim().parse::<f64>())
+                    .collect::<Result<Vec<f64>, _>>();
+                match (seed, metric.map(|x| SwarmMetric::try_from(x.as_slice()))) {
+                    (Ok(seed), Ok(Ok(metric))) => {
+                        rows.push((value(0).to_string(), seed, metric));
+                        true
+                    }
+                    _ => {
+                        error = Some(format!("invalid evaluation of {}", value(0)));
+                        false
+                    }
+                }
+            })
+            .map_err(|e| e.to_string())?;
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        let mean = |m: &SwarmMetric| m.iter().sum::<f64>() / m.len() as f64;
+        rows.sort_by(|a, b| mean(&a.2).total_cmp(&mean(&b.2)));
+        let mut seen = Vec::new();
+        rows.retain(|row| {
+            if seen.contains(&row.0) {
+                return false;
+            }
+            seen.push(row.0.clone());
+            true
+        });
+        if rows.is_empty() {
+            return Err(format!(
+                "{} has no evaluations of the active configuration",
+                eval.db_path
+            ));
+        }
+
+        let controllers = rows
+            .into_iter()
+            .take(num)
+            .enumerate()
+            .map(|(i, (controller_cmd, seed, metric))| {
+                let name = format!("#{}", i + 1);
+                ReportController::new(eval, &name, split_cmd(&controller_cmd), metric, None, seed)
+            })
+            .collect::<Result<Vec<ReportController>, String>>()?;
+
+        return Ok(Self {
+            title: format!("best {} evaluations of {}", controllers.len(), eval.db_path),
+            controllers,
+            real: eval.real.clone(),
+        });
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        out.push_str(&format!("<title>{}</title>\n", escape(&self.title)));
+        out.push_str("<style>\nbody { font-family: sans-serif; margin: 2em; }\n.grid { display: flex; flex-wrap: wrap; gap: 1em; }\ntable { border-collapse: collapse; }\ntd, th { border: 1px solid #ccc; padding: 0.2em 0.5em; text-align: right; }\ncode { word-break: break-all; font-size: 0.8em; }\n</style>\n</head>\n<body>\n");
+        out.push_str(&format!("<h1>{}</h1>\n", escape(&self.title)));
+
+        out.push_str("<h2>Metric distances</h2>\n");
+        out.push_str(&self.bar_chart());
+        out.push_str(&self.distance_table());
+
+        out.push_str("<h2>Per-tick metrics</h2>\n<div class=\"grid\">\n");
+        for (i, name) in METRIC_NAMES.iter().enumerate() {
+            let mut series = Vec::new();
+            for run in &self.real {
+                let vals = run.metric.iter().map(|m| m[i]).collect::<Vec<f64>>();
+                series.push((format!("real {}", run.name), "#000000", vals));
+            }
+            for (k, controller) in self.controllers.iter().enumerate() {
+                let vals = controller.sim_metric.iter().map(|m| m[i]).collect();
+                series.push((controller.name.clone(), COLORS[k % COLORS.len()], vals));
+            }
+            out.push_str(&line_chart(name, &series));
+        }
+        out.push_str("</div>\n");
+
+        out.push_str("<h2>Trajectories</h2>\n<div class=\"grid\">\n");
+        for run in &self.real {
+            out.push_str(&trajectory_plot(&format!("real {}", run.name), &run.pos));
+        }
+        for controller in &self.controllers {
+            out.push_str(&trajectory_plot(&controller.name, &controller.sim_pos));
+        }
+        out.push_str("</div>\n");
+
+        out.push_str("<h2>Controllers</h2>\n");
+        for controller in &self.controllers {
+            out.push_str(&format!("<h3>{}</h3>\n", escape(&controller.name)));
+            out.push_str(&format!(
+                "<p><code>{}</code></p>\n",
+                escape(&controller.controller_cmd.join(" "))
+            ));
+            match Controller::parse(&controller.controller_cmd) {
+                Ok(Controller::Fsm(fsm)) => out.push_str(&fsm_diagram(&fsm)),
+                Ok(Controller::BehaviourTree(tree)) => out.push_str(&tree_list(&tree.root)),
+                Err(e) => out.push_str(&format!("<p>no diagram: {}</p>\n", escape(&e))),
+            }
+        }
+
+        out.push_str("</body>\n</html>\n");
+        return out;
+    }
+
+    /// grouped bars per metric, one bar per controller with its confidence interval
+    fn bar_chart(&self) -> String {
+        let (width, height) = (960.0, 360.0);
+        let (left, right, top, bottom) = (50.0, 10.0, 30.0, 90.0);
+        let plot_width = width - left - right;
+        let plot_height = height - top - bottom;
+
+        let max = self
+            .controllers
+            .iter()
+            .flat_map(|c| {
+                let highs = c.intervals.map(|x| x.map(|i| i.high)).unwrap_or(c.metric);
+                c.metric.into_iter().chain(highs)
+            })
+            .filter(|x| x.is_finite())
+            .fold(0.0, f64::max);
+        let max = if max > 0.0 { max * 1.1 } else { 1.0 };
+        let y = |val: f64| top + plot_height * (1.0 - val / max);
+
+        let mut svg = svg_open(width, height);
+        svg.push_str(&y_axis(left, width - right, top, plot_height, 0.0, max));
+
+        let group_width = plot_width / METRIC_NAMES.len() as f64;
+        let bar_width = group_width * 0.8 / self.controllers.len().max(1) as f64;
+        for (i, name) in METRIC_NAMES.iter().enumerate() {
+            let group = left + group_width * i as f64;
+            for (k, controller) in self.controllers.iter().enumerate() {
+                let x = group + group_width * 0.1 + bar_width * k as f64;
+                let val = controller.metric[i].min(max);
+                svg.push_str(&format!(
+                    "<rect x=\"{x:.1}\" y=\"{:.1}\" width=\"{bar_width:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{} {name}: {:.4}</title></rect>\n",
+                    y(val),
+                    y(0.0) - y(val),
+                    COLORS[k % COLORS.len()],
+                    escape(&controller.name),
+                    controller.metric[i]
+                ));
+                if let Some(intervals) = controller.intervals {
+                    let center = x + bar_width / 2.0;
+                    svg.push_str(&format!(
+                        "<line x1=\"{center:.1}\" y1=\"{:.1}\" x2=\"{center:.1}\" y2=\"{:.1}\" stroke=\"#000\"/>\n",
+                        y(intervals[i].low.min(max)),
+                        y(intervals[i].high.min(max))
+                    ));
+                }
+            }
+            let x = group + group_width / 2.0;
+            svg.push_str(&format!(
+                "<text x=\"{x:.1}\" y=\"{:.1}\" font-size=\"11\" text-anchor=\"end\" transform=\"rotate(-35 {x:.1} {:.1})\">{name}</text>\n",
+                y(0.0) + 14.0,
+                y(0.0) + 14.0
+            ));
+        }
+
+        let names = self
+            .controllers
+            .iter()
+            .enumerate()
+            .map(|(k, c)| (c.name.clone(), COLORS[k % COLORS.len()]))
+            .collect::<Vec<(String, &str)>>();
+        svg.push_str(&legend(left, 4.0, &names));
+        svg.push_str("</svg>\n");
+        return svg;
+    }
+
+    fn distance_table(&self) -> String {
+        let mut out = "<table>\n<tr><th>metric</th>".to_string();
+        for controller in &self.controllers {
+            out.push_str(&format!("<th>{}</th>", escape(&controller.name)));
+        }
+        out.push_str("</tr>\n");
+        for (i, name) in METRIC_NAMES.iter().enumerate() {
+            out.push_str(&format!("<tr><td>{name}</td>"));
+            for controller in &self.controllers {
+                let cell = match controller.intervals {
+                    Some(x) => format!("{:.4} [{:.4}, {:.4}]", x[i].mean, x[i].low, x[i].high),
+                    None => format!("{:.4}", controller.metric[i]),
+                };
+                out.push_str(&format!("<td>{cell}</td>"));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+        return out;
+    }
+}
+
+fn svg_open(width: f64, height: f64) -> String {
+    return format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n"
+    );
+}
+
+/// horizontal grid lines with labels from `min` to `max`
+fn y_axis(left: f64, right: f64, top: f64, plot_height: f64, min: f64, max: f64) -> String {
+    let mut out = String::new();
+    for i in 0..=4 {
+        let val = min + (max - min) * i as f64 / 4.0;
+        let y = top + plot_height * (1.0 - i as f64 / 4.0);
+        out.push_str(&format!(
+            "<line x1=\"{left}\" y1=\"{y:.1}\" x2=\"{right}\" y2=\"{y:.1}\" stroke=\"#ddd\"/>\n<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\" text-anchor=\"end\">{}</text>\n",
+            left - 4.0,
+            y + 3.0,
+            format_tick(val)
+        ));
+    }
+    return out;
+}
+
+fn legend(x: f64, y: f64, names: &[(String, &str)]) -> String {
+    let mut out = String::new();
+    let mut x = x;
+    for (name, color) in names {
+        out.push_str(&format!(
+            "<rect x=\"{x:.1}\" y=\"{y:.1}\" width=\"10\" height=\"10\" fill=\"{color}\"/>\n<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"11\">{}</text>\n",
+            x + 14.0,
+            y + 9.0,
+            escape(name)
+        ));
+        x += 24.0 + 7.0 * name.chars().count() as f64;
+    }
+    return out;
+}
+
+/// one line per series over the ticks
+fn line_chart(title: &str, series: &[(String, &str, Vec<f64>)]) -> String {
+    let (width, height) = (300.0, 200.0);
+    let (left, right, top, bottom) = (45.0, 10.0, 20.0, 20.0);
+    let plot_width = width - left - right;
+    let plot_height = height - top - bottom;
+
+    let vals = series
+        .iter()
+        .flat_map(|x| x.2.iter().copied())
+        .filter(|x| x.is_finite());
+    let (min, max) = vals.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), x| {
+        (min.min(x), max.max(x))
+    });
+    let (min, max) = match (min.is_finite(), max > min) {
+        (false, _) => (0.0, 1.0),
+        (true, false) => (min - 0.5, max + 0.5),
+        (true, true) => (min, max),
+    };
+    let len = series.iter().map(|x| x.2.len()).max().unwrap_or(0);
+
+    let mut svg = svg_open(width, height);
+    svg.push_str(&format!(
+        "<text x=\"{:.1}\" y=\"14\" font-size=\"12\" text-anchor=\"middle\">{}</text>\n",
+        width / 2.0,
+        escape(title)
+    ));
+    svg.push_str(&y_axis(left, width - right, top, plot_height, min, max));
+    for (name, color, vals) in series {
+        let points = vals
+            .iter()
+            .enumerate()
+            .filter(|(_, val)| val.is_finite())
+            .map(|(t, val)| {
+                let x = left + plot_width * t as f64 / (len.max(2) - 1) as f64;
+                let y = top + plot_height * (1.0 - (val - min) / (max - min));
+                format!("{x:.1},{y:.1}")
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        svg.push_str(&format!(
+            "<polyline points=\"{points}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"1\"><title>{}</title></polyline>\n",
+            escape(name)
+        ));
+    }
+    svg.push_str(&format!(
+        "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\" text-anchor=\"end\">tick {}</text>\n",
+        width - right,
+        height - 4.0,
+        len.saturating_sub(1)
+    ));
+    svg.push_str("</svg>\n");
+    return svg;
+}
+
+/// the path of every robot with equal scales on both axes, the start is marked with a dot
+fn trajectory_plot(title: &str, pos: &[SwarmPos]) -> String {
+    let size = 300.0;
+    let (margin, top) = (10.0, 20.0);
+    let plot_size = size - 2.0 * margin - top;
+
+    let points = pos
+        .iter()
+        .flatten()
+        .filter(|(x, y)| x.is_finite() && y.is_finite());
+    let (min_x, max_x, min_y, max_y) = points.fold(
+        (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(min_x, max_x, min_y, max_y), &(x, y)| {
+            (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))
+        },
+    );
+    let extent = f64::max(max_x - min_x, max_y - min_y);
+    let extent = if extent > 0.0 { extent } else { 1.0 };
+    let to_svg = |(x, y): (f64, f64)| {
+        (
+            margin + plot_size * (x - min_x) / extent,
+            top + margin + plot_size * (1.0 - (y - min_y) / extent),
+        )
+    };
+
+    let mut svg = svg_open(size, size);
+    svg.push_str(&format!(
+        "<text x=\"{:.1}\" y=\"14\" font-size=\"12\" text-anchor=\"middle\">{}</text>\n",
+        size / 2.0,
+        escape(title)
+    ));
+    let num_robots = pos.first().map(|x| x.len()).unwrap_or(0);
+    for robot in 0..num_robots {
+        let color = COLORS[robot % COLORS.len()];
+        let path = pos
+            .iter()
+            .map(|frame| frame[robot])
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .map(to_svg)
+            .collect::<Vec<(f64, f64)>>();
+        let Some(&(x, y)) = path.first() else {
+            continue;
+        };
+        let points = path
+            .iter()
+            .map(|(x, y)| format!("{x:.1},{y:.1}"))
+            .collect::<Vec<String>>()
+            .join(" ");
+        svg.push_str(&format!(
+            "<polyline points=\"{points}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"0.8\"/>\n<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"2.5\" fill=\"{color}\"/>\n"
+        ));
+    }
+    svg.push_str("</svg>\n");
+    return svg;
+}
+
+/// states on a circle with their behaviour, transitions as labelled arrows
+fn fsm_diagram(fsm: &Fsm) -> String {
+    let size = 360.0;
+    let (center, radius, node) = (size / 2.0, 120.0, 34.0);
+    let num = fsm.states.len();
+    let node_pos = |k: usize| {
+        if num == 1 {
+            return (center, center);
+        }
+        let angle = std::f64::consts::TAU * k as f64 / num as f64 - std::f64::consts::FRAC_PI_2;
+        (center + radius * angle.cos(), center + radius * angle.sin())
+    };
+
+    let mut svg = svg_open(size, size);
+    svg.push_str("<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\"><path d=\"M 0 0 L 10 5 L 0 10 z\"/></marker></defs>\n");
+
+    for (k, state) in fsm.states.iter().enumerate() {
+        for (j, transition) in state.transitions.iter().enumerate() {
+            let ((x1, y1), (x2, y2)) = (node_pos(k), node_pos(transition.to));
+            let len = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt().max(1.0);
+            let (dx, dy) = ((x2 - x1) / len, (y2 - y1) / len);
+            // both directions between two states are drawn side by side
+            let (nx, ny) = (-dy * 6.0, dx * 6.0);
+            let (sx, sy) = (x1 + dx * node + nx, y1 + dy * node + ny);
+            let (ex, ey) = (x2 - dx * node + nx, y2 - dy * node + ny);
+            svg.push_str(&format!(
+                "<line x1=\"{sx:.1}\" y1=\"{sy:.1}\" x2=\"{ex:.1}\" y2=\"{ey:.1}\" stroke=\"#444\" marker-end=\"url(#arrow)\"/>\n"
+            ));
+            let (lx, ly) = (
+                (sx + ex) / 2.0 + nx * 1.5,
+                (sy + ey) / 2.0 + ny * 1.5 + 11.0 * j as f64,
+            );
+            svg.push_str(&format!(
+                "<text x=\"{lx:.1}\" y=\"{ly:.1}\" font-size=\"9\" text-anchor=\"middle\" fill=\"#444\">{}</text>\n",
+                condition_label(&transition.condition)
+            ));
+        }
+    }
+
+    for (k, state) in fsm.states.iter().enumerate() {
+        let (x, y) = node_pos(k);
+        let (name, param) = behaviour_label(&state.behaviour);
+        svg.push_str(&format!(
+            "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"{node}\" fill=\"#eef\" stroke=\"#336\"/>\n<text x=\"{x:.1}\" y=\"{:.1}\" font-size=\"10\" text-anchor=\"middle\">{k}: {name}</text>\n<text x=\"{x:.1}\" y=\"{:.1}\" font-size=\"9\" text-anchor=\"middle\">{param}</text>\n",
+            y - 2.0,
+            y + 10.0
+        ));
+    }
+    svg.push_str("</svg>\n");
+    return svg;
+}
+
+/// nested list of the nodes, leaves labelled like the FSM diagram
+fn tree_list(node: &Node) -> String {
+    let label = match node {
+        Node::Selector(_) => "selector".to_string(),
+        Node::Sequence(_) => "sequence".to_string(),
+        Node::SequenceStar(_) => "sequence*".to_string(),
+        Node::Condition(condition) => condition_label(condition),
+        Node::Action(behaviour) => {
+            let (name, param) = behaviour_label(behaviour);
+            format!("{name} {param}").trim().to_string()
+        }
+    };
+    let mut out = format!("<ul><li>{label}");
+    for child in node.children() {
+        out.push_str(&tree_list(child));
+    }
+    out.push_str("</li></ul>\n");
+    return out;
+}
+
+fn behaviour_label(behaviour: &Behaviour) -> (&'static str, String) {
+    return match behaviour {
+        Behaviour::Exploration { rwm } => ("exploration", format!("rwm {rwm}")),
+        Behaviour::Stop => ("stop", String::new()),
+        Behaviour::Phototaxis => ("phototaxis", String::new()),
+        Behaviour::AntiPhototaxis => ("anti-phototaxis", String::new()),
+        Behaviour::Attraction { att } => ("attraction", format!("att {att}")),
+        Behaviour::Repulsion { rep } => ("repulsion", format!("rep {rep}")),
+    };
+}
+
+fn condition_label(condition: &Condition) -> String {
+    return match condition {
+        Condition::BlackFloor { p } => format!("black p={p}"),
+        Condition::GrayFloor { p } => format!("gray p={p}"),
+        Condition::WhiteFloor { p } => format!("white p={p}"),
+        Condition::NeighborsCount { p, w } => format!("neighbors p={p} w={w}"),
+        Condition::InvertedNeighborsCount { p, w } => format!("inv. neighbors p={p} w={w}"),
+        Condition::FixedProbability { p } => format!("fixed p={p}"),
+    };
+}
+
+fn format_tick(val: f64) -> String {
+    if val != 0.0 && (val.abs() >= 1000.0 || val.abs() < 0.01) {
+        return format!("{val:.1e}");
+    }
+    return format!("{val:.2}");
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alignment::AlignWindow, SWARM_SIZE};
+
+    fn report_controller(name: &str, cmd: &str) -> ReportController {
+        let sim_pos = (0..5)
+            .map(|t| [(0.1 * t as f64, -0.1 * t as f64); SWARM_SIZE])
+            .collect();
+        return ReportController {
+            name: name.to_string(),
+            controller_cmd: split_cmd(cmd),
+            metric: [0.2; 9],
+            intervals: None,
+            sim_pos,
+            sim_metric: vec![[0.5; 9]; 4],
+        };
+    }
+
+    #[test]
+    fn html_has_every_section() {
+        let report = Report {
+            title: "a <b> & c".to_string(),
+            controllers: vec![
+                report_controller("explore", "--nstates 1 --s0 0 --rwm0 50"),
+                report_controller(
+                    "tree",
+                    "--nroot 3 --nchildroot 1 --n0 0 --nchild0 2 --n00 6 --c00 5 --p00 0.5 --n01 5 --a01 1",
+                ),
+                report_controller("broken", "--nstates 2"),
+            ],
+            real: vec![RealReference {
+                name: "r1".to_string(),
+                window: AlignWindow {
+                    start: 0,
+                    len: 5,
+                    total: 5,
+                },
+                pos: vec![[(0.0, 0.0); SWARM_SIZE]; 5],
+                metric: vec![[0.4; 9]; 4],
+            }],
+        };
+        let html = report.to_html();
+
+        assert!(html.contains("<title>a &lt;b&gt; &amp; c</title>"));
+        // bar chart, a line chart per metric, a trajectory plot per run and the FSM diagram
+        assert_eq!(html.matches("<svg").count(), 1 + 9 + 4 + 1);
+        assert_eq!(html.matches("<polyline").count(), 9 * 4 + 4 * SWARM_SIZE);
+        assert!(html.contains("0: exploration"));
+        assert!(html.contains("<ul><li>sequence*<ul><li>selector"));
+        assert!(html.contains("<li>fixed p=0.5</li>"));
+        assert!(html.contains("<li>stop</li>"));
+        assert!(html.contains("<p>no diagram: "));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn ticks_are_short() {
+        assert_eq!(format_tick(0.0), "0.00");
+        assert_eq!(format_tick(0.5), "0.50");
+        assert_eq!(format_tick(0.001), "1.0e-3");
+        assert_eq!(format_tick(12345.0), "1.2e4");
+    }
+
+    #[test]
+    fn escaped_text() {
+        assert_eq!(
+            escape("\"a\" < b && c > d"),
+            "&quot;a&quot; &lt; b &amp;&amp; c &gt; d"
+        );
+    }
+}
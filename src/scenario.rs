@@ -0,0 +1,356 @@
+use crate::{animation::Arena, xml::XmlElement};
+
+/// element names of the robots ARGoS ships with
+pub const ROBOT_ENTITIES: [&str; 8] = [
+    "e-puck",
+    "foot-bot",
+    "kheperaiv",
+    "kilobot",
+    "pipuck",
+    "pi-puck",
+    "eye-bot",
+    "spiri",
+];
+
+/// Box of the arena that is not distributed randomly, usually a wall.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wall {
+    pub id: String,
+    /// center of the base
+    pub position: (f64, f64, f64),
+    pub size: (f64, f64, f64),
+    /// rotation around the vertical axis in degrees
+    pub yaw: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Light {
+    pub id: String,
+    pub position: (f64, f64, f64),
+    pub color: String,
+    pub intensity: f64,
+}
+
+/// The parts of an ARGoS `.argos` file the evaluator depends on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    /// seconds, 0 if the experiment runs until it is stopped
+    pub length: f64,
+    pub ticks_per_second: f64,
+    /// `None` or 0 lets ARGoS pick a seed, AutoMoDe overrides it with `--seed`
+    pub random_seed: Option<u64>,
+    /// robots placed directly and by `distribute`
+    pub robot_count: usize,
+    pub arena_size: (f64, f64, f64),
+    pub arena_center: (f64, f64, f64),
+    pub walls: Vec<Wall>,
+    pub lights: Vec<Light>,
+}
+
+impl Scenario {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        return Self::parse(&content).map_err(|e| format!("{path}: {e}"));
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let root = XmlElement::parse(content)?;
+        if root.name != "argos-configuration" {
+            return Err(format!(
+                "root element is {} and not argos-configuration",
+                root.name
+            ));
+        }
+
+        let experiment = root
+            .child("framework")
+            .and_then(|x| x.child("experiment"))
+            .ok_or("missing framework/experiment")?;
+        let length = number(experiment, "length")?;
+        let ticks_per_second = number(experiment, "ticks_per_second")?;
+        let random_seed = match experiment.attribute("random_seed") {
+            Some(seed) => Some(
+                seed.trim()
+                    .parse::<u64>()
+                    .map_err(|_| format!("invalid random_seed {seed}"))?,
+            ),
+            None => None,
+        };
+
+        let arena = root.child("arena").ok_or("missing arena")?;
+        let arena_size = vector(arena, "size")?;
+        let arena_center = match arena.attribute("center") {
+            Some(_) => vector(arena, "center")?,
+            None => (0.0, 0.0, 0.0),
+        };
+
+        let mut robot_count = 0;
+        let mut walls = Vec::new();
+        for element in &arena.children {
+            match element.name.as_str() {
+                "distribute" => {
+                    let entity = element.child("entity").ok_or("distribute without entity")?;
+                    if entity
+                        .children
+                        .iter()
+                        .any(|x| ROBOT_ENTITIES.contains(&x.name.as_str()))
+                    {
+                        robot_count += number(entity, "quantity")? as usize;
+                    }
+                }
+                "box" => {
+                    let body = element
+                        .child("body")
+                        .ok_or(format!("box {} without body", id(element)))?;
+                    let orientation = match body.attribute("orientation") {
+                        Some(_) => vector(body, "orientation")?,
+                        None => (0.0, 0.0, 0.0),
+                    };
+                    walls.push(Wall {
+                        id: id(element),
+                        position: vector(body, "position")?,
+                        size: vector(element, "size")?,
+                        // ARGoS orders the euler angles as z, y, x
+                        yaw: orientation.0,
+                    });
+                }
+                name if ROBOT_ENTITIES.contains(&name) => robot_count += 1,
+                _ => {}
+            }
+        }
+
+        let lights = arena
+            .descendants()
+            .into_iter()
+            .filter(|x| x.name == "light")
+            .map(|light| {
+                Ok(Light {
+                    id: id(light),
+                    position: vector(light, "position")?,
+                    color: light.attribute("color").unwrap_or("").to_string(),
+                    intensity: match light.attribute("intensity") {
+                        Some(_) => number(light, "intensity")?,
+                        None => 1.0,
+                    },
+                })
+            })
+            .collect::<Result<Vec<Light>, String>>()?;
+
+        return Ok(Self {
+            length,
+            ticks_per_second,
+            random_seed,
+            robot_count,
+            arena_size,
+            arena_center,
+            walls,
+            lights,
+        });
+    }
+
+    /// ticks the simulator runs, `None` if the experiment has no length
+    pub fn experiment_len(&self) -> Option<usize> {
+        if self.length <= 0.0 {
+            return None;
+        }
+        return Some((self.length * self.ticks_per_second).round() as usize);
+    }
+
+    /// the floor enclosed by the walls, the whole arena if there are none
+    pub fn bounds(&self) -> Arena {
+        if self.walls.is_empty() {
+            let (x, y, _) = self.arena_center;
+            let (width, height, _) = self.arena_size;
+            return Arena {
+                min: (x - width / 2.0, y - height / 2.0),
+                max: (x + width / 2.0, y + height / 2.0),
+            };
+        }
+
+        let mut min = (f64::INFINITY, f64::INFINITY);
+        let mut max = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for wall in &self.walls {
+            let (sin, cos) = wall.yaw.to_radians().sin_cos();
+            for (dx, dy) in [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)] {
+                let (x, y) = (dx * wall.size.0 / 2.0, dy * wall.size.1 / 2.0);
+                let corner = (
+                    wall.position.0 + x * cos - y * sin,
+                    wall.position.1 + x * sin + y * cos,
+                );
+                min = (min.0.min(corner.0), min.1.min(corner.1));
+                max = (max.0.max(corner.0), max.1.max(corner.1));
+            }
+        }
+        return Arena { min, max };
+    }
+
+    /// errors if the scenario simulates another number of robots, at another rate or for fewer
+    /// ticks than the evaluator expects
+    pub fn validate(
+        &self,
+        experiment_len: usize,
+        sim_tick_rate: f64,
+        swarm_size: usize,
+    ) -> Result<(), String> {
+        if self.robot_count != swarm_size {
+            return Err(format!(
+                "the scenario places {} robots but the evaluator expects {swarm_size}",
+                self.robot_count
+            ));
+        }
+        if self.ticks_per_second != sim_tick_rate {
+            return Err(format!(
+                "the scenario runs at {} ticks/s but SIM_TICK_RATE is {sim_tick_rate}",
+                self.ticks_per_second
+            ));
+        }
+        if let Some(len) = self.experiment_len().filter(|&len| len < experiment_len) {
+            return Err(format!(
+                "the scenario stops after {len} ticks but EXPERIMENT_LEN is {experiment_len}"
+            ));
+        }
+        return Ok(());
+    }
+}
+
+fn id(element: &XmlElement) -> String {
+    return element.attribute("id").unwrap_or("").to_string();
+}
+
+fn number(element: &XmlElement, name: &str) -> Result<f64, String> {
+    let val = element
+        .attribute(name)
+        .ok_or(format!("{} without {name}", element.name))?;
+    return val
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("invalid {name} {val} of {}", element.name));
+}
+
+/// an attribute like `position="1, 0.5, 0"`
+pub(crate) fn vector(element: &XmlElement, name: &str) -> Result<(f64, f64, f64), String> {
+    let val = element
+        .attribute(name)
+        .ok_or(format!("{} without {name}", element.name))?;
+    let vals = val
+        .split(",")
+        .map(|x| x.trim().parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>()
+        .map_err(|_| format!("invalid {name} {val} of {}", element.name))?;
+    return match vals.as_slice() {
+        [x, y, z] => Ok((*x, *y, *z)),
+        _ => Err(format!("{name} {val} of {} is not 3 values", element.name)),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARGOS: &str = r#"<?xml version="1.0" ?>
+<argos-configuration>
+  <framework>
+    <experiment length="120" ticks_per_second="10" random_seed="0"/>
+  </framework>
+  <arena size="3, 3, 1" center="0, 0, 0.5">
+    <box id="wall_north" size="2, 0.1, 0.2" movable="false">
+      <body position="0, 1, 0" orientation="0, 0, 0"/>
+    </box>
+    <box id="wall_east" size="2, 0.1, 0.2" movable="false">
+      <body position="1, 0, 0" orientation="90, 0, 0"/>
+    </box>
+    <light id="light0" position="0, 1.2, 0.4" orientation="0, 0, 0" color="yellow"/>
+    <e-puck id="first"><controller config="automode"/></e-puck>
+    <distribute>
+      <position method="uniform" min="-1, -1, 0" max="1, 1, 0"/>
+      <orientation method="gaussian" mean="0, 0, 0" std_dev="360, 0, 0"/>
+      <entity quantity="14" max_trials="100">
+        <e-puck id="epuck"><controller config="automode"/></e-puck>
+      </entity>
+    </distribute>
+    <distribute>
+      <position method="uniform" min="-1, -1, 0" max="1, 1, 0"/>
+      <orientation method="constant" values="0, 0, 0"/>
+      <entity quantity="5" max_trials="100">
+        <cylinder id="obstacle" height="0.1" radius="0.05" movable="false"/>
+      </entity>
+    </distribute>
+  </arena>
+</argos-configuration>
+"#;
+
+    #[test]
+    fn scenario_parse() {
+        let scenario = Scenario::parse(ARGOS).unwrap();
+        assert_eq!(scenario.length, 120.0);
+        assert_eq!(scenario.ticks_per_second, 10.0);
+        assert_eq!(scenario.random_seed, Some(0));
+        assert_eq!(scenario.experiment_len(), Some(1200));
+        assert_eq!(scenario.robot_count, 15);
+        assert_eq!(scenario.arena_size, (3.0, 3.0, 1.0));
+        assert_eq!(scenario.arena_center, (0.0, 0.0, 0.5));
+        assert_eq!(scenario.walls.len(), 2);
+        assert_eq!(scenario.walls[1].id, "wall_east");
+        assert_eq!(scenario.walls[1].yaw, 90.0);
+        assert_eq!(
+            scenario.lights,
+            [Light {
+                id: "light0".to_string(),
+                position: (0.0, 1.2, 0.4),
+                color: "yellow".to_string(),
+                intensity: 1.0,
+            }]
+        );
+
+        assert!(scenario.validate(1200, 10.0, 15).is_ok());
+        assert!(scenario.validate(1201, 10.0, 15).is_err());
+        assert!(scenario.validate(1200, 5.0, 15).is_err());
+        assert!(scenario.validate(1200, 10.0, 20).is_err());
+    }
+
+    #[test]
+    fn bounds_of_rotated_walls() {
+        let bounds = Scenario::parse(ARGOS).unwrap().bounds();
+        let expected = [(-1.0, -1.0), (1.05, 1.05)];
+        for (a, b) in [bounds.min, bounds.max].iter().zip(expected) {
+            assert!(
+                (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9,
+                "{bounds:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn bounds_without_walls_are_the_arena() {
+        let content = r#"<argos-configuration>
+  <framework><experiment length="0" ticks_per_second="10"/></framework>
+  <arena size="4, 2, 1"/>
+</argos-configuration>"#;
+        let scenario = Scenario::parse(content).unwrap();
+        assert_eq!(scenario.experiment_len(), None);
+        assert_eq!(scenario.random_seed, None);
+        assert_eq!(scenario.robot_count, 0);
+        assert_eq!(
+            scenario.bounds(),
+            Arena {
+                min: (-2.0, -1.0),
+                max: (2.0, 1.0)
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_scenarios() {
+        assert!(Scenario::parse("<argos/>").is_err());
+        assert!(Scenario::parse(
+            "<argos-configuration><arena size=\"1,1,1\"/></argos-configuration>"
+        )
+        .is_err());
+        let content = r#"<argos-configuration>
+  <framework><experiment length="10" ticks_per_second="10"/></framework>
+  <arena size="1, 1"/>
+</argos-configuration>"#;
+        let err = Scenario::parse(content).unwrap_err();
+        assert!(err.contains("not 3 values"), "{err}");
+    }
+}
@@ -0,0 +1,293 @@
+use crate::{utilities::fnv1a, SwarmPos, SwarmState, SWARM_SIZE};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// first bytes of every stored trajectory, the last byte is the format version
+const MAGIC: &[u8; 4] = b"AMT\x02";
+/// magic of the first version, which has no robot states
+const MAGIC_V1: &[u8; 4] = b"AMT\x01";
+
+/// bits of the field mask of version 2
+const HEADING: u32 = 1;
+const VELOCITY: u32 = 2;
+const STATE: u32 = 4;
+
+/// Directory of gzip compressed simulated trajectories, one file per controller and seed.
+///
+/// A file holds the magic, the robot count, the frame count and a mask of the stored robot
+/// states as little endian u32 followed by `x, y` of every robot of every frame as little endian
+/// f64. Then come, if the mask has them, the headings and the velocities `vx, vy` as f64, NaN if
+/// a robot did not report one, and the FSM states as u32, `u32::MAX` if missing. Files of the
+/// first version have no mask and no states.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrajectoryStore {
+    pub dir: PathBuf,
+}
+
+impl TrajectoryStore {
+    pub fn new(dir: &str) -> Result<Self, String> {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{dir}: {e}"))?;
+        return Ok(Self {
+            dir: PathBuf::from(dir),
+        });
+    }
+
+    /// file of a controller and seed, the controller string is hashed
+    pub fn path(&self, controller_cmd: &[String], seed: i32) -> PathBuf {
+        let key = fnv1a(controller_cmd.join(" ").as_bytes());
+        return self.dir.join(format!("{key:016x}_{seed}.traj.gz"));
+    }
+
+    pub fn contains(&self, controller_cmd: &[String], seed: i32) -> bool {
+        return self.path(controller_cmd, seed).is_file();
+    }
+
+    /// `states` is empty or has one entry per frame
+    pub fn save(
+        &self,
+        controller_cmd: &[String],
+        seed: i32,
+        bot_pos: &[SwarmPos],
+        states: &[SwarmState],
+    ) -> Result<PathBuf, String> {
+        let path = self.path(controller_cmd, seed);
+        write_trajectory_file(&path, bot_pos, states)?;
+        return Ok(path);
+    }
+
+    pub fn load(&self, controller_cmd: &[String], seed: i32) -> Result<Vec<SwarmPos>, String> {
+        return Ok(self.load_states(controller_cmd, seed)?.0);
+    }
+
+    /// positions and robot states, the states are empty if none were stored
+    pub fn load_states(
+        &self,
+        controller_cmd: &[String],
+        seed: i32,
+    ) -> Result<(Vec<SwarmPos>, Vec<SwarmState>), String> {
+        return read_trajectory_file(&self.path(controller_cmd, seed));
+    }
+}
+
+pub fn write_trajectory_file(
+    path: &Path,
+    bot_pos: &[SwarmPos],
+    states: &[SwarmState],
+) -> Result<(), String> {
+    assert!(states.is_empty() || states.len() == bot_pos.len());
+    let robot_states = states.iter().flatten();
+    let mut mask = 0;
+    if robot_states.clone().any(|x| x.heading.is_some()) {
+        mask |= HEADING;
+    }
+    if robot_states.clone().any(|x| x.velocity.is_some()) {
+        mask |= VELOCITY;
+    }
+    if robot_states.clone().any(|x| x.state.is_some()) {
+        mask |= STATE;
+    }
+
+    let mut data = Vec::with_capacity(16 + bot_pos.len() * SWARM_SIZE * 16);
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&(SWARM_SIZE as u32).to_le_bytes());
+    data.extend_from_slice(&(bot_pos.len() as u32).to_le_bytes());
+    data.extend_from_slice(&mask.to_le_bytes());
+    for frame in bot_pos {
+        for (x, y) in frame {
+            data.extend_from_slice(&x.to_le_bytes());
+            data.extend_from_slice(&y.to_le_bytes());
+        }
+    }
+    if mask & HEADING != 0 {
+        for state in robot_states.clone() {
+            data.extend_from_slice(&state.heading.unwrap_or(f64::NAN).to_le_bytes());
+        }
+    }
+    if mask & VELOCITY != 0 {
+        for state in robot_states.clone() {
+            let (vx, vy) = state.velocity.unwrap_or((f64::NAN, f64::NAN));
+            data.extend_from_slice(&vx.to_le_bytes());
+            data.extend_from_slice(&vy.to_le_bytes());
+        }
+    }
+    if mask & STATE != 0 {
+        for state in robot_states {
+            data.extend_from_slice(&state.state.unwrap_or(u32::MAX).to_le_bytes());
+        }
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(&data)
+        .and_then(|_| encoder.finish())
+        .and_then(|compressed| std::fs::write(path, compressed))
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    return Ok(());
+}
+
+pub fn read_trajectory_file(path: &Path) -> Result<(Vec<SwarmPos>, Vec<SwarmState>), String> {
+    let compressed = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut data = Vec::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut data)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+
+    let v1 = data.len() >= 12 && &data[0..4] == MAGIC_V1;
+    if !v1 && (data.len() < 16 || &data[0..4] != MAGIC) {
+        return Err(format!("{} is not a stored trajectory", path.display()));
+    }
+    let read_u32 = |i: usize| u32::from_le_bytes(data[i..i + 4].try_into().unwrap()) as usize;
+    let robot_count = read_u32(4);
+    let len = read_u32(8);
+    let (mask, header) = if v1 {
+        (0, 12)
+    } else {
+        (read_u32(12) as u32, 16)
+    };
+    if robot_count != SWARM_SIZE {
+        return Err(format!(
+            "{} has {robot_count} robots but the evaluator expects {SWARM_SIZE}",
+            path.display()
+        ));
+    }
+    let robots = len * SWARM_SIZE;
+    let mut size = header + robots * 16;
+    for (bit, bytes) in [(HEADING, 8), (VELOCITY, 16), (STATE, 4)] {
+        if mask & bit != 0 {
+            size += robots * bytes;
+        }
+    }
+    if data.len() != size {
+        return Err(format!("{} is truncated", path.display()));
+    }
+
+    let read_f64 = |i: usize| f64::from_le_bytes(data[i..i + 8].try_into().unwrap());
+    let optional = |x: f64| Some(x).filter(|x| !x.is_nan());
+    let mut bot_pos = Vec::with_capacity(len);
+    for t in 0..len {
+        let mut frame = SwarmPos::default();
+        for (r, pos) in frame.iter_mut().enumerate() {
+            let i = header + (t * SWARM_SIZE + r) * 16;
+            *pos = (read_f64(i), read_f64(i + 8));
+        }
+        bot_pos.push(frame);
+    }
+    if mask == 0 {
+        return Ok((bot_pos, Vec::new()));
+    }
+
+    let mut states = vec![SwarmState::default(); len];
+    let mut offset = header + robots * 16;
+    if mask & HEADING != 0 {
+        for (k, state) in states.iter_mut().flatten().enumerate() {
+            state.heading = optional(read_f64(offset + k * 8));
+        }
+        offset += robots * 8;
+    }
+    if mask & VELOCITY != 0 {
+        for (k, state) in states.iter_mut().flatten().enumerate() {
+            let i = offset + k * 16;
+            state.velocity = optional(read_f64(i)).zip(optional(read_f64(i + 8)));
+        }
+        offset += robots * 16;
+    }
+    if mask & STATE != 0 {
+        for (k, state) in states.iter_mut().flatten().enumerate() {
+            state.state = Some(read_u32(offset + k * 4) as u32).filter(|&x| x != u32::MAX);
+        }
+    }
+    return Ok((bot_pos, states));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectory::RobotState;
+
+    fn store(name: &str) -> TrajectoryStore {
+        let dir = std::env::temp_dir().join(format!("store-{name}-{}", std::process::id()));
+        return TrajectoryStore::new(&dir.to_string_lossy()).unwrap();
+    }
+
+    fn frames(len: usize) -> Vec<SwarmPos> {
+        return (0..len)
+            .map(|t| {
+                let mut frame = SwarmPos::default();
+                for (r, pos) in frame.iter_mut().enumerate() {
+                    *pos = (t as f64 * 0.1 - r as f64, r as f64 / 3.0);
+                }
+                frame
+            })
+            .collect();
+    }
+
+    #[test]
+    fn positions_round_trip() {
+        let store = store("positions");
+        let cmd = vec!["--nstates".to_string(), "1".to_string()];
+        let pos = frames(4);
+        store.save(&cmd, 7, &pos, &[]).unwrap();
+
+        assert!(store.contains(&cmd, 7));
+        assert!(!store.contains(&cmd, 8));
+        assert_eq!(store.load_states(&cmd, 7).unwrap(), (pos, Vec::new()));
+        std::fs::remove_dir_all(&store.dir).unwrap();
+    }
+
+    #[test]
+    fn states_round_trip() {
+        let store = store("states");
+        let cmd = vec!["--nstates".to_string(), "2".to_string()];
+        let pos = frames(3);
+        let mut states = vec![SwarmState::default(); 3];
+        for (k, state) in states.iter_mut().flatten().enumerate() {
+            // every field is missing for some robots
+            *state = RobotState {
+                heading: (k % 2 == 0).then_some(k as f64 * 0.25),
+                velocity: (k % 3 != 0).then_some((k as f64, -0.5)),
+                state: (k % 5 != 0).then_some(k as u32 % 4),
+            };
+        }
+        store.save(&cmd, -3, &pos, &states).unwrap();
+        assert_eq!(store.load_states(&cmd, -3).unwrap(), (pos.clone(), states));
+
+        // only the fields some robot has are stored
+        let mut headings = vec![SwarmState::default(); 3];
+        headings[1][4].heading = Some(1.5);
+        store.save(&cmd, -3, &pos, &headings).unwrap();
+        assert_eq!(store.load_states(&cmd, -3).unwrap(), (pos, headings));
+        std::fs::remove_dir_all(&store.dir).unwrap();
+    }
+
+    #[test]
+    fn first_version_and_broken_files_are_read() {
+        let store = store("versions");
+        let cmd = vec!["--nstates".to_string(), "3".to_string()];
+        let pos = frames(2);
+        let mut data = Vec::new();
+        data.extend_from_slice(MAGIC_V1);
+        data.extend_from_slice(&(SWARM_SIZE as u32).to_le_bytes());
+        data.extend_from_slice(&(pos.len() as u32).to_le_bytes());
+        for (x, y) in pos.iter().flatten() {
+            data.extend_from_slice(&x.to_le_bytes());
+            data.extend_from_slice(&y.to_le_bytes());
+        }
+        let write = |data: &[u8]| {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).unwrap();
+            std::fs::write(store.path(&cmd, 1), encoder.finish().unwrap()).unwrap();
+        };
+
+        write(&data);
+        assert_eq!(store.load(&cmd, 1).unwrap(), pos);
+
+        write(&data[..data.len() - 8]);
+        assert!(store.load(&cmd, 1).unwrap_err().ends_with("is truncated"));
+        write(b"nothing");
+        assert!(store.load(&cmd, 1).is_err());
+        std::fs::remove_dir_all(&store.dir).unwrap();
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{
+    alignment::Alignment,
+    distance::DistanceMeasure,
+    metrics::swarm_metic,
+    utilities::{Evaluator, RealReference},
+    SwarmMetric, SwarmPos, SwarmState,
+};
+
+/// Output of a simulation read while it was running.
+#[derive(Debug, Clone)]
+pub struct StreamedRun {
+    pub frames: Vec<SwarmPos>,
+    /// empty if the simulator printed only positions, otherwise one per frame
+    pub states: Vec<SwarmState>,
+    /// see `StreamingDist::per_tick`
+    pub per_tick: Vec<SwarmMetric>,
+    /// killed because its partial cost exceeded the bound, `frames` are incomplete
+    pub terminated: bool,
+}
+
+/// Metric distances of a simulation computed frame by frame while it is still running.
+///
+/// Simulated tick `t` is compared with tick `t` of every real window, which is what the tick
+/// aligned distance does once the simulation is complete, provided it runs `experiment_len` ticks.
+pub struct StreamingDist<'a> {
+    eval: &'a Evaluator,
+    origin: Option<SwarmPos>,
+    pre: Option<SwarmPos>,
+    /// squared differences summed so far, per real run
+    sums: Vec<SwarmMetric>,
+    /// absolute difference of the normalized metrics at every tick, aggregated over the real runs
+    pub per_tick: Vec<SwarmMetric>,
+}
+
+impl<'a> StreamingDist<'a> {
+    pub fn new(eval: &'a Evaluator) -> Self {
+        return Self {
+            eval,
+            origin: None,
+            pre: None,
+            sums: vec![SwarmMetric::default(); eval.real.len()],
+            per_tick: Vec::with_capacity(eval.experiment_len),
+        };
+    }
+
+    /// partial distances only bound the final ones if every metric uses the tick aligned distance,
+    /// the cost is not a weighted mean of phases and the scored window starts at the first frame,
+    /// other alignments crop a longer simulation at its front once its length is known
+    pub fn is_bounding(&self) -> bool {
+        let from_start = matches!(
+            self.eval.alignment,
+            Alignment::Start | Alignment::Offset { start: 0 }
+        ) || self.eval.tick_limit == Some(self.eval.experiment_len);
+        return from_start
+            && self.eval.phases.is_empty()
+            && self
+                .eval
+                .distance_measures
+                .iter()
+                .all(|x| *x == DistanceMeasure::TickAligned);
+    }
+
+    /// adds the next simulated frame, frames beyond the real windows are ignored
+    pub fn push(&mut self, swarm_pos: &SwarmPos) {
+        let (Some(origin), Some(pre)) = (self.origin, self.pre) else {
+            self.origin = Some(*swarm_pos);
+            self.pre = Some(*swarm_pos);
+            return;
+        };
+        self.pre = Some(*swarm_pos);
+
+        let t = self.per_tick.len();
+        if self.eval.real.iter().any(|real| t >= real.metric.len()) {
+            return;
+        }
+
+        let metric = swarm_metic(
+            swarm_pos,
+            &origin,
+            &pre,
+            self.eval.swarm_mode_dist,
+            self.eval.density_radius,
+            self.eval.metric_version,
+        );
+        let metric = self.eval.normalization.norm(&metric);
+
+        let mut diffs = Vec::with_capacity(self.eval.real.len());
+        for (real, sum) in self.eval.real.iter().zip(self.sums.iter_mut()) {
+            let diff = tick_diff(&metric, real, t);
+            for i in 0..sum.len() {
+                sum[i] += diff[i].powi(2);
+            }
+            diffs.push(diff);
+        }
+        self.per_tick
+            .push(self.eval.real_aggregation.aggregate(&diffs));
+    }
+
+    /// tick aligned distance of the ticks seen so far, it only grows with further ticks
+    pub fn partial_dist(&self) -> SwarmMetric {
+        let dists = self
+            .sums
+            .iter()
+            .map(|sum| sum.map(f64::sqrt))
+            .collect::<Vec<SwarmMetric>>();
+        return self.eval.real_aggregation.aggregate(&dists);
+    }
+
+    /// mean of `partial_dist`, a lower bound of the final cost of this simulation
+    pub fn partial_cost(&self) -> f64 {
+        let dist = self.partial_dist();
+        return dist.iter().sum::<f64>() / dist.len() as f64;
+    }
+}
+
+fn tick_diff(metric: &SwarmMetric, real: &RealReference, t: usize) -> SwarmMetric {
+    let mut diff = SwarmMetric::default();
+    for (i, val) in diff.iter_mut().enumerate() {
+        *val = (metric[i] - real.metric[t][i]).abs();
+    }
+    return diff;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{phases::Phase, SWARM_SIZE};
+
+    /// robots spreading from a line at `speed` per tick
+    fn spreading(len: usize, speed: f64) -> Vec<SwarmPos> {
+        return (0..len)
+            .map(|t| {
+                let mut frame = [(0.0, 0.0); SWARM_SIZE];
+                for (i, pos) in frame.iter_mut().enumerate() {
+                    let angle = i as f64 / SWARM_SIZE as f64 * std::f64::consts::TAU;
+                    let r = 0.1 + speed * t as f64;
+                    *pos = (r * angle.cos(), r * angle.sin());
+                }
+                frame
+            })
+            .collect();
+    }
+
+    #[test]
+    fn streamed_distance_equals_tick_aligned() {
+        let mut eval = Evaluator::for_tests(&[spreading(20, 0.01), spreading(20, 0.03)], 20);
+        eval.alignment = Alignment::Start;
+        let sim = spreading(20, 0.02);
+
+        let mut dist = StreamingDist::new(&eval);
+        assert!(dist.is_bounding());
+        let mut cost = 0.0;
+        for frame in &sim {
+            dist.push(frame);
+            assert!(dist.partial_cost() >= cost);
+            cost = dist.partial_cost();
+        }
+        assert_eq!(dist.per_tick.len(), 19);
+
+        let expected = eval.eval_pos(&sim).metric;
+        for (a, b) in dist.partial_dist().iter().zip(expected) {
+            assert!((a - b).abs() < 1e-9, "{a} != {b}");
+        }
+        assert!(cost > 0.0);
+    }
+
+    #[test]
+    fn frames_beyond_the_real_runs_are_ignored() {
+        let eval = Evaluator::for_tests(&[spreading(10, 0.01)], 10);
+        let mut dist = StreamingDist::new(&eval);
+        for frame in &spreading(15, 0.02) {
+            dist.push(frame);
+        }
+        assert_eq!(dist.per_tick.len(), 9);
+    }
+
+    #[test]
+    fn longer_simulations_are_not_bounded_under_center() {
+        let real = spreading(10, 0.01);
+        let mut eval = Evaluator::for_tests(&[real], 10);
+        assert_eq!(eval.alignment, Alignment::Center);
+        // the center crop drops the two still frames in front, leaving the real run itself
+        let sim = [vec![spreading(1, 0.0)[0]; 2], spreading(12, 0.01)].concat();
+        assert_eq!(eval.eval_pos(&sim).metric, [0.0; 9]);
+
+        let mut dist = StreamingDist::new(&eval);
+        for frame in &sim {
+            dist.push(frame);
+        }
+        assert!(dist.partial_cost() > 0.0);
+        assert!(!dist.is_bounding());
+
+        // stopped after the experiment, nothing is cropped
+        eval.tick_limit = Some(eval.experiment_len);
+        assert!(StreamingDist::new(&eval).is_bounding());
+        eval.tick_limit = None;
+        eval.alignment = Alignment::Offset { start: 0 };
+        assert!(StreamingDist::new(&eval).is_bounding());
+        eval.alignment = Alignment::End;
+        assert!(!StreamingDist::new(&eval).is_bounding());
+    }
+
+    #[test]
+    fn only_tick_aligned_whole_runs_are_bounding() {
+        let mut eval = Evaluator::for_tests(&[spreading(10, 0.01)], 10);
+        eval.alignment = Alignment::Start;
+        assert!(StreamingDist::new(&eval).is_bounding());
+        eval.distance_measures[3] = DistanceMeasure::Summary;
+        assert!(!StreamingDist::new(&eval).is_bounding());
+
+        let mut eval = Evaluator::for_tests(&[spreading(10, 0.01)], 10);
+        eval.alignment = Alignment::Start;
+        eval.phases = vec![Phase {
+            name: "early".to_string(),
+            ticks: 0..5,
+            weight: 1.0,
+        }];
+        assert!(!StreamingDist::new(&eval).is_bounding());
+    }
+}
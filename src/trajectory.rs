@@ -0,0 +1,409 @@
+use crate::{SwarmPos, SwarmState};
+
+/// Positions of the whole swarm with the time in seconds of every frame.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Trajectory {
+    pub times: Vec<f64>,
+    pub frames: Vec<SwarmPos>,
+    /// empty if the source reports only positions, otherwise one per frame
+    pub states: Vec<SwarmState>,
+}
+
+/// What a robot reports besides its position, `None` for what the source does not provide.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RobotState {
+    /// radians, counterclockwise from the x axis
+    pub heading: Option<f64>,
+    /// m/s in x and y
+    pub velocity: Option<(f64, f64)>,
+    /// index of the FSM state the robot is in
+    pub state: Option<u32>,
+}
+
+impl RobotState {
+    pub fn is_empty(&self) -> bool {
+        return self.heading.is_none() && self.velocity.is_none() && self.state.is_none();
+    }
+}
+
+/// How a trajectory is put onto another time grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Resampling {
+    /// frames are used as they are, one frame per simulator tick is assumed
+    #[default]
+    None,
+    /// linear interpolation between the neighbouring frames
+    Linear,
+    /// cubic Hermite spline with Catmull-Rom tangents, which handles uneven frame times
+    Spline,
+    /// mean of all frames within half a grid step of the grid time, linear interpolation if there are none
+    Average,
+}
+
+impl Resampling {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Resampling::None => "none",
+            Resampling::Linear => "linear",
+            Resampling::Spline => "spline",
+            Resampling::Average => "average",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "none" => Some(Resampling::None),
+            "linear" => Some(Resampling::Linear),
+            "spline" => Some(Resampling::Spline),
+            "average" => Some(Resampling::Average),
+            _ => None,
+        }
+    }
+}
+
+impl Trajectory {
+    /// frames with timestamps, they are sorted by time with a warning if the recording jittered
+    /// backwards
+    pub fn new(times: Vec<f64>, frames: Vec<SwarmPos>) -> Self {
+        return Self::with_states(times, frames, Vec::new());
+    }
+
+    /// like `new`, `states` is empty or has one entry per frame
+    pub fn with_states(times: Vec<f64>, frames: Vec<SwarmPos>, states: Vec<SwarmState>) -> Self {
+        assert_eq!(times.len(), frames.len());
+        assert!(states.is_empty() || states.len() == frames.len());
+
+        let backwards = times.windows(2).filter(|x| x[1] < x[0]).count();
+        if backwards > 0 {
+            eprintln!("{backwards} frames are earlier than the frame before them, the frames are sorted by time");
+        }
+        let mut order = (0..times.len()).collect::<Vec<usize>>();
+        order.sort_by(|&a, &b| times[a].total_cmp(&times[b]));
+        return Self {
+            times: order.iter().map(|&i| times[i]).collect(),
+            frames: order.iter().map(|&i| frames[i]).collect(),
+            states: order
+                .iter()
+                .filter_map(|&i| states.get(i).copied())
+                .collect(),
+        };
+    }
+
+    /// evenly spaced frames starting at time 0
+    pub fn from_tick_rate(frames: Vec<SwarmPos>, tick_rate: f64) -> Self {
+        let times = time_grid(0.0, tick_rate, frames.len());
+        return Self {
+            times,
+            frames,
+            states: Vec::new(),
+        };
+    }
+
+    pub fn has_states(&self) -> bool {
+        return !self.states.is_empty();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.frames.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.frames.is_empty();
+    }
+
+    pub fn duration(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        return self.times[self.len() - 1] - self.times[0];
+    }
+
+    /// all frames shifted so the first one is at time 0
+    pub fn from_zero(&self) -> Self {
+        let start = self.times.first().copied().unwrap_or(0.0);
+        return Self {
+            times: self.times.iter().map(|t| t - start).collect(),
+            frames: self.frames.clone(),
+            states: self.states.clone(),
+        };
+    }
+
+    /// puts the trajectory onto the grid of `tick_rate` ticks per second, starting at its first frame
+    pub fn resample_to_rate(&self, tick_rate: f64, resampling: Resampling) -> Result<Self, String> {
+        if self.is_empty() {
+            return Err("trajectory has no frames".to_string());
+        }
+        let trajectory = self.from_zero();
+        if resampling == Resampling::None {
+            return Ok(trajectory);
+        }
+
+        let len = (trajectory.duration() * tick_rate + 1e-9).floor() as usize + 1;
+        return trajectory.resample(&time_grid(0.0, tick_rate, len), resampling);
+    }
+
+    /// fails for a trajectory without frames, there is nothing to resample
+    pub fn resample(&self, grid: &[f64], resampling: Resampling) -> Result<Self, String> {
+        if self.is_empty() {
+            return Err("trajectory has no frames".to_string());
+        }
+
+        let mut frames = Vec::with_capacity(grid.len());
+        for (k, &t) in grid.iter().enumerate() {
+            let frame = match resampling {
+                Resampling::None => self.frames[usize::min(k, self.len() - 1)],
+                Resampling::Linear => self.linear(t),
+                Resampling::Spline => self.spline(t),
+                Resampling::Average => {
+                    let step = if grid.len() > 1 {
+                        grid[1] - grid[0]
+                    } else {
+                        f64::INFINITY
+                    };
+                    self.average(t, step / 2.0).unwrap_or(self.linear(t))
+                }
+            };
+            frames.push(frame);
+        }
+
+        // states are not interpolated, every grid time takes those of the closest frame
+        let states = match self.has_states() {
+            true => grid
+                .iter()
+                .enumerate()
+                .map(|(k, &t)| match resampling {
+                    Resampling::None => self.states[usize::min(k, self.len() - 1)],
+                    _ => self.states[self.closest(t)],
+                })
+                .collect(),
+            false => Vec::new(),
+        };
+
+        return Ok(Self {
+            times: grid.to_vec(),
+            frames,
+            states,
+        });
+    }
+
+    fn closest(&self, t: f64) -> usize {
+        let i = self.times.partition_point(|&x| x <= t);
+        if i == 0 {
+            return 0;
+        }
+        if i == self.len() || t - self.times[i - 1] <= self.times[i] - t {
+            return i - 1;
+        }
+        return i;
+    }
+
+    /// index i of the segment with times[i] <= t < times[i + 1], clamped to the valid segments
+    fn segment(&self, t: f64) -> usize {
+        let i = self.times.partition_point(|&x| x <= t);
+        return i.saturating_sub(1).min(self.len().saturating_sub(2));
+    }
+
+    fn linear(&self, t: f64) -> SwarmPos {
+        if self.len() == 1 {
+            return self.frames[0];
+        }
+
+        let i = self.segment(t);
+        let (t0, t1) = (self.times[i], self.times[i + 1]);
+        let frac = if t1 > t0 {
+            ((t - t0) / (t1 - t0)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let mut frame = SwarmPos::default();
+        for (r, pos) in frame.iter_mut().enumerate() {
+            let (p0, p1) = (self.frames[i][r], self.frames[i + 1][r]);
+            *pos = (p0.0 + (p1.0 - p0.0) * frac, p0.1 + (p1.1 - p0.1) * frac);
+        }
+        return frame;
+    }
+
+    fn spline(&self, t: f64) -> SwarmPos {
+        if self.len() < 3 {
+            return self.linear(t);
+        }
+
+        let i = self.segment(t);
+        let (t0, t1) = (self.times[i], self.times[i + 1]);
+        if t1 <= t0 || t <= t0 || t >= t1 {
+            return self.linear(t);
+        }
+        let h = t1 - t0;
+        let s = (t - t0) / h;
+
+        // hermite basis
+        let h00 = 2.0 * s.powi(3) - 3.0 * s.powi(2) + 1.0;
+        let h10 = s.powi(3) - 2.0 * s.powi(2) + s;
+        let h01 = -2.0 * s.powi(3) + 3.0 * s.powi(2);
+        let h11 = s.powi(3) - s.powi(2);
+
+        let mut frame = SwarmPos::default();
+        for (r, pos) in frame.iter_mut().enumerate() {
+            let m0 = self.tangent(i, r);
+            let m1 = self.tangent(i + 1, r);
+            let (p0, p1) = (self.frames[i][r], self.frames[i + 1][r]);
+            *pos = (
+                h00 * p0.0 + h10 * h * m0.0 + h01 * p1.0 + h11 * h * m1.0,
+                h00 * p0.1 + h10 * h * m0.1 + h01 * p1.1 + h11 * h * m1.1,
+            );
+        }
+        return frame;
+    }
+
+    /// Catmull-Rom tangent of robot `r` at frame `i`, one sided at the ends
+    fn tangent(&self, i: usize, r: usize) -> (f64, f64) {
+        let pre = i.saturating_sub(1);
+        let next = usize::min(i + 1, self.len() - 1);
+        let dt = self.times[next] - self.times[pre];
+        if dt <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let (p0, p1) = (self.frames[pre][r], self.frames[next][r]);
+        return ((p1.0 - p0.0) / dt, (p1.1 - p0.1) / dt);
+    }
+
+    fn average(&self, t: f64, half_width: f64) -> Option<SwarmPos> {
+        let start = self.times.partition_point(|&x| x < t - half_width);
+        let end = self.times.partition_point(|&x| x < t + half_width);
+        if start >= end {
+            return None;
+        }
+
+        let mut frame = SwarmPos::default();
+        let count = (end - start) as f64;
+        for other in &self.frames[start..end] {
+            for (pos, other_pos) in frame.iter_mut().zip(other.iter()) {
+                pos.0 += other_pos.0 / count;
+                pos.1 += other_pos.1 / count;
+            }
+        }
+        return Some(frame);
+    }
+}
+
+/// `len` evenly spaced times starting at `start`
+pub fn time_grid(start: f64, tick_rate: f64, len: usize) -> Vec<f64> {
+    return (0..len).map(|k| start + k as f64 / tick_rate).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SWARM_SIZE;
+
+    /// every robot moves with its own constant velocity
+    fn constant_velocity(times: &[f64]) -> Trajectory {
+        let frames = times
+            .iter()
+            .map(|&t| {
+                let mut frame = SwarmPos::default();
+                for (r, pos) in frame.iter_mut().enumerate() {
+                    *pos = (r as f64 + 0.3 * t, -0.1 * r as f64 * t);
+                }
+                frame
+            })
+            .collect();
+        return Trajectory::new(times.to_vec(), frames);
+    }
+
+    fn assert_close(a: &[SwarmPos], b: &[SwarmPos]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().flatten().zip(b.iter().flatten()) {
+            assert!(
+                (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9,
+                "{a:?} {b:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn frames_are_sorted_by_time() {
+        let mut states = vec![SwarmState::default(); 3];
+        states[0][0].state = Some(2);
+        let trajectory = Trajectory::with_states(
+            vec![0.2, 0.0, 0.1],
+            vec![
+                [(2.0, 0.0); SWARM_SIZE],
+                [(0.0, 0.0); SWARM_SIZE],
+                [(1.0, 0.0); SWARM_SIZE],
+            ],
+            states,
+        );
+        assert_eq!(trajectory.times, [0.0, 0.1, 0.2]);
+        assert_eq!(trajectory.frames[2][0], (2.0, 0.0));
+        assert_eq!(trajectory.states[2][0].state, Some(2));
+    }
+
+    #[test]
+    fn linear_and_spline_resample_constant_velocity_exactly() {
+        // uneven frame times, as recorded by a jittering tracker
+        let times = [0.3, 0.32, 0.41, 0.55, 0.6, 0.74, 0.83, 0.9, 1.05, 1.1, 1.31];
+        let trajectory = constant_velocity(&times);
+        for resampling in [Resampling::Linear, Resampling::Spline] {
+            let resampled = trajectory.resample_to_rate(10.0, resampling).unwrap();
+            assert_eq!(resampled.len(), 11);
+            let expected = constant_velocity(&time_grid(0.3, 10.0, 11));
+            assert_close(&resampled.frames, &expected.frames);
+            assert!((resampled.times[10] - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn average_takes_the_mean_around_the_grid_time() {
+        // three frames per grid step, centred on it, except at both ends where only the frames
+        // within the trajectory are averaged
+        let trajectory = constant_velocity(&time_grid(0.0, 30.0, 31));
+        let resampled = trajectory
+            .resample_to_rate(10.0, Resampling::Average)
+            .unwrap();
+        let expected = constant_velocity(&time_grid(0.0, 10.0, 11));
+        assert_close(&resampled.frames[1..10], &expected.frames[1..10]);
+
+        // without a frame close to the grid time it interpolates
+        let trajectory = Trajectory::new(
+            vec![0.0, 1.0],
+            vec![[(0.0, 0.0); SWARM_SIZE], [(1.0, 2.0); SWARM_SIZE]],
+        );
+        let resampled = trajectory
+            .resample(&[0.0, 0.5, 1.0], Resampling::Average)
+            .unwrap();
+        assert_eq!(resampled.frames[1], [(0.5, 1.0); SWARM_SIZE]);
+    }
+
+    #[test]
+    fn resampled_states_are_those_of_the_closest_frame() {
+        let mut trajectory = constant_velocity(&[0.0, 0.1, 0.2]);
+        trajectory.states = vec![SwarmState::default(); 3];
+        for (i, states) in trajectory.states.iter_mut().enumerate() {
+            states[0].state = Some(i as u32);
+        }
+        let resampled = trajectory
+            .resample(&[0.0, 0.04, 0.06, 0.2], Resampling::Linear)
+            .unwrap();
+        let states = resampled.states.iter().map(|x| x[0].state.unwrap());
+        assert_eq!(states.collect::<Vec<u32>>(), [0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn empty_trajectory_cannot_be_resampled() {
+        let trajectory = Trajectory::new(Vec::new(), Vec::new());
+        assert!(trajectory
+            .resample(&[0.0, 0.1], Resampling::Linear)
+            .is_err());
+        assert!(trajectory.resample_to_rate(10.0, Resampling::None).is_err());
+    }
+
+    #[test]
+    fn none_keeps_the_frames() {
+        let trajectory = constant_velocity(&[1.0, 1.3, 1.35]);
+        let resampled = trajectory.resample_to_rate(10.0, Resampling::None).unwrap();
+        assert_eq!(resampled.frames, trajectory.frames);
+        assert!((resampled.times[1] - 0.3).abs() < 1e-12);
+    }
+}
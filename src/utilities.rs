@@ -1,23 +1,120 @@
-use crate::{metrics::metric_dist, SwarmMetric, SwarmPos};
+use crate::{
+    alignment::{AlignWindow, Alignment},
+    canonical::canonical_hash,
+    controller::ControllerKind,
+    curves::{CurveOptions, DistanceCurves},
+    dataset::{parse_bot_pos_csv, Dataset, RunAggregation, DEFAULT_TICK_RATE},
+    distance::{series_dist, DistanceMeasure},
+    fidelity::Fidelity,
+    formats::AutomodeFrames,
+    fsm::split_cmd,
+    matching::{match_trajectories, TrajectoryError, TrajectoryMatch},
+    metrics::{mean_state_metric, to_metic, MetricVersion, STATE_METRIC_NAMES},
+    normalization::{NormMode, Normalization},
+    occupancy::Occupancy,
+    phases::{Phase, Phases},
+    scenario::Scenario,
+    store::TrajectoryStore,
+    streaming::{StreamedRun, StreamingDist},
+    trajectory::Resampling,
+    SwarmMetric, SwarmPos, SwarmState, SWARM_SIZE,
+};
 use rand::Rng;
-use std::process::Command;
+use std::{
+    io::{BufRead, BufReader},
+    ops::Range,
+    process::{Command, Stdio},
+};
 use tokio::runtime::Builder;
 
 #[derive(Debug, Clone)]
 pub struct Evaluator {
     pub automode_exe: String,
     pub scenario: String,
+    /// what `scenario` configures, `None` if the file is not readable from here
+    pub argos: Option<Scenario>,
     pub experiment_len: usize,
     pub num_of_experiments: usize,
     pub save_probability: f64,
     pub swarm_mode_dist: f64,
     pub density_radius: f64,
-    pub real_metric: Vec<SwarmMetric>,
-    pub metics_norm_min: SwarmMetric,
-    pub metics_norm_max: SwarmMetric,
+    pub metric_version: MetricVersion,
+    pub distance_measures: [DistanceMeasure; 9],
+    /// every real run of the mission the controllers are compared against
+    pub real: Vec<RealReference>,
+    pub real_aggregation: RunAggregation,
+    /// how recordings and simulations longer than `experiment_len` are cropped
+    pub alignment: Alignment,
+    /// control steps per second of the simulator, the common time grid of sim and real runs
+    pub sim_tick_rate: f64,
+    /// how real recordings are put onto the simulator's time grid
+    pub resampling: Resampling,
+    /// weight of the matched per-robot path error in the cost, 0 disables the matching
+    pub trajectory_weight: f64,
+    /// ticks per window for the robot assignment, `None` matches the whole trajectory at once
+    pub trajectory_window: Option<usize>,
+    pub normalization: Normalization,
+    /// where simulated trajectories of saved evaluations are kept for rescoring, `None` keeps none
+    pub trajectory_store: Option<TrajectoryStore>,
+    /// cost of a single simulation above which it is killed, see `eval_controller_bounded`
+    pub cost_bound: Option<f64>,
+    /// simulations are stopped after this many frames
+    pub tick_limit: Option<usize>,
+    /// set for evaluators of a prefix of the experiment, see `at_fidelity`
+    pub fidelity: Option<Fidelity>,
+    /// fidelity of the first evaluation of a candidate in multi-fidelity mode, `None` disables it
+    pub low_fidelity: Option<Fidelity>,
+    /// share of the candidates promoted from low to full fidelity
+    pub promote_fraction: f64,
+    /// how the experiment is split into phases
+    pub phase_spec: Phases,
+    /// the phases of `phase_spec` resolved for the metric series, empty if the whole run is scored at once
+    pub phases: Vec<Phase>,
+    /// ticks per bin of the FSM state occupancy over time
+    pub occupancy_bin: usize,
     pub db_path: String,
 }
 
+/// Positions of one real run, cropped to the experiment length, and their normalized metrics.
+#[derive(Debug, Clone)]
+pub struct RealReference {
+    pub name: String,
+    /// the part of the recording that was kept
+    pub window: AlignWindow,
+    pub pos: Vec<SwarmPos>,
+    pub metric: Vec<SwarmMetric>,
+}
+
+/// Distances of one simulation or the mean of several.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub metric: SwarmMetric,
+    /// one distance per phase of `Evaluator::phases`
+    pub phases: Vec<SwarmMetric>,
+    /// path error, Fréchet distance and final position error of the matched robots, `None` if
+    /// the matching is disabled
+    pub trajectory_error: Option<TrajectoryError>,
+    /// FSM state usage over the whole simulation, `None` if the simulator reported no states
+    pub occupancy: Option<Occupancy>,
+    /// mean polarization, milling and speed over the whole simulation, see `state_metric`, `None`
+    /// for those the reported robot states do not allow
+    pub state_metrics: [Option<f64>; 3],
+}
+
+impl Evaluation {
+    pub fn is_finite(&self) -> bool {
+        return self.metric.iter().all(|x| x.is_finite())
+            && self.phases.iter().flatten().all(|x| x.is_finite())
+            && self.trajectory_error.is_none_or(|x| x.is_finite());
+    }
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Evaluator {
     pub fn new() -> Self {
         let automode_exe = env!("AUTOMODE_EXE").to_string();
@@ -28,79 +125,377 @@ impl Evaluator {
         let save_probability = env!("SAVE_PROBABILITY").parse::<f64>().unwrap();
         let swarm_mode_dist = env!("SWARM_MODE_DIST").parse::<f64>().unwrap();
         let density_radius = env!("DENSITY_RADIUS").parse::<f64>().unwrap();
-        let [metics_norm_min, metics_norm_max] = get_metics_normalization();
-
-        // let real_pos = get_real_bot_data(experiment_len);
-        // // covert real pos to swarm metric and normalize them
-        // let real_metric = to_metic(&real_pos, swarm_mode_dist, density_radius);
-        // let mut temp = Vec::new();
-        // for metric in real_metric {
-        //     let mut metric_norm = SwarmMetric::default();
-        //     // (x - x_min) / (x_max - x_min)
-        //     for i in 0..metric_norm.len() {
-        //         metric_norm[i] =
-        //             (metric[i] - metics_norm_min[i]) / (metics_norm_max[i] - metics_norm_min[i]);
-        //     }
-        //     println!("{:?}",metric_norm);
-        //
-        //     temp.push(metric_norm);
-        // }
-        let real_metric = get_real_norm_metrics();
+        let metric_version = option_env!("METRIC_VERSION")
+            .map(|v| MetricVersion::parse(v).expect("METRIC_VERSION must be v1 or v2"))
+            .unwrap_or_default();
+        let distance_measures = option_env!("METRIC_DISTANCE")
+            .map(|v| DistanceMeasure::parse_all(v).expect("invalid METRIC_DISTANCE"))
+            .unwrap_or_default();
+        let trajectory_weight = option_env!("TRAJECTORY_WEIGHT")
+            .map(|v| v.parse::<f64>().unwrap())
+            .unwrap_or(0.0);
+        let trajectory_window = option_env!("TRAJECTORY_WINDOW")
+            .map(|v| v.parse::<usize>().unwrap())
+            .filter(|&v| v > 0);
+        // read at runtime, so a refitted normalization does not require a rebuild
+        let mut normalization =
+            match runtime_env("NORMALIZATION_FILE", option_env!("NORMALIZATION_FILE")) {
+                Some(path) => Normalization::load(&path).unwrap(),
+                None => {
+                    let [metics_norm_min, metics_norm_max] = get_metics_normalization();
+                    Normalization::min_max(metics_norm_min, metics_norm_max, "builtin")
+                }
+            };
+        if let Some(version) = normalization.metric_version {
+            assert_eq!(
+                version, metric_version,
+                "normalization was fitted with other metric definitions"
+            );
+        }
+        // all statistics are stored, so the mode of a fitted file can be switched
+        if let Some(mode) = runtime_env("NORMALIZATION_MODE", option_env!("NORMALIZATION_MODE")) {
+            normalization.mode = NormMode::parse(&mode).expect("invalid NORMALIZATION_MODE");
+        }
+        let real_aggregation = option_env!("REAL_AGGREGATION")
+            .filter(|x| !x.is_empty())
+            .map(|v| {
+                RunAggregation::parse(v).expect("REAL_AGGREGATION must be mean, best or worst")
+            })
+            .unwrap_or_default();
+        let alignment = option_env!("ALIGNMENT")
+            .filter(|x| !x.is_empty())
+            .map(|v| Alignment::parse(v).expect("invalid ALIGNMENT"))
+            .unwrap_or_default();
+        let sim_tick_rate = option_env!("SIM_TICK_RATE")
+            .map(|v| v.parse::<f64>().unwrap())
+            .unwrap_or(10.0);
+        // the simulator reports a missing scenario itself, it may only exist where it runs
+        let argos = if std::path::Path::new(&scenario).exists() {
+            let argos = Scenario::load(&scenario).unwrap();
+            argos
+                .validate(experiment_len, sim_tick_rate, SWARM_SIZE)
+                .map_err(|e| format!("{scenario}: {e}"))
+                .unwrap();
+            Some(argos)
+        } else {
+            eprintln!("scenario {scenario} not found, its settings are not validated");
+            None
+        };
+        let resampling = option_env!("RESAMPLING")
+            .filter(|x| !x.is_empty())
+            .map(|v| Resampling::parse(v).expect("invalid RESAMPLING"))
+            .unwrap_or_default();
+        let mut dataset = match runtime_env("REAL_DATA", option_env!("REAL_DATA")) {
+            Some(path) => Dataset::load(&path).unwrap(),
+            None => Dataset::builtin(),
+        };
+        if let Some(mission) = runtime_env("MISSION", option_env!("MISSION")) {
+            dataset = dataset.mission(&mission).unwrap();
+        }
+
+        let real_metric_cache = runtime_env("REAL_METRIC_CACHE", option_env!("REAL_METRIC_CACHE"));
+        let mut real = Vec::with_capacity(dataset.runs.len());
+        for run in dataset.runs {
+            let tick_rate = run.tick_rate.unwrap_or(DEFAULT_TICK_RATE);
+            if resampling == Resampling::None && tick_rate != sim_tick_rate {
+                eprintln!(
+                    "real run {} is recorded at {tick_rate} ticks/s but the simulator runs at {sim_tick_rate}, set RESAMPLING",
+                    run.name
+                );
+            }
+            let trajectory = run.resample_to_rate(sim_tick_rate, resampling).unwrap();
+            let (pos, window) = alignment
+                .align(&trajectory.frames, experiment_len)
+                .map_err(|e| format!("real run {}: {e}", run.name))
+                .unwrap();
+            let cache = real_metric_cache
+                .as_ref()
+                .map(|path| format!("{path}.{}", run.name));
+            let metric = get_real_metric(
+                &pos,
+                swarm_mode_dist,
+                density_radius,
+                metric_version,
+                &normalization,
+                cache.as_deref(),
+            );
+            real.push(RealReference {
+                name: run.name,
+                window,
+                pos,
+                metric,
+            });
+        }
+
+        let low_fidelity = option_env!("LOW_FIDELITY")
+            .filter(|x| !x.is_empty())
+            .map(|v| Fidelity::parse(v).expect("LOW_FIDELITY must look like 300x1"));
+        let promote_fraction = option_env!("PROMOTE_FRACTION")
+            .map(|v| v.parse::<f64>().unwrap())
+            .unwrap_or(0.25);
+        let trajectory_store = runtime_env("TRAJECTORY_STORE", option_env!("TRAJECTORY_STORE"))
+            .map(|dir| TrajectoryStore::new(&dir).unwrap());
+        let phase_spec = option_env!("PHASES")
+            .map(|v| Phases::parse(v).expect("invalid PHASES"))
+            .unwrap_or_default();
+        let phases = phase_spec.resolve(experiment_len - 1);
+        assert!(
+            phase_spec == Phases::Whole || !phases.is_empty(),
+            "no phase of PHASES is within the experiment"
+        );
+
+        let occupancy_bin = option_env!("OCCUPANCY_BIN")
+            .map(|v| v.parse::<usize>().expect("invalid OCCUPANCY_BIN"))
+            .unwrap_or(100);
 
         let db_con: sqlite::Connection = sqlite::open(&db_path).unwrap();
 
         let query =
             "CREATE TABLE IF NOT EXISTS data (controller_cmd TEXT, seeds TEXT, metric_norm TEXT, time INTEGER);";
         db_con.execute(query).unwrap();
+        add_column(&db_con, "data", "metric_version", "TEXT DEFAULT 'v1'");
+        add_column(&db_con, "data", "distance_measures", "TEXT DEFAULT 'tick'");
+        add_column(&db_con, "data", "path_error", "REAL");
+        add_column(&db_con, "data", "frechet", "REAL");
+        add_column(&db_con, "data", "final_position_error", "REAL");
+        for name in STATE_METRIC_NAMES {
+            add_column(&db_con, "data", name, "REAL");
+        }
+        add_column(
+            &db_con,
+            "data",
+            "normalization",
+            "TEXT DEFAULT 'minmax@builtin'",
+        );
+        add_column(
+            &db_con,
+            "data",
+            "real_runs",
+            "TEXT DEFAULT 'mean(all_bot_pos)'",
+        );
+        add_column(&db_con, "data", "source", "TEXT DEFAULT 'simulation'");
+        add_column(&db_con, "data", "fidelity", "TEXT DEFAULT 'full'");
+        add_column(&db_con, "data", "phases", "TEXT DEFAULT 'whole'");
+        add_column(&db_con, "data", "state_entropy", "REAL");
+        add_column(&db_con, "data", "canonical_hash", "TEXT");
+        let query = "CREATE TABLE IF NOT EXISTS phase_data (data_id INTEGER, phase TEXT, start INTEGER, end INTEGER, weight REAL, metric_norm TEXT);";
+        db_con.execute(query).unwrap();
+        // bin is NULL for the occupancy of the whole run
+        let query = "CREATE TABLE IF NOT EXISTS occupancy (data_id INTEGER, bin INTEGER, state INTEGER, fraction REAL);";
+        db_con.execute(query).unwrap();
+        let query = "CREATE TABLE IF NOT EXISTS transitions (data_id INTEGER, from_state INTEGER, to_state INTEGER, count REAL);";
+        db_con.execute(query).unwrap();
 
         assert!(save_probability <= 1.0);
         return Self {
             db_path,
             automode_exe,
             scenario,
+            argos,
             experiment_len,
             num_of_experiments,
             save_probability,
             swarm_mode_dist,
             density_radius,
-            real_metric,
-            metics_norm_min,
-            metics_norm_max,
+            metric_version,
+            distance_measures,
+            real,
+            real_aggregation,
+            alignment,
+            sim_tick_rate,
+            resampling,
+            trajectory_weight,
+            trajectory_window,
+            normalization,
+            trajectory_store,
+            cost_bound: None,
+            tick_limit: None,
+            fidelity: None,
+            low_fidelity,
+            promote_fraction,
+            phase_spec,
+            phases,
+            occupancy_bin,
+        };
+    }
+
+    /// evaluator of `experiment_len` ticks against the given real runs, without the simulator,
+    /// the environment or a database
+    #[cfg(test)]
+    pub(crate) fn for_tests(real_pos: &[Vec<SwarmPos>], experiment_len: usize) -> Self {
+        let normalization = Normalization::min_max(SwarmMetric::default(), [1.0; 9], "builtin");
+        let metric_version = MetricVersion::default();
+        let alignment = Alignment::default();
+        let real = real_pos
+            .iter()
+            .enumerate()
+            .map(|(i, pos)| {
+                let (pos, window) = alignment.align(pos, experiment_len).unwrap();
+                let metric = to_metic(&pos, 0.1, 0.5, metric_version);
+                RealReference {
+                    name: format!("run{i}"),
+                    window,
+                    pos,
+                    metric: normalization.norm_all(&metric),
+                }
+            })
+            .collect();
+        return Self {
+            automode_exe: String::new(),
+            scenario: String::new(),
+            argos: None,
+            experiment_len,
+            num_of_experiments: 1,
+            save_probability: 0.0,
+            swarm_mode_dist: 0.1,
+            density_radius: 0.5,
+            metric_version,
+            distance_measures: Default::default(),
+            real,
+            real_aggregation: RunAggregation::default(),
+            alignment,
+            sim_tick_rate: DEFAULT_TICK_RATE,
+            resampling: Resampling::default(),
+            trajectory_weight: 0.0,
+            trajectory_window: None,
+            normalization,
+            trajectory_store: None,
+            cost_bound: None,
+            tick_limit: None,
+            fidelity: None,
+            low_fidelity: None,
+            promote_fraction: 0.25,
+            phase_spec: Phases::default(),
+            phases: Vec::new(),
+            occupancy_bin: 100,
+            db_path: String::new(),
         };
     }
 
-    fn save_data(&self, controller_cmd: Vec<String>, seeds: Vec<i32>, metics: SwarmMetric) {
+    fn save_data(
+        &self,
+        controller_cmd: Vec<String>,
+        seeds: Vec<i32>,
+        evaluation: &Evaluation,
+        source: &str,
+    ) {
         let db_con: sqlite::Connection = sqlite::open(&self.db_path).unwrap();
 
+        // equivalent controllers share the hash, NULL if the controller does not parse
+        let canonical_hash = canonical_hash(&controller_cmd)
+            .map(|x| format!("'{x}'"))
+            .unwrap_or("NULL".to_string());
         let controller_cmd = controller_cmd.join(" ");
         let seeds = seeds
             .iter()
             .map(|x| format!("{x}"))
             .collect::<Vec<String>>()
             .join(", ");
-        let metric_norm = metics
+        let metric_norm = join_metric(&evaluation.metric);
+
+        let metric_version = self.metric_version.name();
+        let distance_measures = self
+            .distance_measures
             .iter()
-            .map(|x| format!("{x}"))
+            .map(|x| x.name())
             .collect::<Vec<String>>()
             .join(", ");
+        let trajectory_error = |f: fn(&TrajectoryError) -> f64| {
+            evaluation
+                .trajectory_error
+                .as_ref()
+                .map(|x| format!("{}", f(x)))
+                .unwrap_or("NULL".to_string())
+        };
+        let path_error = trajectory_error(|x| x.path_error);
+        let frechet = trajectory_error(|x| x.frechet);
+        let final_position_error = trajectory_error(|x| x.final_position_error);
+        let normalization = self.normalization.id();
+        let real_runs = sql_escape(&format!(
+            "{}({})",
+            self.real_aggregation.name(),
+            self.real
+                .iter()
+                .map(|x| format!("{} {}", x.name, x.window))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+
+        let fidelity = self
+            .fidelity
+            .map(|x| x.name())
+            .unwrap_or("full".to_string());
+        let phases = sql_escape(&self.phase_spec.name());
+        let state_metrics = evaluation
+            .state_metrics
+            .map(|x| x.map(|x| format!("{x}")).unwrap_or("NULL".to_string()));
+        let [polarization, milling, mean_speed] = &state_metrics;
+        let state_entropy = evaluation
+            .occupancy
+            .as_ref()
+            .map(|x| format!("{}", x.entropy))
+            .unwrap_or("NULL".to_string());
 
-        let query = format!("INSERT INTO data (controller_cmd, seeds, metric_norm, time) VALUES ('{controller_cmd}', '{seeds}', '{metric_norm}', datetime('now', 'localtime'));");
+        let query = format!("INSERT INTO data (controller_cmd, seeds, metric_norm, metric_version, distance_measures, path_error, frechet, final_position_error, normalization, real_runs, source, fidelity, phases, state_entropy, polarization, milling, mean_speed, canonical_hash, time) VALUES ('{controller_cmd}', '{seeds}', '{metric_norm}', '{metric_version}', '{distance_measures}', {path_error}, {frechet}, {final_position_error}, '{normalization}', '{real_runs}', '{source}', '{fidelity}', '{phases}', {state_entropy}, {polarization}, {milling}, {mean_speed}, {canonical_hash}, datetime('now', 'localtime'));");
         db_con.execute(query).unwrap();
+
+        let mut data_id = String::new();
+        db_con
+            .iterate("SELECT last_insert_rowid();", |pairs| {
+                data_id = pairs[0].1.unwrap_or("NULL").to_string();
+                true
+            })
+            .unwrap();
+        for (phase, metric) in self.phases.iter().zip(&evaluation.phases) {
+            let metric_norm = join_metric(metric);
+            let query = format!("INSERT INTO phase_data (data_id, phase, start, end, weight, metric_norm) VALUES ({data_id}, '{}', {}, {}, {}, '{metric_norm}');", sql_escape(&phase.name), phase.ticks.start, phase.ticks.end, phase.weight);
+            db_con.execute(query).unwrap();
+        }
+
+        if let Some(occupancy) = &evaluation.occupancy {
+            let bins = std::iter::once(("NULL".to_string(), &occupancy.fractions)).chain(
+                occupancy
+                    .over_time
+                    .iter()
+                    .enumerate()
+                    .map(|(k, x)| (format!("{k}"), x)),
+            );
+            for (bin, fractions) in bins {
+                for (state, fraction) in fractions.iter().enumerate() {
+                    let query = format!("INSERT INTO occupancy (data_id, bin, state, fraction) VALUES ({data_id}, {bin}, {state}, {fraction});");
+                    db_con.execute(query).unwrap();
+                }
+            }
+            for (from, row) in occupancy.transitions.iter().enumerate() {
+                for (to, count) in row.iter().enumerate().filter(|(_, x)| **x > 0.0) {
+                    let query = format!("INSERT INTO transitions (data_id, from_state, to_state, count) VALUES ({data_id}, {from}, {to}, {count});");
+                    db_con.execute(query).unwrap();
+                }
+            }
+        }
     }
 
     // INSERT INTO data (controller_cmd, seeds, metric_norm, time) VALUES ('aa', 'bb', 'cc', datetime('now', 'localtime'));
 
     pub fn get_command(&self, seed: i32, controller_cmd: Vec<String>) -> std::process::Output {
-        return Command::new(self.automode_exe.clone())
+        return self
+            .command(seed, controller_cmd)
+            .output()
+            .expect("failed to execute experiment");
+    }
+
+    fn command(&self, seed: i32, controller_cmd: Vec<String>) -> Command {
+        let config_flag = ControllerKind::of(&controller_cmd).config_flag();
+        let mut command = Command::new(self.automode_exe.clone());
+        command
             .arg("-n")
             .arg("-c")
             .arg(self.scenario.clone())
             .arg("--seed")
             .arg(format!("{}", seed))
-            .arg("--fsm-config")
-            .args(controller_cmd)
-            .output()
-            .expect("failed to execute experiment");
+            .arg(config_flag)
+            .args(controller_cmd);
+        return command;
     }
 
     pub fn eval_controller(&self, controller_cmd: Vec<String>) -> f64 {
@@ -111,21 +506,138 @@ impl Evaluator {
             seeds.push(rng.gen_range(0..0x7FFFFFFF));
         }
 
-        let metics = self.eval_all(controller_cmd.clone(), seeds.clone());
+        // decided up front, so only the trajectories of saved evaluations are stored
+        let save = self.save_probability > rng.gen_range(0.0..1.0);
+        let f: fn(&Evaluator, Vec<String>, i32) -> Evaluation = if save {
+            Evaluator::eval_and_store
+        } else {
+            Evaluator::eval
+        };
 
-        if self.save_probability > rng.gen_range(0.0..1.0) {
-            self.save_data(controller_cmd, seeds, metics);
+        let data = self.run_all(controller_cmd.clone(), seeds.clone(), f);
+        let evaluation = self.mean_evaluation(&data, seeds.len());
+        if !evaluation.is_finite() {
+            return f64::INFINITY;
         }
 
-        let mut sum = 0.0;
-        for val in metics {
-            sum += val;
+        if save {
+            self.save_data(controller_cmd, seeds, &evaluation, "simulation");
         }
+        return self.cost(&evaluation);
+    }
+
+    /// mean metric distance, or the weighted mean of the phase distances if the run is split into
+    /// phases, plus the weighted path error
+    pub fn cost(&self, evaluation: &Evaluation) -> f64 {
+        let mean = |m: &SwarmMetric| m.iter().sum::<f64>() / m.len() as f64;
+
+        let total_weight = self.phases.iter().map(|x| x.weight).sum::<f64>();
+        let cost = if total_weight > 0.0 {
+            self.phases
+                .iter()
+                .zip(&evaluation.phases)
+                .map(|(phase, metric)| phase.weight * mean(metric))
+                .sum::<f64>()
+                / total_weight
+        } else {
+            mean(&evaluation.metric)
+        };
+        let path_error = evaluation.trajectory_error.map(|x| x.path_error);
+        return cost + self.trajectory_weight * path_error.unwrap_or(0.0);
+    }
 
-        return sum / SwarmMetric::default().len() as f64;
+    /// like `eval_controller`, but simulations are killed as soon as the cost is certain to exceed
+    /// `bound`, e.g. the best cost found so far, and the cost of such a controller is infinite
+    ///
+    /// only the tick aligned distance of a window starting at the first frame can be bounded early,
+    /// see `StreamingDist::is_bounding`, otherwise every simulation runs to the end
+    pub fn eval_controller_bounded(&self, controller_cmd: Vec<String>, bound: f64) -> f64 {
+        // a single simulation above n times the bound pushes the mean over n simulations above it
+        let eval = Evaluator {
+            cost_bound: Some(bound * self.num_of_experiments as f64),
+            ..self.clone()
+        };
+        return eval.eval_controller(controller_cmd);
     }
 
-    pub fn eval_all(&self, controller_cmd: Vec<String>, seeds: Vec<i32>) -> SwarmMetric {
+    /// evaluator of the first `fidelity.ticks` ticks of the experiment with `fidelity.seeds` seeds,
+    /// the real windows are cut to the same prefix and simulations are stopped after it
+    pub fn at_fidelity(&self, fidelity: Fidelity) -> Result<Evaluator, String> {
+        if fidelity.ticks < 2 || fidelity.ticks > self.experiment_len {
+            return Err(format!(
+                "fidelity {} needs between 2 and {} ticks",
+                fidelity.name(),
+                self.experiment_len
+            ));
+        }
+        if fidelity.seeds == 0 {
+            return Err(format!("fidelity {} has no seeds", fidelity.name()));
+        }
+
+        let real = self
+            .real
+            .iter()
+            .map(|real| RealReference {
+                name: real.name.clone(),
+                window: AlignWindow {
+                    len: fidelity.ticks,
+                    ..real.window
+                },
+                pos: real.pos[..fidelity.ticks].to_vec(),
+                metric: real.metric[..fidelity.ticks - 1].to_vec(),
+            })
+            .collect();
+
+        return Ok(Evaluator {
+            experiment_len: fidelity.ticks,
+            num_of_experiments: fidelity.seeds,
+            real,
+            tick_limit: Some(fidelity.ticks),
+            fidelity: Some(fidelity),
+            // phases beyond the prefix are dropped, if none is left the prefix is scored at once
+            phases: self.phase_spec.resolve(fidelity.ticks - 1),
+            // a prefix is no trajectory to rescore
+            trajectory_store: None,
+            ..self.clone()
+        });
+    }
+
+    pub fn eval_all(&self, controller_cmd: Vec<String>, seeds: Vec<i32>) -> Evaluation {
+        let num_of_experiments = seeds.len();
+        let data = self.run_all(controller_cmd, seeds, Evaluator::eval);
+
+        return self.mean_evaluation(&data, num_of_experiments);
+    }
+
+    /// the evaluation of every seed on its own, seeds whose experiment panicked are missing
+    pub fn eval_seeds(
+        &self,
+        controller_cmd: Vec<String>,
+        seeds: Vec<i32>,
+    ) -> Vec<(i32, Evaluation)> {
+        return self.run_all(controller_cmd, seeds, |eval, controller_cmd, seed| {
+            (seed, eval.eval(controller_cmd, seed))
+        });
+    }
+
+    /// the simulated positions of every seed, seeds whose experiment panicked are missing
+    pub fn run_seeds(
+        &self,
+        controller_cmd: Vec<String>,
+        seeds: Vec<i32>,
+    ) -> Vec<(i32, Vec<SwarmPos>)> {
+        return self.run_all(controller_cmd, seeds, |eval, controller_cmd, seed| {
+            (seed, eval.run_experiment(controller_cmd, seed))
+        });
+    }
+
+    /// runs `f` for every seed in parallel, results of experiments that panicked are dropped
+    fn run_all<T: Send + 'static>(
+        &self,
+        controller_cmd: Vec<String>,
+        seeds: Vec<i32>,
+        f: fn(&Evaluator, Vec<String>, i32) -> T,
+    ) -> Vec<T> {
         let num_of_experiments = seeds.len();
 
         let runtime = Builder::new_multi_thread()
@@ -135,205 +647,681 @@ impl Evaluator {
             .unwrap();
         let mut handles = Vec::with_capacity(num_of_experiments);
         for seed in seeds {
-            handles.push(runtime.spawn(self.clone().eval_async(controller_cmd.clone(), seed)));
+            handles.push(runtime.spawn(self.clone().run_async(f, controller_cmd.clone(), seed)));
         }
 
-        let metrics_data: Vec<Result<SwarmMetric, _>> =
-            runtime.block_on(futures::future::join_all(handles));
+        let data: Vec<Result<T, _>> = runtime.block_on(futures::future::join_all(handles));
+        return data.into_iter().flatten().collect();
+    }
+
+    async fn run_async<T>(
+        self,
+        f: fn(&Evaluator, Vec<String>, i32) -> T,
+        controller_cmd: Vec<String>,
+        seed: i32,
+    ) -> T {
+        f(&self, controller_cmd, seed)
+    }
 
-        let mut result = SwarmMetric::default();
-        for metrics in metrics_data {
-            if let Ok(val) = metrics {
-                for i in 0..val.len() {
-                    result[i] += val[i];
-                }
+    /// the distances of a simulation that was terminated early are infinite
+    pub fn eval(&self, controller_cmd: Vec<String>, seed: i32) -> Evaluation {
+        return self.eval_run(&self.run_streaming(controller_cmd, seed));
+    }
+
+    /// like `eval`, but a simulation that ran to the end is also kept in the trajectory store
+    pub fn eval_and_store(&self, controller_cmd: Vec<String>, seed: i32) -> Evaluation {
+        let run = self.run_streaming(controller_cmd.clone(), seed);
+        if let (Some(store), false) = (&self.trajectory_store, run.terminated) {
+            if let Err(e) = store.save(&controller_cmd, seed, &run.frames, &run.states) {
+                eprintln!("{e}");
             }
         }
+        return self.eval_run(&run);
+    }
+
+    fn eval_run(&self, run: &StreamedRun) -> Evaluation {
+        if run.terminated {
+            return self.infinite_evaluation();
+        }
+        return self.eval_states(&run.frames, &run.states);
+    }
+
+    /// the evaluation of a simulation that did not reach the end of the experiment
+    fn infinite_evaluation(&self) -> Evaluation {
+        return Evaluation {
+            metric: [f64::INFINITY; 9],
+            phases: vec![[f64::INFINITY; 9]; self.phases.len()],
+            trajectory_error: (self.trajectory_weight > 0.0).then(TrajectoryError::infinite),
+            occupancy: None,
+            state_metrics: [None; 3],
+        };
+    }
+
+    /// like `eval_pos`, with the occupancy and the state metrics of the reported robot states
+    pub fn eval_states(&self, sim_pos: &[SwarmPos], states: &[SwarmState]) -> Evaluation {
+        return Evaluation {
+            occupancy: Occupancy::new(states, self.occupancy_bin),
+            state_metrics: mean_state_metric(sim_pos, states),
+            ..self.eval_pos(sim_pos)
+        };
+    }
+
+    /// element wise mean of the distances, divided by the number of experiments that were started,
+    /// and mean path error, occupancy and state metrics of the finished ones
+    fn mean_evaluation(&self, data: &[Evaluation], num_of_experiments: usize) -> Evaluation {
+        let metrics = data.iter().map(|x| x.metric).collect::<Vec<SwarmMetric>>();
+        let phases = (0..self.phases.len())
+            .map(|i| {
+                let metrics = data
+                    .iter()
+                    .map(|x| x.phases[i])
+                    .collect::<Vec<SwarmMetric>>();
+                mean_metric(&metrics, num_of_experiments)
+            })
+            .collect();
+        let trajectory_error = TrajectoryError::mean(
+            &data
+                .iter()
+                .filter_map(|x| x.trajectory_error)
+                .collect::<Vec<TrajectoryError>>(),
+        );
+
+        let occupancy = Occupancy::mean(
+            &data
+                .iter()
+                .filter_map(|x| x.occupancy.as_ref())
+                .collect::<Vec<&Occupancy>>(),
+        );
+
+        let state_metrics = std::array::from_fn(|i| {
+            let vals = data
+                .iter()
+                .filter_map(|x| x.state_metrics[i])
+                .collect::<Vec<f64>>();
+            (!vals.is_empty()).then(|| mean(&vals))
+        });
+
+        return Evaluation {
+            metric: mean_metric(&metrics, num_of_experiments),
+            phases,
+            trajectory_error,
+            occupancy,
+            state_metrics,
+        };
+    }
+
+    /// errors of the matches of one simulation against every real run, the ones of the `selected`
+    /// run or the mean over all of them
+    fn trajectory_error(
+        &self,
+        matches: &[TrajectoryMatch],
+        selected: Option<usize>,
+    ) -> TrajectoryError {
+        if let Some(i) = selected {
+            return matches[i].error();
+        }
+        let errors = matches.iter().map(|x| x.error()).collect::<Vec<_>>();
+        return TrajectoryError::mean(&errors).unwrap();
+    }
+
+    /// recomputes the simulated evaluations whose trajectories are all stored under the active
+    /// configuration and saves them as new rows, returns the number of rescored and skipped ones
+    pub fn rescore(&self) -> Result<(usize, usize), String> {
+        let store = self
+            .trajectory_store
+            .as_ref()
+            .ok_or("TRAJECTORY_STORE is not set")?;
+        let db_con: sqlite::Connection = sqlite::open(&self.db_path).map_err(|e| e.to_string())?;
+
+        let mut evaluations = Vec::new();
+        db_con
+            .iterate(
+                "SELECT DISTINCT controller_cmd, seeds FROM data WHERE source = 'simulation';",
+                |pairs| {
+                    let value = |i: usize| pairs[i].1.unwrap_or("").to_string();
+                    evaluations.push((value(0), value(1)));
+                    true
+                },
+            )
+            .map_err(|e| e.to_string())?;
+
+        let mut rescored = 0;
+        let mut skipped = 0;
+        for (controller_cmd, seeds) in evaluations {
+            let controller_cmd = split_cmd(&controller_cmd);
+            let seeds = seeds
+                .split(",")
+                .map(|x| x.trim().parse::<i32>())
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(|_| format!("invalid seeds {seeds}"))?;
+
+            let mut sim_runs = Vec::with_capacity(seeds.len());
+            for &seed in &seeds {
+                match store.load_states(&controller_cmd, seed) {
+                    Ok(run) if run.0.len() >= self.experiment_len => sim_runs.push(run),
+                    _ => break,
+                }
+            }
+            if sim_runs.len() != seeds.len() {
+                skipped += 1;
+                continue;
+            }
 
-        for i in 0..result.len() {
-            result[i] /= num_of_experiments as f64;
+            let data = sim_runs
+                .iter()
+                .map(|(pos, states)| self.eval_states(pos, states))
+                .collect::<Vec<Evaluation>>();
+            let evaluation = self.mean_evaluation(&data, seeds.len());
+            self.save_data(controller_cmd, seeds, &evaluation, "rescore");
+            rescored += 1;
         }
 
-        return result;
+        return Ok((rescored, skipped));
     }
 
-    async fn eval_async(self, controller_cmd: Vec<String>, seed: i32) -> SwarmMetric {
-        self.eval(controller_cmd, seed)
+    /// simulations longer than expected are cropped like the real recordings, shorter ones fail
+    pub fn align_sim(&self, sim_pos: &[SwarmPos]) -> Result<Vec<SwarmPos>, String> {
+        return self.alignment.align_sim(sim_pos, self.experiment_len);
     }
-    pub fn eval(&self, controller_cmd: Vec<String>, seed: i32) -> SwarmMetric {
-        let sim_pos = self.run_experiment(controller_cmd, seed);
 
-        assert_eq!(sim_pos.len(), self.experiment_len);
+    /// distances of already simulated positions to the real runs, as a whole and per phase, the
+    /// occupancy is left to the caller which knows the robot states, a simulation that ended
+    /// early is infinitely far like a terminated one
+    pub fn eval_pos(&self, sim_pos: &[SwarmPos]) -> Evaluation {
+        let sim_pos = match self.align_sim(sim_pos) {
+            Ok(sim_pos) => sim_pos,
+            Err(_) => return self.infinite_evaluation(),
+        };
+
+        let sim_metric = self.norm_metric(&sim_pos);
+        let phases = self
+            .phases
+            .iter()
+            .map(|phase| {
+                let dists = self.run_dists(&sim_metric, phase.ticks.clone());
+                self.real_aggregation.aggregate(&dists)
+            })
+            .collect();
+        let dists = self.run_dists(&sim_metric, 0..sim_metric.len());
+        let trajectory_error = (self.trajectory_weight > 0.0).then(|| {
+            let matched = self
+                .real
+                .iter()
+                .map(|real| match_trajectories(&sim_pos, &real.pos, self.trajectory_window))
+                .collect::<Vec<TrajectoryMatch>>();
+            // best and worst take the errors of the run they take the metric distances of
+            self.trajectory_error(&matched, self.real_aggregation.select(&dists))
+        });
+
+        return Evaluation {
+            metric: self.real_aggregation.aggregate(&dists),
+            phases,
+            trajectory_error,
+            occupancy: None,
+            state_metrics: [None; 3],
+        };
+    }
 
-        let metrics_dist = metric_dist(
-            &sim_pos,
+    /// normalized per-tick metrics of already aligned positions
+    pub fn norm_metric(&self, sim_pos: &[SwarmPos]) -> Vec<SwarmMetric> {
+        let sim_metric = to_metic(
+            sim_pos,
             self.swarm_mode_dist,
             self.density_radius,
-            &self.real_metric,
-            &self.metics_norm_min,
-            &self.metics_norm_max,
+            self.metric_version,
         );
+        return self.normalization.norm_all(&sim_metric);
+    }
 
-        return metrics_dist;
+    /// metric distance of the ticks `ticks` of the normalized simulated metrics to every real run
+    fn run_dists(&self, sim_metric: &[SwarmMetric], ticks: Range<usize>) -> Vec<SwarmMetric> {
+        return self
+            .real
+            .iter()
+            .map(|real| {
+                series_dist(
+                    &sim_metric[ticks.clone()],
+                    &real.metric[ticks.clone()],
+                    &self.distance_measures,
+                )
+            })
+            .collect::<Vec<SwarmMetric>>();
+    }
+
+    /// per-tick differences of already simulated positions to the real metrics, aligned like `eval_pos`
+    pub fn distance_curves(
+        &self,
+        sim_pos: &[SwarmPos],
+        options: CurveOptions,
+    ) -> Result<DistanceCurves, String> {
+        let mut dist = StreamingDist::new(self);
+        for frame in self.align_sim(sim_pos)? {
+            dist.push(&frame);
+        }
+        return Ok(DistanceCurves::new(dist.per_tick, options));
+    }
+
+    /// compares an external reference, e.g. `real_norm_metrics.csv`, with the metrics of a real run
+    /// recomputed for the active configuration and returns the largest absolute difference per metric
+    pub fn verify_reference(
+        &self,
+        run: &str,
+        reference: &[SwarmMetric],
+    ) -> Result<SwarmMetric, String> {
+        let real = self
+            .real
+            .iter()
+            .find(|x| x.name == run)
+            .ok_or(format!("unknown real run {run}"))?;
+        if reference.len() != real.metric.len() {
+            return Err(format!(
+                "reference has {} ticks but {} were recomputed",
+                reference.len(),
+                real.metric.len()
+            ));
+        }
+
+        let mut max_diff = SwarmMetric::default();
+        for (reference, real) in reference.iter().zip(real.metric.iter()) {
+            for i in 0..max_diff.len() {
+                let diff = if reference[i].is_nan() && real[i].is_nan() {
+                    0.0
+                } else {
+                    f64::abs(reference[i] - real[i])
+                };
+                // NaN in only one of them is a mismatch
+                let diff = if diff.is_nan() { f64::INFINITY } else { diff };
+                max_diff[i] = f64::max(max_diff[i], diff);
+            }
+        }
+        return Ok(max_diff);
+    }
+
+    /// runs every controller with every seed and fits the normalization to the raw per-tick metrics
+    ///
+    /// unlike `test.py`, which took the bounds of the per-run averages, all ticks are used,
+    /// since the normalization is applied per tick
+    pub fn fit_normalization(
+        &self,
+        controllers: Vec<Vec<String>>,
+        seeds: Vec<i32>,
+        mode: NormMode,
+    ) -> Normalization {
+        let mut metrics = Vec::new();
+        for controller_cmd in controllers {
+            for sim_pos in self.run_all(controller_cmd, seeds.clone(), Evaluator::run_experiment) {
+                metrics.extend(to_metic(
+                    &sim_pos,
+                    self.swarm_mode_dist,
+                    self.density_radius,
+                    self.metric_version,
+                ));
+            }
+        }
+
+        return Normalization::fit(
+            &metrics,
+            mode,
+            self.metric_version,
+            self.swarm_mode_dist,
+            self.density_radius,
+        );
     }
 
     pub fn run_experiment(&self, controller_cmd: Vec<String>, seed: i32) -> Vec<SwarmPos> {
-        let output = self.get_command(seed, controller_cmd);
+        return self.run_streaming(controller_cmd, seed).frames;
+    }
 
-        let out_bytes = output.stdout;
-        let out_str = String::from_utf8(out_bytes)
-            .map_err(|e| format!("Invalid UTF-8 sequence: {}", e))
-            .unwrap();
+    /// reads the simulator's output while it runs and kills it once the partial cost exceeds `cost_bound`
+    pub fn run_streaming(&self, controller_cmd: Vec<String>, seed: i32) -> StreamedRun {
+        let mut child = self
+            .command(seed, controller_cmd)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to execute experiment");
+        let stdout = child.stdout.take().unwrap();
 
-        let mut swarm_pos = Vec::new();
-        let mut current_pos = SwarmPos::default();
+        let mut parser = AutomodeFrames::default();
+        let mut dist = StreamingDist::new(self);
+        let bound = self.cost_bound.filter(|_| dist.is_bounding());
+        let mut frames = Vec::with_capacity(self.experiment_len + 1);
+        let mut states = Vec::with_capacity(self.experiment_len + 1);
+        let mut terminated = false;
 
-        for line in out_str.split("\n") {
-            if line.chars().nth(0) == Some('%') && line.chars().nth(1) == Some('!') {
-                let mut line_it = line.split(" ").collect::<Vec<&str>>().into_iter();
-                assert_eq!(line_it.next(), Some("%!"));
+        for line in BufReader::new(stdout).lines() {
+            let line = line
+                .map_err(|e| format!("Invalid UTF-8 sequence: {}", e))
+                .unwrap();
+            let Some(frame) = parser.push_line(&line).unwrap() else {
+                continue;
+            };
+            dist.push(&frame);
+            frames.push(frame);
+            states.push(parser.state());
 
-                let mut i = line_it.next().unwrap().split(":");
-                let mut x = line_it.next().unwrap().split(":");
-                let mut y = line_it.next().unwrap().split(":");
+            if bound.is_some_and(|bound| dist.partial_cost() > bound) {
+                terminated = true;
+                let _ = child.kill();
+                break;
+            }
+            if self.tick_limit.is_some_and(|limit| frames.len() >= limit) {
+                let _ = child.kill();
+                break;
+            }
+        }
+        let _ = child.wait();
+        if !parser.has_states() {
+            states.clear();
+        }
 
-                assert_eq!(i.next(), Some("i"));
-                assert_eq!(x.next(), Some("x"));
-                assert_eq!(y.next(), Some("y"));
+        return StreamedRun {
+            frames,
+            states,
+            per_tick: dist.per_tick,
+            terminated,
+        };
+    }
+}
 
-                let i = i.next().unwrap().parse::<usize>().unwrap();
-                let x = x.next().unwrap().parse::<f64>().unwrap();
-                let y = y.next().unwrap().parse::<f64>().unwrap();
+/// a runtime environment variable, falling back to the value set at build time, empty counts as unset
+pub(crate) fn runtime_env(name: &str, build_time: Option<&str>) -> Option<String> {
+    return std::env::var(name)
+        .ok()
+        .or(build_time.map(|x| x.to_string()))
+        .filter(|x| !x.is_empty());
+}
 
-                current_pos[i] = (x, y);
-                if i == 14 {
-                    swarm_pos.push(current_pos);
+/// text with its single quotes doubled, to be put between single quotes in a query
+fn sql_escape(text: &str) -> String {
+    return text.replace('\'', "''");
+}
+
+fn join_metric(metric: &SwarmMetric) -> String {
+    return metric
+        .iter()
+        .map(|x| format!("{x}"))
+        .collect::<Vec<String>>()
+        .join(", ");
+}
+
+/// element wise mean, divided by the number of experiments that were started
+fn mean_metric(metrics_data: &[SwarmMetric], num_of_experiments: usize) -> SwarmMetric {
+    let mut result = SwarmMetric::default();
+    for val in metrics_data {
+        for i in 0..val.len() {
+            result[i] += val[i];
+        }
+    }
+
+    for val in result.iter_mut() {
+        *val /= num_of_experiments as f64;
+    }
+
+    return result;
+}
+
+/// adds a column to an existing table, databases created before the column existed are migrated in place
+fn add_column(db_con: &sqlite::Connection, table: &str, column: &str, column_type: &str) {
+    let mut exists = false;
+    db_con
+        .iterate(format!("PRAGMA table_info({table});"), |pairs| {
+            for &(name, value) in pairs {
+                if name == "name" && value == Some(column) {
+                    exists = true;
                 }
             }
-        }
+            true
+        })
+        .unwrap();
 
-        return swarm_pos;
+    if !exists {
+        let query = format!("ALTER TABLE {table} ADD COLUMN {column} {column_type};");
+        db_con.execute(query).unwrap();
     }
 }
 
-fn get_metics_normalization() -> [SwarmMetric; 2] {
+pub(crate) fn get_metics_normalization() -> [SwarmMetric; 2] {
     let mut line_it = include_str!("metics_normalization.csv")
         .split("\n")
         .collect::<Vec<&str>>()
         .into_iter();
 
     let mut max = SwarmMetric::default();
-    let mut i = 0;
-    for val in line_it.next().unwrap().split(",") {
+    for (i, val) in line_it.next().unwrap().split(",").enumerate() {
         assert!(i < SwarmMetric::default().len());
         max[i] = val.trim().parse::<f64>().unwrap();
-        i += 1;
     }
 
     let mut min = SwarmMetric::default();
-    let mut i = 0;
-    for val in line_it.next().unwrap().split(",") {
+    for (i, val) in line_it.next().unwrap().split(",").enumerate() {
         assert!(i < SwarmMetric::default().len());
         min[i] = val.trim().parse::<f64>().unwrap();
-        i += 1;
     }
     assert_eq!(line_it.next(), None);
 
     return [min, max];
 }
 
-fn get_real_norm_metrics() -> Vec<SwarmMetric> {
-    let line_it = include_str!("real_norm_metrics.csv")
-        .split("\n")
-        .collect::<Vec<&str>>()
-        .into_iter();
+/// covert the real positions to swarm metrics and normalize them
+///
+/// if a cache file is given, it is reused as long as it was written for the same metric configuration, normalization and data
+fn get_real_metric(
+    real_pos: &[SwarmPos],
+    swarm_mode_dist: f64,
+    density_radius: f64,
+    metric_version: MetricVersion,
+    normalization: &Normalization,
+    cache: Option<&str>,
+) -> Vec<SwarmMetric> {
+    let mut key = format!(
+        "# {} {swarm_mode_dist} {density_radius} {} {:016x} {:016x}",
+        metric_version.name(),
+        real_pos.len(),
+        fnv1a(normalization.to_file_string().as_bytes()),
+        fnv1a(format!("{real_pos:?}").as_bytes()),
+    );
+    // the mode is not part of the file content, so it is added to the key separately
+    key.push_str(&format!(" {}", normalization.mode.name()));
+
+    if let Some(path) = cache {
+        if let Ok(content) = std::fs::read_to_string(path) {
+            if let Some((head, body)) = content.split_once("\n") {
+                if head == key {
+                    if let Ok(metrics) = parse_metrics_csv(body) {
+                        return metrics;
+                    }
+                }
+            }
+        }
+    }
 
+    let real_metric = to_metic(real_pos, swarm_mode_dist, density_radius, metric_version);
+    let real_metric = normalization.norm_all(&real_metric);
+
+    if let Some(path) = cache {
+        let content = format!("{key}\n{}", metrics_to_csv(&real_metric));
+        if let Err(e) = std::fs::write(path, content) {
+            eprintln!("failed to write real metric cache {path}: {e}");
+        }
+    }
+
+    return real_metric;
+}
+
+/// one metric per line, as in `real_norm_metrics.csv`
+pub fn parse_metrics_csv(content: &str) -> Result<Vec<SwarmMetric>, String> {
     let mut metrics = Vec::new();
-    for line in line_it {
+    for line in content.split("\n") {
         let line = line.trim();
-        if line.is_empty() {
+        if line.is_empty() || line.starts_with('#') {
             continue;
         }
 
         let vals = line
             .split(",")
-            .map(|s| s.trim().parse().unwrap())
-            .collect::<Vec<f64>>();
+            .map(|s| {
+                s.trim()
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid value {s}"))
+            })
+            .collect::<Result<Vec<f64>, String>>()?;
 
         let mut metric = SwarmMetric::default();
-        assert_eq!(vals.len(), metric.len());
-
-        for i in 0..metric.len() {
-            metric[i] = vals[i]
+        if vals.len() != metric.len() {
+            return Err(format!(
+                "expected {} values but got {}",
+                metric.len(),
+                vals.len()
+            ));
         }
 
+        metric.copy_from_slice(&vals);
+
         metrics.push(metric)
     }
 
-    return metrics;
+    return Ok(metrics);
 }
 
-pub fn get_real_bot_data(experiment_len: usize) -> Vec<SwarmPos> {
-    let mut line_it = include_str!("all_bot_pos.csv")
-        .split("\n")
-        .collect::<Vec<&str>>()
-        .into_iter();
+pub fn metrics_to_csv(metrics: &[SwarmMetric]) -> String {
+    let mut content = String::new();
+    for metric in metrics {
+        let line = metric
+            .iter()
+            .map(|x| format!("{x}"))
+            .collect::<Vec<String>>()
+            .join(", ");
+        content.push_str(&line);
+        content.push('\n');
+    }
+    return content;
+}
+
+/// stable 64 bit FNV-1a hash, used to key cached data
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    return hash;
+}
 
-    let head = line_it
-        .next()
-        .unwrap()
-        .split(",")
-        .map(|s| s.trim())
-        .collect::<Vec<&str>>();
+/// arithmetic mean, NaN for no values
+pub fn mean(vals: &[f64]) -> f64 {
+    return vals.iter().sum::<f64>() / vals.len() as f64;
+}
 
-    assert_eq!(head.len(), 2 * 15);
+/// sample standard deviation, 0 for less than two values
+pub fn std_dev(vals: &[f64]) -> f64 {
+    if vals.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(vals);
+    let var = vals.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (vals.len() - 1) as f64;
+    return var.sqrt();
+}
 
-    let mut bot_pos = Vec::new();
+pub fn get_real_bot_data(experiment_len: usize) -> Vec<SwarmPos> {
+    let bot_pos = parse_bot_pos_csv(include_str!("all_bot_pos.csv")).unwrap();
+    let (bot_pos, _) = Alignment::Center.align(&bot_pos, experiment_len).unwrap();
+    return bot_pos;
+}
 
-    for line in line_it {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let vals = line
-            .split(",")
-            .map(|s| s.trim().parse().unwrap())
-            .collect::<Vec<f64>>();
+    fn spreading(len: usize, speed: f64) -> Vec<SwarmPos> {
+        return (0..len)
+            .map(|t| {
+                let mut frame = [(0.0, 0.0); SWARM_SIZE];
+                for (i, pos) in frame.iter_mut().enumerate() {
+                    let angle = i as f64 / SWARM_SIZE as f64 * std::f64::consts::TAU;
+                    let r = 0.1 + speed * t as f64;
+                    *pos = (r * angle.cos(), r * angle.sin());
+                }
+                frame
+            })
+            .collect();
+    }
 
-        assert_eq!(vals.len(), 2 * 15);
+    #[test]
+    fn real_metric_cache_follows_the_positions() {
+        let path = std::env::temp_dir().join(format!("real-metric-cache-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let normalization = Normalization::min_max([0.0; 9], [1.0; 9], "test");
+        let metric = |pos: &[SwarmPos]| {
+            get_real_metric(pos, 0.1, 0.5, MetricVersion::V2, &normalization, Some(path))
+        };
+        let head = || {
+            let content = std::fs::read_to_string(path).unwrap();
+            content.split_once("\n").unwrap().0.to_string()
+        };
 
-        let mut data = SwarmPos::default();
-        for (i, &val) in vals.iter().enumerate() {
-            if i % 2 == 0 {
-                data[i / 2].0 = val;
-            } else {
-                data[(i - 1) / 2].1 = val;
-            }
-        }
-        bot_pos.push(data)
+        let pos = spreading(10, 0.01);
+        let written = metric(&pos);
+        let key = head();
+        assert_eq!(metric(&pos), written);
+        assert_eq!(head(), key);
+
+        // other raw positions of the same length are a cache miss and recomputed
+        let mut moved = pos.clone();
+        moved[5][3].0 += 0.2;
+        let recomputed = metric(&moved);
+        let moved_key = head();
+        std::fs::remove_file(path).unwrap();
+
+        assert_ne!(moved_key, key);
+        assert_ne!(recomputed, written);
+        assert_eq!(
+            recomputed,
+            normalization.norm_all(&to_metic(&moved, 0.1, 0.5, MetricVersion::V2))
+        );
     }
 
-    assert!(bot_pos.len() >= experiment_len);
-    if bot_pos.len() >= experiment_len {
-        let delta = bot_pos.len() - experiment_len;
+    #[test]
+    fn verify_reference_reports_the_largest_differences() {
+        let eval = Evaluator::for_tests(&[spreading(10, 0.01)], 10);
+        let mut reference = eval.real[0].metric.clone();
+        assert_eq!(eval.verify_reference("run0", &reference).unwrap(), [0.0; 9]);
 
-        let mut temp = bot_pos.iter();
-        if delta % 2 == 1 {
-            assert_ne!(temp.next(), None);
-        }
+        reference[2][4] += 0.25;
+        reference[7][4] -= 0.5;
+        reference[3][0] = f64::NAN;
+        let max_diff = eval.verify_reference("run0", &reference).unwrap();
+        assert_eq!(max_diff[4], 0.5);
+        assert_eq!(max_diff[0], f64::INFINITY);
+        assert_eq!(max_diff[1], 0.0);
 
-        for _ in 0..(delta / 2) {
-            assert_ne!(temp.next(), None);
-        }
+        assert!(eval.verify_reference("run1", &reference).is_err());
+        assert!(eval.verify_reference("run0", &reference[1..]).is_err());
+    }
 
-        let mut temp = temp.map(|x| x.to_owned()).collect::<Vec<SwarmPos>>();
-        for _ in 0..(delta / 2) {
-            assert_ne!(temp.pop(), None);
-        }
-        bot_pos = temp;
+    #[test]
+    fn simulation_that_ended_early_is_infinitely_far() {
+        let eval = Evaluator::for_tests(&[spreading(12, 0.01)], 10);
+        let evaluation = eval.eval_pos(&spreading(9, 0.01));
+        assert_eq!(evaluation.metric, [f64::INFINITY; 9]);
+        assert!(!evaluation.is_finite());
+        assert!(eval.eval_pos(&spreading(10, 0.01)).is_finite());
+        assert!(eval
+            .distance_curves(&spreading(9, 0.01), CurveOptions::default())
+            .is_err());
     }
-    assert_eq!(bot_pos.len(), experiment_len);
 
-    return bot_pos;
+    #[test]
+    fn shipped_reference_matches_the_builtin_real_run() {
+        let [min, max] = get_metics_normalization();
+        let normalization = Normalization::min_max(min, max, "builtin");
+        let real_metric = normalization.norm_all(&to_metic(
+            &get_real_bot_data(1200),
+            0.01,
+            0.01,
+            MetricVersion::V1,
+        ));
+        let reference = parse_metrics_csv(include_str!("real_norm_metrics.csv")).unwrap();
+        assert_eq!(reference, real_metric);
+    }
 }
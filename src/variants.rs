@@ -0,0 +1,466 @@
+use crate::{
+    fidelity::Fidelity,
+    json::Json,
+    metrics::METRIC_NAMES,
+    scenario::{vector, Scenario, ROBOT_ENTITIES},
+    utilities::{mean, std_dev, Evaluator},
+    xml::XmlElement,
+    SwarmMetric, SWARM_SIZE,
+};
+use std::path::{Path, PathBuf};
+
+/// One change to a base scenario.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Variation {
+    /// quantity of the distributed robots, only `SWARM_SIZE` is accepted because the real runs
+    /// have no other number of robots to compare with
+    Robots(usize),
+    /// scales the arena and the positions and lengths of its walls
+    ArenaScale(f64),
+    /// uniform initial positions of the robots within the rectangle `min` to `max`
+    Distribution { min: (f64, f64), max: (f64, f64) },
+    /// experiment length in seconds
+    Length(f64),
+    /// moves the light with the id to `(x, y)`, its height is kept
+    Light { id: String, position: (f64, f64) },
+}
+
+/// Named set of variations, evaluated as one concrete scenario.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub name: String,
+    pub variations: Vec<Variation>,
+}
+
+/// Scores of one controller in one variant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantScores {
+    /// ticks compared with the real runs, shorter variants are scored on a prefix
+    pub ticks: usize,
+    /// mean metric distance over the seeds that finished
+    pub metric: SwarmMetric,
+    /// one cost per seed that finished
+    pub costs: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantResult {
+    pub name: String,
+    pub path: PathBuf,
+    /// the reason if the variant could not be evaluated
+    pub scores: Result<VariantScores, String>,
+}
+
+impl Variation {
+    /// parses one `key=value` pair, e.g. `robots=20`, `arena=1.5`, `distribution=-0.5,-0.5,0.5,0.5`,
+    /// `length=60` or `light.<id>=0,1.2`
+    pub fn parse(pair: &str) -> Option<Self> {
+        let (key, value) = pair.trim().split_once("=")?;
+        let vals = value
+            .split(",")
+            .map(|x| x.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .ok()?;
+
+        return match (key.trim(), vals.as_slice()) {
+            ("robots", _) => Some(Variation::Robots(value.trim().parse().ok()?)),
+            ("arena", [scale]) if *scale > 0.0 => Some(Variation::ArenaScale(*scale)),
+            ("distribution", [x0, y0, x1, y1]) if x0 < x1 && y0 < y1 => {
+                Some(Variation::Distribution {
+                    min: (*x0, *y0),
+                    max: (*x1, *y1),
+                })
+            }
+            ("length", [length]) if *length > 0.0 => Some(Variation::Length(*length)),
+            (key, [x, y]) => Some(Variation::Light {
+                id: key.strip_prefix("light.")?.to_string(),
+                position: (*x, *y),
+            }),
+            _ => None,
+        };
+    }
+
+    pub fn apply(&self, root: &mut XmlElement) -> Result<(), String> {
+        match self {
+            Variation::Robots(quantity) => {
+                robot_entity(root)?.set_attribute("quantity", &format!("{quantity}"));
+            }
+            Variation::ArenaScale(scale) => {
+                let arena = root.child_mut("arena").ok_or("missing arena")?;
+                let (x, y, z) = vector(arena, "size")?;
+                arena.set_attribute("size", &format_vector((x * scale, y * scale, z)));
+                for wall in arena.children.iter_mut().filter(|x| x.name == "box") {
+                    let (x, y, z) = vector(wall, "size")?;
+                    // only the length of a wall grows, not its thickness
+                    wall.set_attribute("size", &format_vector((x * scale, y, z)));
+                    let body = wall.child_mut("body").ok_or("box without body")?;
+                    let (x, y, z) = vector(body, "position")?;
+                    body.set_attribute("position", &format_vector((x * scale, y * scale, z)));
+                }
+            }
+            Variation::Distribution { min, max } => {
+                let distribute = robot_distribute(root)?;
+                let position = distribute
+                    .child_mut("position")
+                    .ok_or("distribute without position")?;
+                position.attributes = vec![
+                    ("method".to_string(), "uniform".to_string()),
+                    ("min".to_string(), format_vector((min.0, min.1, 0.0))),
+                    ("max".to_string(), format_vector((max.0, max.1, 0.0))),
+                ];
+            }
+            Variation::Length(length) => {
+                root.child_mut("framework")
+                    .and_then(|x| x.child_mut("experiment"))
+                    .ok_or("missing framework/experiment")?
+                    .set_attribute("length", &format!("{length}"));
+            }
+            Variation::Light { id, position } => {
+                let arena = root.child_mut("arena").ok_or("missing arena")?;
+                let light = arena
+                    .children
+                    .iter_mut()
+                    .find(|x| x.name == "light" && x.attribute("id") == Some(id))
+                    .ok_or(format!("no light {id}"))?;
+                let (_, _, z) = vector(light, "position")?;
+                light.set_attribute("position", &format_vector((position.0, position.1, z)));
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl Variant {
+    /// one variant per line, `<name>: <key>=<value> ...`, empty lines and lines starting with `#`
+    /// are skipped
+    pub fn parse_all(content: &str) -> Result<Vec<Self>, String> {
+        let mut variants: Vec<Variant> = Vec::new();
+        for line in content.lines().map(|x| x.trim()) {
+            if line.is_empty() || line.starts_with("#") {
+                continue;
+            }
+            let (name, variations) = line
+                .split_once(":")
+                .ok_or(format!("variant without name: {line}"))?;
+            let name = name.trim();
+            if name.is_empty() || name.contains(['/', '\\']) {
+                return Err(format!("invalid variant name {name}"));
+            }
+            if name == "base" || variants.iter().any(|x| x.name == name) {
+                return Err(format!("duplicate variant {name}"));
+            }
+            let variations = variations
+                .split_whitespace()
+                .map(|x| Variation::parse(x).ok_or(format!("invalid variation {x} of {name}")))
+                .collect::<Result<Vec<Variation>, String>>()?;
+            if let Some(Variation::Robots(quantity)) = variations
+                .iter()
+                .find(|x| matches!(x, Variation::Robots(quantity) if *quantity != SWARM_SIZE))
+            {
+                return Err(format!(
+                    "robots={quantity} of {name}: the real runs have {SWARM_SIZE} robots, other counts cannot be compared"
+                ));
+            }
+            variants.push(Variant {
+                name: name.to_string(),
+                variations,
+            });
+        }
+        return Ok(variants);
+    }
+
+    pub fn apply(&self, base: &XmlElement) -> Result<XmlElement, String> {
+        let mut root = base.clone();
+        for variation in &self.variations {
+            variation
+                .apply(&mut root)
+                .map_err(|e| format!("{}: {e}", self.name))?;
+        }
+        return Ok(root);
+    }
+}
+
+/// writes one scenario per variant into `dir` and evaluates the controller in each of them on the
+/// same seeds, the unchanged base scenario is evaluated as the variant `base`
+pub fn eval_variants(
+    eval: &Evaluator,
+    base: &Path,
+    variants: &[Variant],
+    dir: &Path,
+    controller_cmd: Vec<String>,
+    seeds: &[i32],
+) -> Result<Vec<VariantResult>, String> {
+    let content = std::fs::read_to_string(base).map_err(|e| format!("{}: {e}", base.display()))?;
+    let base = XmlElement::parse(&content).map_err(|e| format!("{}: {e}", base.display()))?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+
+    let all = std::iter::once(Variant {
+        name: "base".to_string(),
+        variations: Vec::new(),
+    })
+    .chain(variants.iter().cloned());
+
+    let mut results = Vec::new();
+    for variant in all {
+        let path = dir.join(format!("{}.argos", variant.name));
+        let root = variant.apply(&base)?;
+        std::fs::write(&path, format!("<?xml version=\"1.0\" ?>\n{root}"))
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+
+        let scores = eval_variant(eval, &path, controller_cmd.clone(), seeds);
+        results.push(VariantResult {
+            name: variant.name,
+            path,
+            scores,
+        });
+    }
+    return Ok(results);
+}
+
+fn eval_variant(
+    eval: &Evaluator,
+    path: &Path,
+    controller_cmd: Vec<String>,
+    seeds: &[i32],
+) -> Result<VariantScores, String> {
+    let scenario = Scenario::load(&path.to_string_lossy())?;
+    let ticks = scenario
+        .experiment_len()
+        .map_or(eval.experiment_len, |len| {
+            usize::min(len, eval.experiment_len)
+        });
+    // the real runs have no other number of robots to compare with
+    scenario.validate(ticks, eval.sim_tick_rate, SWARM_SIZE)?;
+
+    let eval = Evaluator {
+        scenario: path.to_string_lossy().to_string(),
+        argos: Some(scenario),
+        // stored trajectories are keyed by controller and seed only
+        trajectory_store: None,
+        cost_bound: None,
+        ..eval.clone()
+    };
+    let eval = if ticks < eval.experiment_len {
+        eval.at_fidelity(Fidelity {
+            ticks,
+            seeds: seeds.len(),
+        })?
+    } else {
+        eval
+    };
+
+    let data = eval
+        .eval_seeds(controller_cmd, seeds.to_vec())
+        .into_iter()
+        .map(|x| x.1)
+        .filter(|x| x.is_finite())
+        .collect::<Vec<_>>();
+    if data.is_empty() {
+        return Err("no simulation finished".to_string());
+    }
+
+    let mut metric = SwarmMetric::default();
+    for evaluation in &data {
+        for (sum, val) in metric.iter_mut().zip(evaluation.metric) {
+            *sum += val / data.len() as f64;
+        }
+    }
+    return Ok(VariantScores {
+        ticks,
+        metric,
+        costs: data.iter().map(|x| eval.cost(x)).collect(),
+    });
+}
+
+/// one row per variant with its mean cost, spread and metric distances, then the mean over the
+/// evaluated variants and the worst one
+pub fn variants_table(results: &[VariantResult]) -> String {
+    let mut out = format!(
+        "{:<16}\t{:>6}\t{:>8}\t{:>8}",
+        "variant", "ticks", "cost", "std"
+    );
+    for name in METRIC_NAMES {
+        out.push_str(&format!("\t{name}"));
+    }
+    out.push('\n');
+
+    for result in results {
+        match &result.scores {
+            Ok(scores) => {
+                out.push_str(&format!(
+                    "{:<16}\t{:>6}\t{:>8.4}\t{:>8.4}",
+                    result.name,
+                    scores.ticks,
+                    mean(&scores.costs),
+                    std_dev(&scores.costs)
+                ));
+                for val in scores.metric {
+                    out.push_str(&format!("\t{val:.4}"));
+                }
+                out.push('\n');
+            }
+            Err(e) => out.push_str(&format!("{:<16}\tskipped: {e}\n", result.name)),
+        }
+    }
+
+    let costs = results
+        .iter()
+        .filter_map(|x| {
+            x.scores
+                .as_ref()
+                .ok()
+                .map(|s| (x.name.as_str(), mean(&s.costs)))
+        })
+        .collect::<Vec<(&str, f64)>>();
+    if let Some(worst) = costs.iter().max_by(|a, b| a.1.total_cmp(&b.1)) {
+        let vals = costs.iter().map(|x| x.1).collect::<Vec<f64>>();
+        out.push_str(&format!(
+            "\noverall over {} variants: mean cost {:.4}, std {:.4}, worst {} with {:.4}\n",
+            vals.len(),
+            mean(&vals),
+            std_dev(&vals),
+            worst.0,
+            worst.1
+        ));
+    }
+    return out;
+}
+
+pub fn variants_json(results: &[VariantResult]) -> Json {
+    let variants = results
+        .iter()
+        .map(|result| {
+            let mut fields = vec![
+                ("name".to_string(), Json::from(result.name.as_str())),
+                (
+                    "scenario".to_string(),
+                    Json::from(result.path.to_string_lossy().to_string()),
+                ),
+            ];
+            match &result.scores {
+                Ok(scores) => {
+                    let metrics = METRIC_NAMES
+                        .iter()
+                        .zip(scores.metric)
+                        .map(|(name, val)| (name.to_string(), Json::from(val)))
+                        .collect();
+                    fields.push(("ticks".to_string(), Json::from(scores.ticks as f64)));
+                    fields.push(("cost".to_string(), Json::from(mean(&scores.costs))));
+                    fields.push(("costs".to_string(), Json::from(scores.costs.as_slice())));
+                    fields.push(("metrics".to_string(), Json::Object(metrics)));
+                }
+                Err(e) => fields.push(("skipped".to_string(), Json::from(e.as_str()))),
+            }
+            Json::Object(fields)
+        })
+        .collect();
+
+    let costs = results
+        .iter()
+        .filter_map(|x| x.scores.as_ref().ok().map(|s| mean(&s.costs)))
+        .collect::<Vec<f64>>();
+    let overall = if costs.is_empty() {
+        Json::Null
+    } else {
+        Json::Object(vec![
+            ("mean".to_string(), Json::from(mean(&costs))),
+            ("std".to_string(), Json::from(std_dev(&costs))),
+            (
+                "worst".to_string(),
+                Json::from(costs.iter().copied().fold(f64::NEG_INFINITY, f64::max)),
+            ),
+        ])
+    };
+    return Json::Object(vec![
+        ("variants".to_string(), Json::Array(variants)),
+        ("overall".to_string(), overall),
+    ]);
+}
+
+/// the `entity` of the `distribute` block that places the robots
+fn robot_entity(root: &mut XmlElement) -> Result<&mut XmlElement, String> {
+    return robot_distribute(root)?
+        .child_mut("entity")
+        .ok_or("distribute without entity".to_string());
+}
+
+fn robot_distribute(root: &mut XmlElement) -> Result<&mut XmlElement, String> {
+    let arena = root.child_mut("arena").ok_or("missing arena")?;
+    let mut distributes = arena.children.iter_mut().filter(|x| {
+        x.name == "distribute"
+            && x.child("entity").is_some_and(|entity| {
+                entity
+                    .children
+                    .iter()
+                    .any(|x| ROBOT_ENTITIES.contains(&x.name.as_str()))
+            })
+    });
+    let distribute = distributes.next().ok_or("no distributed robots")?;
+    if distributes.next().is_some() {
+        return Err("robots are distributed in more than one block".to_string());
+    }
+    return Ok(distribute);
+}
+
+fn format_vector((x, y, z): (f64, f64, f64)) -> String {
+    return format!("{x},{y},{z}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_parse() {
+        let variants = Variant::parse_all(
+            "# comment\n\nwide: arena=1.5 light.l0=0,1.2\nshort: length=60 robots=15\n",
+        )
+        .unwrap();
+        assert_eq!(variants.len(), 2);
+        assert_eq!(
+            variants[0].variations,
+            [
+                Variation::ArenaScale(1.5),
+                Variation::Light {
+                    id: "l0".to_string(),
+                    position: (0.0, 1.2)
+                }
+            ]
+        );
+        assert_eq!(
+            variants[1].variations,
+            [Variation::Length(60.0), Variation::Robots(SWARM_SIZE)]
+        );
+    }
+
+    #[test]
+    fn other_robot_counts_are_rejected() {
+        let err = Variant::parse_all("many: robots=20").unwrap_err();
+        assert!(err.contains("robots=20 of many"), "{err}");
+    }
+
+    #[test]
+    fn invalid_variants_are_rejected() {
+        assert!(Variant::parse_all("base: arena=2").is_err());
+        assert!(Variant::parse_all("a: arena=2\na: arena=3").is_err());
+        assert!(Variant::parse_all("a: arena=-1").is_err());
+        assert!(Variant::parse_all("a/b: arena=2").is_err());
+        assert!(Variant::parse_all("arena=2").is_err());
+    }
+
+    #[test]
+    fn length_is_set_in_the_experiment() {
+        let base = XmlElement::parse(
+            "<argos-configuration><framework><experiment length=\"120\" ticks_per_second=\"10\"/></framework></argos-configuration>",
+        )
+        .unwrap();
+        let mut changed = base.clone();
+        Variation::Length(60.0).apply(&mut changed).unwrap();
+        let experiment = changed
+            .child("framework")
+            .and_then(|x| x.child("experiment"))
+            .unwrap();
+        assert_eq!(experiment.attribute("length"), Some("60"));
+        assert_eq!(experiment.attribute("ticks_per_second"), Some("10"));
+    }
+}
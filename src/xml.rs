@@ -0,0 +1,297 @@
+/// Element of a minimal XML tree, enough to read ARGoS scenario files. Text content, comments,
+/// processing instructions and doctypes are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlElement {
+    pub name: String,
+    /// attributes keep their order
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        return self
+            .attributes
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str());
+    }
+
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        return self.children.iter().find(|x| x.name == name);
+    }
+
+    /// replaces the value of the attribute or appends it
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        match self.attributes.iter_mut().find(|(k, _)| k == name) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.attributes.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut XmlElement> {
+        return self.children.iter_mut().find(|x| x.name == name);
+    }
+
+    /// this element and all elements below it, depth first
+    pub fn descendants(&self) -> Vec<&XmlElement> {
+        let mut result = vec![self];
+        for child in &self.children {
+            result.extend(child.descendants());
+        }
+        return result;
+    }
+
+    /// the root element of a document
+    pub fn parse(content: &str) -> Result<XmlElement, String> {
+        let mut parser = Parser {
+            chars: content.chars().collect(),
+            pos: 0,
+        };
+        parser.skip_misc()?;
+        let root = parser.element()?;
+        parser.skip_misc()?;
+        if parser.pos != parser.chars.len() {
+            return Err(format!("unexpected trailing data at {}", parser.pos));
+        }
+        return Ok(root);
+    }
+}
+
+impl std::fmt::Display for XmlElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl XmlElement {
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        write!(f, "{:indent$}<{}", "", self.name, indent = 2 * depth)?;
+        for (key, val) in &self.attributes {
+            write!(f, " {key}=\"{}\"", escape(val))?;
+        }
+        if self.children.is_empty() {
+            return writeln!(f, "/>");
+        }
+        writeln!(f, ">")?;
+        for child in &self.children {
+            child.write(f, depth + 1)?;
+        }
+        writeln!(f, "{:indent$}</{}>", "", self.name, indent = 2 * depth)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn starts_with(&self, text: &str) -> bool {
+        return text
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    /// moves behind the next `end`
+    fn skip_until(&mut self, end: &str) -> Result<(), String> {
+        while self.pos < self.chars.len() {
+            if self.starts_with(end) {
+                self.pos += end.chars().count();
+                return Ok(());
+            }
+            self.pos += 1;
+        }
+        return Err(format!("missing '{end}'"));
+    }
+
+    /// whitespace, comments, processing instructions and doctypes
+    fn skip_misc(&mut self) -> Result<(), String> {
+        loop {
+            self.skip_whitespace();
+            if self.starts_with("<!--") {
+                self.skip_until("-->")?;
+            } else if self.starts_with("<?") {
+                self.skip_until("?>")?;
+            } else if self.starts_with("<!") && !self.starts_with("<![CDATA[") {
+                self.skip_until(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        while self.pos < self.chars.len()
+            && (self.chars[self.pos].is_alphanumeric()
+                || matches!(self.chars[self.pos], '_' | '-' | '.' | ':'))
+        {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(format!("expected a name at {start}"));
+        }
+        return Ok(self.chars[start..self.pos].iter().collect());
+    }
+
+    fn element(&mut self) -> Result<XmlElement, String> {
+        if self.chars.get(self.pos) != Some(&'<') {
+            return Err(format!("expected '<' at {}", self.pos));
+        }
+        self.pos += 1;
+        let name = self.name()?;
+
+        let mut attributes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.starts_with("/>") {
+                self.pos += 2;
+                return Ok(XmlElement {
+                    name,
+                    attributes,
+                    children: Vec::new(),
+                });
+            }
+            if self.starts_with(">") {
+                self.pos += 1;
+                break;
+            }
+            let key = self.name()?;
+            self.skip_whitespace();
+            if self.chars.get(self.pos) != Some(&'=') {
+                return Err(format!(
+                    "expected '=' after attribute {key} at {}",
+                    self.pos
+                ));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            attributes.push((key, self.quoted()?));
+        }
+
+        let mut children = Vec::new();
+        loop {
+            if self.pos >= self.chars.len() {
+                return Err(format!("unterminated element {name}"));
+            }
+            if self.starts_with("</") {
+                self.pos += 2;
+                let end = self.name()?;
+                if end != name {
+                    return Err(format!("element {name} is closed by {end}"));
+                }
+                self.skip_whitespace();
+                if self.chars.get(self.pos) != Some(&'>') {
+                    return Err(format!("expected '>' at {}", self.pos));
+                }
+                self.pos += 1;
+                return Ok(XmlElement {
+                    name,
+                    attributes,
+                    children,
+                });
+            }
+            if self.starts_with("<![CDATA[") {
+                self.skip_until("]]>")?;
+            } else if self.starts_with("<!") || self.starts_with("<?") {
+                self.skip_misc()?;
+            } else if self.starts_with("<") {
+                children.push(self.element()?);
+            } else {
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, String> {
+        let quote = match self.chars.get(self.pos) {
+            Some(&c) if c == '"' || c == '\'' => c,
+            _ => return Err(format!("expected a quoted value at {}", self.pos)),
+        };
+        self.pos += 1;
+        let start = self.pos;
+        while self.pos < self.chars.len() && self.chars[self.pos] != quote {
+            self.pos += 1;
+        }
+        if self.pos >= self.chars.len() {
+            return Err("unterminated attribute value".to_string());
+        }
+        let raw = self.chars[start..self.pos].iter().collect::<String>();
+        self.pos += 1;
+        return Ok(unescape(&raw));
+    }
+}
+
+fn unescape(text: &str) -> String {
+    return text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+}
+
+fn escape(text: &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_print_round_trip() {
+        let content = "<?xml version=\"1.0\"?>\n<!-- comment -->\n<root a='1' b=\"x &amp; &lt;y&gt;\">\n  text <![CDATA[<ignored/>]]>\n  <child id=\"c0\"/>\n  <!-- inner -->\n  <child id=\"c1\"><leaf/></child>\n</root>\n";
+        let root = XmlElement::parse(content).unwrap();
+        assert_eq!(root.name, "root");
+        assert_eq!(root.attribute("a"), Some("1"));
+        assert_eq!(root.attribute("b"), Some("x & <y>"));
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.child("child").unwrap().attribute("id"), Some("c0"));
+        assert_eq!(root.descendants().len(), 4);
+
+        let printed = root.to_string();
+        assert_eq!(
+            printed,
+            "<root a=\"1\" b=\"x &amp; &lt;y&gt;\">\n  <child id=\"c0\"/>\n  <child id=\"c1\">\n    <leaf/>\n  </child>\n</root>\n"
+        );
+        assert_eq!(XmlElement::parse(&printed).unwrap(), root);
+    }
+
+    #[test]
+    fn attributes_are_replaced_or_appended() {
+        let mut root = XmlElement::parse("<a x=\"1\"><b/></a>").unwrap();
+        root.set_attribute("x", "2");
+        root.set_attribute("y", "\"3\"");
+        root.child_mut("b").unwrap().set_attribute("z", "4");
+        let printed = root.to_string();
+        assert_eq!(
+            printed,
+            "<a x=\"2\" y=\"&quot;3&quot;\">\n  <b z=\"4\"/>\n</a>\n"
+        );
+        assert_eq!(XmlElement::parse(&printed).unwrap(), root);
+    }
+
+    #[test]
+    fn malformed_documents() {
+        assert!(XmlElement::parse("").is_err());
+        assert!(XmlElement::parse("<a>").is_err());
+        assert!(XmlElement::parse("<a></b>").is_err());
+        assert!(XmlElement::parse("<a x=1/>").is_err());
+        assert!(XmlElement::parse("<a x=\"1/>").is_err());
+        assert!(XmlElement::parse("<a/><b/>").is_err());
+        assert!(XmlElement::parse("<a><!-- open</a>").is_err());
+    }
+}